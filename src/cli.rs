@@ -1,4 +1,12 @@
-use clap::{ArgAction, Parser};
+use std::path::PathBuf;
+
+use clap::{
+    ArgAction,
+    Parser,
+    Subcommand,
+    builder::{PossibleValuesParser, TypedValueParser},
+};
+use mixxx_library_exporter::TargetFilesystem;
 
 #[derive(Parser, Debug)]
 #[command(
@@ -12,7 +20,119 @@ pub struct CliArguments {
     #[arg(short, long, action = ArgAction::Count)]
     pub verbose: u8,
 
-    /// Set this to create a `mixxx_library.json` file in the target directory
+    /// Set this to create a `mixxx_library.json` file in the target directory.
+    /// This ignores all configured profiles.
     #[arg(short, long)]
     pub json_export: bool,
+
+    /// Read the library from this file instead of the configured `mixxx_db`,
+    /// e.g. a previously exported `mixxx_library.json`. Use `-` to read json from stdin.
+    #[arg(short, long)]
+    pub input: Option<String>,
+
+    /// The format of `--input`, e.g. `json`.
+    /// Detected by the file extension by default.
+    #[arg(long)]
+    pub input_format: Option<String>,
+
+    /// The amount of threads that're used to process tracks.
+    /// Defaults to the amount of CPU cores.
+    #[arg(long)]
+    pub jobs: Option<usize>,
+
+    #[command(subcommand)]
+    pub cmd: Option<SubCommand>,
+}
+
+#[derive(Subcommand, Debug)]
+pub enum SubCommand {
+    /// Write hotcues, loops, colours, BPM and key into the audio files' tags.
+    ///
+    /// Cues and beatgrids are saved as Serato tags, which are understood by lots of DJ software
+    /// and hardware.
+    /// Only MP3 files are supported for now. Traktor's PRIV frames aren't written.
+    TagWriter {
+        /// Don't touch any files, only report which files would be modified.
+        #[arg(short, long)]
+        dry_run: bool,
+
+        /// Create a `.bak` copy of each file before it's modified.
+        #[arg(short, long)]
+        backup: bool,
+    },
+
+    /// Compare two libraries and print what has changed from `old` to `new`.
+    ///
+    /// Each library is either a Mixxx database or a json export,
+    /// which is detected by the file extension.
+    Diff {
+        /// The previous state of the library.
+        old: String,
+        /// The current state of the library.
+        new: String,
+
+        /// Print the differences as JSON instead of text.
+        #[arg(long)]
+        json: bool,
+    },
+
+    /// Print statistics of the library and tracks that might need attention,
+    /// e.g. tracks without beatgrid or key.
+    Stats {
+        /// Print the statistics as JSON instead of text.
+        #[arg(long)]
+        json: bool,
+
+        /// Also write a self-contained `mixxx_library_stats.html` into the target directory.
+        #[arg(long)]
+        html: bool,
+    },
+
+    /// Check that all music files exist and that their exported paths are valid.
+    ///
+    /// The paths of each profile are checked against the `target_filesystem` of the profile.
+    /// Fails if any problem has been found.
+    Validate {
+        /// The file system of the target library root, which overrides the configured one.
+        #[arg(long, value_parser = filesystem_parser())]
+        filesystem: Option<TargetFilesystem>,
+
+        /// Print the problems as JSON instead of text.
+        #[arg(long)]
+        json: bool,
+    },
+
+    /// Find tracks that exist multiple times, e.g. as mp3 and flac.
+    ///
+    /// Tracks are the same song, if their artist and title match and their durations are similar.
+    /// The copy with the most cues, plays and playlists is listed first.
+    Duplicates {
+        /// The maximum difference between the durations of two copies in seconds.
+        #[arg(long, default_value_t = 2.0)]
+        tolerance: f64,
+
+        /// Also find copies with identical audio, but different tags. This reads every file.
+        #[arg(long)]
+        content_hash: bool,
+
+        /// Write all copies into this M3U playlist, which can be imported into Mixxx.
+        #[arg(long)]
+        playlist: Option<PathBuf>,
+
+        /// Print the duplicates as JSON instead of text.
+        #[arg(long)]
+        json: bool,
+    },
+
+    /// List all import and export formats including the options they accept in profiles.
+    Formats,
+}
+
+/// File systems are named like in the config file, e.g. `fat32`.
+fn filesystem_parser() -> impl TypedValueParser<Value = TargetFilesystem> {
+    PossibleValuesParser::new(["windows", "fat32", "exfat"]).map(|value| match value.as_str() {
+        "windows" => TargetFilesystem::Windows,
+        "fat32" => TargetFilesystem::Fat32,
+        _ => TargetFilesystem::Exfat,
+    })
 }
@@ -0,0 +1,142 @@
+use anyhow::{Context, Result, bail};
+use schemars::JsonSchema;
+use serde_derive::{Deserialize, Serialize};
+
+/// Mixxx uses this value for cues without a colour.
+const NO_CUE_COLOR: i64 = 0xFFFF_0000;
+
+/// A plain 24-bit RGB colour, as used by Mixxx for track and cue colours.
+///
+/// Mixxx saves colours as integers in the form of `0xRRGGBB`.
+#[derive(Serialize, Deserialize, JsonSchema, Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[serde(transparent)]
+pub struct RgbColor(pub u32);
+
+impl RgbColor {
+    pub fn red(self) -> u8 {
+        ((self.0 >> 16) & 0xFF) as u8
+    }
+
+    pub fn green(self) -> u8 {
+        ((self.0 >> 8) & 0xFF) as u8
+    }
+
+    pub fn blue(self) -> u8 {
+        (self.0 & 0xFF) as u8
+    }
+
+    /// The colour of a Mixxx cue. Returns `None` for cues without a colour.
+    ///
+    /// All exporters use this, so they agree on which cues have a colour.
+    pub fn from_cue_color(color: i64) -> Option<Self> {
+        if color == NO_CUE_COLOR || !(0..=0xFFFFFF).contains(&color) {
+            return None;
+        }
+
+        Some(RgbColor(color as u32))
+    }
+
+    /// Parse a colour from a hex string such as `#FF0000`, `0xFF0000` or `FF0000`.
+    pub fn from_hex(hex: &str) -> Result<Self> {
+        let trimmed = hex.trim();
+        let digits = trimmed
+            .strip_prefix('#')
+            .or_else(|| trimmed.strip_prefix("0x"))
+            .or_else(|| trimmed.strip_prefix("0X"))
+            .unwrap_or(trimmed);
+
+        if digits.len() != 6 || !digits.chars().all(|digit| digit.is_ascii_hexdigit()) {
+            bail!("Colour '{hex}' isn't a six digit hex colour");
+        }
+
+        let value =
+            u32::from_str_radix(digits, 16).context(format!("Failed to parse colour '{hex}'"))?;
+
+        Ok(RgbColor(value))
+    }
+
+    /// Format the colour the way rekordbox expects it, i.e. `0xRRGGBB`.
+    pub fn to_hex(self) -> String {
+        format!("0x{:06X}", self.0)
+    }
+
+    /// The squared euclidean distance between two colours in the RGB space.
+    pub fn distance(self, other: RgbColor) -> u32 {
+        let red = self.red().abs_diff(other.red()) as u32;
+        let green = self.green().abs_diff(other.green()) as u32;
+        let blue = self.blue().abs_diff(other.blue()) as u32;
+
+        red * red + green * green + blue * blue
+    }
+
+    /// Get the colour of the palette that's closest to this colour.
+    /// Returns `None` if the palette is empty.
+    pub fn nearest(self, palette: &[RgbColor]) -> Option<RgbColor> {
+        palette
+            .iter()
+            .min_by_key(|color| self.distance(**color))
+            .copied()
+    }
+}
+
+/// Parse a list of hex strings into a colour palette.
+pub fn parse_palette(colors: &[String]) -> Result<Vec<RgbColor>> {
+    colors
+        .iter()
+        .map(|color| RgbColor::from_hex(color))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use pretty_assertions::assert_eq;
+    use rstest::rstest;
+
+    use super::*;
+
+    #[rstest]
+    #[case::red(0xC50A08, Some(RgbColor(0xC50A08)))]
+    #[case::black(0, Some(RgbColor(0)))]
+    #[case::no_color(NO_CUE_COLOR, None)]
+    #[case::negative(-1, None)]
+    fn cue_colors(#[case] color: i64, #[case] expected: Option<RgbColor>) {
+        assert_eq!(RgbColor::from_cue_color(color), expected);
+    }
+
+    #[rstest]
+    #[case::hash("#FF8000", RgbColor(0xFF8000))]
+    #[case::prefix("0xff8000", RgbColor(0xFF8000))]
+    #[case::upper_prefix("0XFF8000", RgbColor(0xFF8000))]
+    #[case::plain(" ff8000 ", RgbColor(0xFF8000))]
+    fn parse_hex(#[case] hex: &str, #[case] expected: RgbColor) {
+        assert_eq!(RgbColor::from_hex(hex).unwrap(), expected);
+    }
+
+    #[rstest]
+    #[case::empty("")]
+    #[case::short("#FFF")]
+    #[case::long("#FF00000")]
+    #[case::not_hex("#GG0000")]
+    #[case::sign("+FFFFF")]
+    #[case::double_prefix("#0xFF00")]
+    fn reject_invalid_hex(#[case] hex: &str) {
+        assert!(RgbColor::from_hex(hex).is_err());
+    }
+
+    #[test]
+    fn nearest_exact_match() {
+        let palette = [RgbColor(0xFF0000), RgbColor(0x00FF00), RgbColor(0x0000FF)];
+        assert_eq!(
+            RgbColor(0x00FF00).nearest(&palette),
+            Some(RgbColor(0x00FF00))
+        );
+    }
+
+    #[test]
+    fn nearest_tie_prefers_first() {
+        // Both colours have a distance of 1, the first of the palette wins.
+        let palette = [RgbColor(0x010000), RgbColor(0x000100)];
+        assert_eq!(RgbColor(0).nearest(&palette), Some(RgbColor(0x010000)));
+        assert_eq!(RgbColor(0).nearest(&[]), None);
+    }
+}
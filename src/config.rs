@@ -1,16 +1,55 @@
-use std::path::{Path, PathBuf};
+use std::{
+    collections::HashSet,
+    path::{Path, PathBuf},
+};
 
-use anyhow::{Result, bail};
+use anyhow::{Context, Result, bail};
 use confique::Config as Confique;
 use log::{debug, info, warn};
+use serde_derive::Deserialize;
 use shellexpand::tilde;
 
-#[derive(Confique)]
+use crate::{
+    color::parse_palette,
+    db::DbAccess,
+    export::Profile,
+    merge::{CuePrecedence, MixxxSource},
+    sync::SyncConfig,
+    tags::merge::TagPrecedence,
+    validate::TargetFilesystem,
+};
+
+#[derive(Confique, Debug)]
 pub struct Config {
     /// The exact location of the mixxxdb.sqlite file.
-    pub mixxx_db: String,
+    /// This may be omitted, if the library is always read from another source via `--input`.
+    pub mixxx_db: Option<String>,
+    /// How the Mixxx database is accessed. It's never opened with write access.
+    /// - `read_only`: Wait for Mixxx to finish writing, if it's currently doing so.
+    /// - `immutable`: Never wait for Mixxx. The data might be inconsistent, if Mixxx writes during
+    ///   the export.
+    /// - `snapshot`: Take a consistent copy of the database and export from that copy.
+    #[config(default = "read_only")]
+    pub mixxx_db_access: DbAccess,
+    /// How long to wait for Mixxx to release its lock on the database, in milliseconds.
+    #[config(default = 5000)]
+    pub mixxx_db_busy_timeout: u64,
+    /// Multiple Mixxx databases, e.g. of several DJs, that're merged into one library.
+    /// If this is set, `mixxx_db` is ignored.
+    /// Tracks that point to the same file are merged and playlists are prefixed per database.
+    #[config(default = [])]
+    pub mixxx_sources: Vec<MixxxSource>,
+    /// Decides whose cues are kept, if multiple `mixxx_sources` contain the same file.
+    /// - `first`: The cues of the source that's listed first win.
+    /// - `most_cues`: The cues of the source with the most cues for that track win.
+    #[config(default = "first")]
+    pub cue_precedence: CuePrecedence,
     /// The directory to which the library will be exported to.
     target_directory: PathBuf,
+    /// How many previous exports are kept as timestamped backups next to the export.
+    /// Backups are named after the export, e.g. `mixxx_rekordbox_export.2024-01-02T10-00-00.xml`.
+    #[config(default = 0)]
+    pub backups: usize,
 
     /// This program only works, if all music files are located in a single directory.
     /// This directory should then also be available in the target OS
@@ -25,6 +64,78 @@ pub struct Config {
     /// This is the path to the library root in the target OS.
     /// For Windows this would look something like "C:/Users/your_user/Music/Mixxx"
     pub create_all_playlist: bool,
+
+    /// Most DJ software only supports a fixed set of track colours.
+    /// Mixxx track colours are mapped to the nearest colour of this palette on export.
+    /// The colours are expected as hex strings, e.g. `["#FF0000", "#00FF00"]`.
+    /// If this isn't set, the default palette of the respective export format is used.
+    pub track_color_palette: Option<Vec<String>>,
+
+    /// Mixxx doesn't save some metadata, such as the record label, remixer or disc number.
+    /// If this is set, these fields are read from the audio files' own tags.
+    /// This requires the music files to be reachable under their Mixxx location.
+    #[config(default = false)]
+    pub read_file_tags: bool,
+
+    /// Decides which value wins, if the Mixxx library and the file's tags disagree.
+    /// Only used if `read_file_tags` is set.
+    /// - `mixxx`: Values from Mixxx are never touched, only missing fields are added.
+    /// - `fill_empty`: File values are used, if the value is empty in Mixxx.
+    /// - `file`: File values always replace the values from Mixxx.
+    #[config(default = "mixxx")]
+    pub file_tag_precedence: TagPrecedence,
+
+    /// Export the tracks' cover art into an `artwork` folder inside the target directory of each
+    /// profile. The `json` and `public_json` exports reference these images.
+    #[config(default = false)]
+    pub export_cover_art: bool,
+
+    /// Exported cover art is shrunk to fit into a square of this many pixels.
+    #[config(default = 500)]
+    pub cover_art_size: u32,
+
+    /// The file system of the `target_library_root`: `windows`, `fat32` or `exfat`.
+    /// Exported paths are checked against its rules for names and lengths.
+    pub target_filesystem: Option<TargetFilesystem>,
+
+    /// Check that all music files exist and that their exported paths are valid before exporting.
+    /// Problems are reported, but don't stop the export.
+    #[config(default = false)]
+    pub validate_before_export: bool,
+
+    /// Copy the exported tracks into a directory before exporting, e.g. onto a USB stick.
+    /// The exported locations then point to the copied files below the `target_library_root`.
+    pub sync: Option<SyncConfig>,
+
+    /// Each profile exports the library in one format, e.g. `rekordbox` or `json`.
+    /// Run `mixxx-library-exporter formats` to list all formats and their options.
+    /// Settings that aren't set in a profile fall back to the respective settings above.
+    /// If no profile is configured, the library is exported as rekordbox XML.
+    #[config(default = [])]
+    pub profiles: Vec<ProfileConfig>,
+}
+
+/// A single export profile as it's written in the config file.
+#[derive(Deserialize, Debug, Clone)]
+#[serde(deny_unknown_fields)]
+pub struct ProfileConfig {
+    /// A unique name for this profile.
+    pub name: String,
+    /// The name of the exporter that's used by this profile.
+    pub format: String,
+    pub target_directory: Option<PathBuf>,
+    /// The name of the exported file inside the target directory.
+    pub file_name: Option<String>,
+    pub backups: Option<usize>,
+    pub source_library_root: Option<String>,
+    pub target_library_root: Option<PathBuf>,
+    pub create_all_playlist: Option<bool>,
+    pub track_color_palette: Option<Vec<String>>,
+    pub target_filesystem: Option<TargetFilesystem>,
+    pub sync: Option<SyncConfig>,
+    /// Options that're specific to the exporter.
+    #[serde(default)]
+    pub options: serde_json::Value,
 }
 
 /// Little helper which expands a given path's `~` characters to a fully qualified path.
@@ -32,10 +143,79 @@ pub fn expand_home(old_path: &Path) -> PathBuf {
     PathBuf::from(tilde(&old_path.to_string_lossy()).into_owned())
 }
 
+/// Expand the `~` of the sync directory, like it's done for the target directory.
+fn expand_sync(mut sync: SyncConfig) -> SyncConfig {
+    sync.directory = expand_home(&sync.directory);
+    sync
+}
+
 impl Config {
     pub fn target_directory(&self) -> PathBuf {
         expand_home(&self.target_directory)
     }
+
+    /// The profile that's used for `format`, if no profiles are configured.
+    /// It only consists of the global settings.
+    pub fn default_profile(&self, format: &str) -> Profile {
+        Profile {
+            name: format.to_string(),
+            format: format.to_string(),
+            target_directory: self.target_directory(),
+            file_name: None,
+            backups: self.backups,
+            source_library_root: self.source_library_root.clone(),
+            target_library_root: self.target_library_root.clone(),
+            create_all_playlist: self.create_all_playlist,
+            track_color_palette: self.track_color_palette.clone(),
+            target_filesystem: self.target_filesystem,
+            sync: self.sync.clone().map(expand_sync),
+            options: serde_json::Value::Null,
+        }
+    }
+
+    /// All configured profiles, with unset settings filled in from the global settings.
+    pub fn profiles(&self) -> Vec<Profile> {
+        if self.profiles.is_empty() {
+            return vec![self.default_profile("rekordbox")];
+        }
+
+        self.profiles
+            .iter()
+            .map(|profile| Profile {
+                name: profile.name.clone(),
+                format: profile.format.clone(),
+                target_directory: profile
+                    .target_directory
+                    .as_deref()
+                    .map(expand_home)
+                    .unwrap_or_else(|| self.target_directory()),
+                file_name: profile.file_name.clone(),
+                backups: profile.backups.unwrap_or(self.backups),
+                source_library_root: profile
+                    .source_library_root
+                    .clone()
+                    .unwrap_or_else(|| self.source_library_root.clone()),
+                target_library_root: profile
+                    .target_library_root
+                    .clone()
+                    .unwrap_or_else(|| self.target_library_root.clone()),
+                create_all_playlist: profile
+                    .create_all_playlist
+                    .unwrap_or(self.create_all_playlist),
+                track_color_palette: profile
+                    .track_color_palette
+                    .clone()
+                    .or_else(|| self.track_color_palette.clone()),
+                target_filesystem: profile.target_filesystem.or(self.target_filesystem),
+                sync: profile
+                    .sync
+                    .clone()
+                    .or_else(|| self.sync.clone())
+                    .map(expand_sync),
+                options: profile.options.clone(),
+            })
+            .collect()
+    }
 }
 
 impl Config {
@@ -94,6 +274,54 @@ impl Config {
             )
         }
 
+        // Make sure the colour palette can be parsed.
+        if let Some(colors) = &self.track_color_palette {
+            parse_palette(colors).context("Invalid track_color_palette")?;
+        }
+
+        // Make sure the playlists of all Mixxx sources can be told apart.
+        let mut sources = HashSet::new();
+        for source in &self.mixxx_sources {
+            if !sources.insert(&source.name) {
+                bail!(
+                    "There're multiple mixxx_sources with the name '{}'",
+                    source.name
+                );
+            }
+        }
+
+        // Make sure all profiles can be told apart and export into existing directories.
+        let mut names = HashSet::new();
+        for profile in self.profiles() {
+            if !names.insert(profile.name.clone()) {
+                bail!(
+                    "There're multiple profiles with the name '{}'",
+                    profile.name
+                );
+            }
+            if !profile.target_directory.exists() {
+                bail!(
+                    "Target directory of profile '{}' doesn't seem to exist: {:?}",
+                    profile.name,
+                    profile.target_directory
+                )
+            }
+            if let Some(file_name) = &profile.file_name
+                && Path::new(file_name).file_name() != Some(file_name.as_ref())
+            {
+                bail!(
+                    "file_name of profile '{}' has to be a plain file name: '{file_name}'",
+                    profile.name
+                )
+            }
+            if let Some(colors) = &profile.track_color_palette {
+                parse_palette(colors).context(format!(
+                    "Invalid track_color_palette in profile '{}'",
+                    profile.name
+                ))?;
+            }
+        }
+
         // Make sure the target_library_root actually exists.
         // if !self.target_library_root.exists() {
         //     bail!(
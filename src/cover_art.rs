@@ -0,0 +1,282 @@
+use std::{
+    fs::{create_dir_all, read},
+    path::{Path, PathBuf},
+};
+
+use anyhow::{Context, Result};
+use image::{ImageFormat, imageops::FilterType};
+use log::{debug, warn};
+use sha2::{Digest, Sha256};
+
+use crate::{
+    mixxx::library::{CoverArtType, Library, Track},
+    tags::reader::read_embedded_cover,
+};
+
+/// The directory inside the target directory into which the cover art is exported.
+pub const COVER_ART_DIRECTORY: &str = "artwork";
+
+/// Image files that're commonly placed next to audio files as cover art.
+const FOLDER_COVER_NAMES: [&str; 6] = [
+    "cover.jpg",
+    "cover.png",
+    "folder.jpg",
+    "folder.png",
+    "front.jpg",
+    "front.png",
+];
+
+/// A summary of the cover art export.
+#[derive(Debug, Default)]
+pub struct CoverArtReport {
+    /// The amount of newly written images.
+    pub exported: usize,
+    /// The amount of tracks whose image has already been exported, e.g. for a different track of
+    /// the same album or during a previous export.
+    pub deduplicated: usize,
+    /// The amount of tracks without any cover art.
+    pub missing: usize,
+    /// The ids of tracks whose cover art couldn't be exported.
+    pub failed: Vec<usize>,
+}
+
+/// Export the cover art of all tracks into the `artwork` directory inside `target_directory`.
+///
+/// Images are resized to fit into `max_size`x`max_size` pixels and are deduplicated by their
+/// content. The path of the exported image is saved in each track's [CoverArt::exported].
+///
+/// [CoverArt::exported]: crate::mixxx::library::CoverArt::exported
+pub fn export_cover_art(
+    library: &mut Library,
+    target_directory: &Path,
+    max_size: u32,
+) -> Result<CoverArtReport> {
+    let cover_directory = target_directory.join(COVER_ART_DIRECTORY);
+    create_dir_all(&cover_directory).context(format!(
+        "Failed to create cover art directory at {cover_directory:?}"
+    ))?;
+
+    let mut report = CoverArtReport::default();
+    for track in library.tracks.values_mut() {
+        let image = match read_cover(track) {
+            Ok(Some(image)) => image,
+            Ok(None) => {
+                report.missing += 1;
+                continue;
+            }
+            Err(err) => {
+                warn!("Failed to read cover art of track {}: {err:#}", track.id);
+                report.failed.push(track.id);
+                continue;
+            }
+        };
+
+        // Name the exported file after the content of the original image and its size.
+        // That way, covers that're shared by multiple tracks are only exported once and a
+        // changed `max_size` results in new images.
+        let digest = format!("{:x}", Sha256::digest(&image));
+        let name = format!("{}_{max_size}.jpg", &digest[..16]);
+        let path = cover_directory.join(&name);
+
+        if path.exists() {
+            debug!("Cover art of track {} already exists at {path:?}", track.id);
+            report.deduplicated += 1;
+        } else if let Err(err) = write_resized(&image, &path, max_size) {
+            warn!("Failed to export cover art of track {}: {err:#}", track.id);
+            report.failed.push(track.id);
+            continue;
+        } else {
+            report.exported += 1;
+        }
+
+        track.cover_art.exported = Some(format!("{COVER_ART_DIRECTORY}/{name}"));
+    }
+
+    Ok(report)
+}
+
+/// Read the raw image data of a track's cover art.
+///
+/// Mixxx's info on the cover art is used first.
+/// If Mixxx doesn't know about any cover art, we look for embedded images and common image files
+/// in the track's directory.
+fn read_cover(track: &Track) -> Result<Option<Vec<u8>>> {
+    let audio_path = Path::new(&track.location.location);
+    let directory = PathBuf::from(&track.location.directory);
+
+    match track.cover_art.cover_type {
+        CoverArtType::Metadata => return read_embedded_cover(audio_path),
+        CoverArtType::File => {
+            if let Some(location) = &track.cover_art.location {
+                // Joining an absolute path replaces the directory.
+                let path = directory.join(location);
+                let image = read(&path).context(format!("Failed to read cover at {path:?}"))?;
+                return Ok(Some(image));
+            }
+        }
+        CoverArtType::None => (),
+    }
+
+    // Mixxx doesn't know about any cover art, so we have to look for it ourselves.
+    // Files that cannot be parsed might still have a cover image in their directory.
+    if let Ok(Some(image)) = read_embedded_cover(audio_path) {
+        return Ok(Some(image));
+    }
+
+    for name in FOLDER_COVER_NAMES {
+        let path = directory.join(name);
+        if path.exists() {
+            let image = read(&path).context(format!("Failed to read cover at {path:?}"))?;
+            return Ok(Some(image));
+        }
+    }
+
+    Ok(None)
+}
+
+/// Decode the image, shrink it if it's bigger than `max_size` and save it as a JPEG.
+fn write_resized(image: &[u8], path: &Path, max_size: u32) -> Result<()> {
+    let mut image = image::load_from_memory(image).context("Failed to decode image")?;
+    if image.width() > max_size || image.height() > max_size {
+        image = image.resize(max_size, max_size, FilterType::Lanczos3);
+    }
+
+    // JPEG doesn't support transparency.
+    image
+        .into_rgb8()
+        .save_with_format(path, ImageFormat::Jpeg)
+        .context(format!("Failed to write image to {path:?}"))?;
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        fs::{copy, create_dir},
+        io::Cursor,
+    };
+
+    use anyhow::Result;
+    use id3::{
+        Tag,
+        TagLike,
+        Version,
+        frame::{Picture, PictureType},
+    };
+    use image::{Rgb, RgbImage};
+    use path_slash::PathExt;
+    use pretty_assertions::assert_eq;
+    use tempfile::TempDir;
+
+    use super::*;
+    use crate::mixxx::{
+        aggregator::read_library,
+        fixture::{LibraryBuilder, Schema, TrackFixture, audio_fixture},
+    };
+
+    fn png(color: [u8; 3]) -> Result<Vec<u8>> {
+        let mut bytes = Vec::new();
+        RgbImage::from_pixel(64, 64, Rgb(color))
+            .write_to(&mut Cursor::new(&mut bytes), ImageFormat::Png)?;
+        Ok(bytes)
+    }
+
+    /// A copy of the tagged MP3 fixture at `<music>/<directory>/<name>`.
+    fn audio_file(music: &Path, directory: &str, name: &str) -> Result<String> {
+        let directory = music.join(directory);
+        if !directory.exists() {
+            create_dir(&directory)?;
+        }
+        let path = directory.join(name);
+        copy(audio_fixture("tagged.mp3"), &path)?;
+        Ok(path.to_slash_lossy().into_owned())
+    }
+
+    async fn library(music: &Path) -> Result<Library> {
+        let embedded = audio_file(music, "Embedded", "track.mp3")?;
+        let mut tag = Tag::read_from_path(&embedded)?;
+        tag.add_frame(Picture {
+            mime_type: "image/png".into(),
+            picture_type: PictureType::CoverFront,
+            description: String::new(),
+            data: png([255, 0, 0])?,
+        });
+        tag.write_to_path(&embedded, Version::Id3v24)?;
+
+        // Two tracks of the same album share the cover of their folder.
+        let first = audio_file(music, "Album", "first.mp3")?;
+        let second = audio_file(music, "Album", "second.mp3")?;
+        std::fs::write(music.join("Album").join("cover.png"), png([0, 0, 255])?)?;
+
+        let without = audio_file(music, "Without", "track.mp3")?;
+
+        let track = |id, path: String| TrackFixture {
+            path,
+            ..TrackFixture::new(id, "Artist", &format!("Track {id}"))
+        };
+        let mut fixture = LibraryBuilder::new()
+            .track(track(1, embedded))
+            .track(track(2, first))
+            .track(track(3, second))
+            .track(track(4, without))
+            .build(Schema::Latest)
+            .await?;
+
+        read_library(&mut fixture.connection).await
+    }
+
+    fn exported(library: &Library) -> Vec<Option<String>> {
+        library
+            .tracks
+            .values()
+            .map(|track| track.cover_art.exported.clone())
+            .collect()
+    }
+
+    #[tokio::test]
+    async fn export_embedded_and_folder_covers() -> Result<()> {
+        let music = TempDir::new()?;
+        let target = TempDir::new()?;
+        let mut library = library(music.path()).await?;
+
+        let report = export_cover_art(&mut library, target.path(), 32)?;
+
+        assert_eq!(
+            (report.exported, report.deduplicated, report.missing),
+            (2, 1, 1)
+        );
+        assert!(report.failed.is_empty());
+        let paths = exported(&library);
+        assert!(paths[0].is_some());
+        assert!(paths[1].is_some());
+        assert_ne!(paths[0], paths[1]);
+        // The folder cover is only exported once.
+        assert_eq!(paths[1], paths[2]);
+        assert_eq!(paths[3], None);
+
+        let image = image::open(target.path().join(paths[0].as_ref().unwrap()))?;
+        assert_eq!((image.width(), image.height()), (32, 32));
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn reexport_changed_size() -> Result<()> {
+        let music = TempDir::new()?;
+        let target = TempDir::new()?;
+        let mut library = library(music.path()).await?;
+
+        export_cover_art(&mut library, target.path(), 32)?;
+        let report = export_cover_art(&mut library, target.path(), 32)?;
+        assert_eq!((report.exported, report.deduplicated), (0, 3));
+
+        let report = export_cover_art(&mut library, target.path(), 16)?;
+        assert_eq!((report.exported, report.deduplicated), (2, 1));
+        let path = library.tracks[&1].cover_art.exported.clone().unwrap();
+        let image = image::open(target.path().join(path))?;
+        assert_eq!((image.width(), image.height()), (16, 16));
+
+        Ok(())
+    }
+}
@@ -1,8 +1,179 @@
-use anyhow::Result;
-use sqlx::{Connection, sqlite::SqliteConnection};
+use std::{ffi::CStr, str::FromStr, time::Duration};
 
-pub async fn new_connection(db_path: &str) -> Result<SqliteConnection> {
-    let conn = SqliteConnection::connect(db_path).await?;
+use anyhow::{Context, Result, bail};
+use libsqlite3_sys::{
+    SQLITE_DONE,
+    SQLITE_OK,
+    sqlite3_backup_finish,
+    sqlite3_backup_init,
+    sqlite3_backup_step,
+    sqlite3_errmsg,
+    sqlite3_errstr,
+};
+use log::info;
+use serde_derive::{Deserialize, Serialize};
+use sqlx::{
+    Connection,
+    sqlite::{SqliteConnectOptions, SqliteConnection},
+};
+use tempfile::TempDir;
 
-    Ok(conn)
+/// How the Mixxx database is opened.
+///
+/// The database is never opened with write access, so an export cannot corrupt the library
+/// of a running Mixxx instance.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum DbAccess {
+    /// Open the database read-only.
+    /// If Mixxx is currently writing, we wait for up to `mixxx_db_busy_timeout` milliseconds.
+    #[default]
+    ReadOnly,
+    /// Open the database as immutable, which disables all locking.
+    /// This never blocks Mixxx, but the export might see inconsistent data if Mixxx writes to
+    /// the database at the same time.
+    Immutable,
+    /// Take a consistent copy of the database via SQLite's backup API and read from the copy.
+    /// Mixxx is only blocked while the copy is created.
+    Snapshot,
+}
+
+/// A connection to the Mixxx database.
+#[derive(Debug)]
+pub struct Database {
+    pub connection: SqliteConnection,
+    /// The directory of the snapshot, which is removed once the database is dropped.
+    _snapshot: Option<TempDir>,
+}
+
+/// Open the Mixxx database at `db_path` without write access.
+pub async fn new_connection(
+    db_path: &str,
+    access: DbAccess,
+    busy_timeout: Duration,
+) -> Result<Database> {
+    let options = SqliteConnectOptions::from_str(db_path)
+        .context(format!("Invalid database path {db_path}"))?
+        .read_only(true)
+        .immutable(access == DbAccess::Immutable)
+        .busy_timeout(busy_timeout);
+
+    let mut connection = SqliteConnection::connect_with(&options)
+        .await
+        .context(format!("Failed to open Mixxx database at {db_path}"))?;
+
+    if access != DbAccess::Snapshot {
+        return Ok(Database {
+            connection,
+            _snapshot: None,
+        });
+    }
+
+    let snapshot_dir = TempDir::new().context("Failed to create snapshot directory")?;
+    let snapshot_path = snapshot_dir.path().join("mixxxdb.sqlite");
+    info!("Creating snapshot of Mixxx database at {snapshot_path:?}");
+
+    let snapshot_options = SqliteConnectOptions::new()
+        .filename(&snapshot_path)
+        .create_if_missing(true);
+    let mut snapshot = SqliteConnection::connect_with(&snapshot_options)
+        .await
+        .context(format!("Failed to create snapshot at {snapshot_path:?}"))?;
+
+    backup(&mut connection, &mut snapshot).await?;
+    connection.close().await?;
+
+    Ok(Database {
+        connection: snapshot,
+        _snapshot: Some(snapshot_dir),
+    })
+}
+
+/// Copy the whole `source` database into `target` in a single step.
+///
+/// The copy is consistent, as SQLite holds a read lock on the source for the whole step.
+/// If Mixxx is writing at that moment, the busy timeout of `source` applies.
+async fn backup(source: &mut SqliteConnection, target: &mut SqliteConnection) -> Result<()> {
+    let mut source_handle = source.lock_handle().await?;
+    let mut target_handle = target.lock_handle().await?;
+    let source_db = source_handle.as_raw_handle().as_ptr();
+    let target_db = target_handle.as_raw_handle().as_ptr();
+
+    // SAFETY: Both handles are valid and locked for the whole duration of the backup,
+    // so sqlx cannot use them concurrently. The backup object is always finished, which
+    // releases all of its resources.
+    unsafe {
+        let backup = sqlite3_backup_init(target_db, c"main".as_ptr(), source_db, c"main".as_ptr());
+        if backup.is_null() {
+            let message = CStr::from_ptr(sqlite3_errmsg(target_db)).to_string_lossy();
+            bail!("Failed to start database snapshot: {message}");
+        }
+
+        // Copy all pages at once.
+        let step = sqlite3_backup_step(backup, -1);
+        let finish = sqlite3_backup_finish(backup);
+
+        if step != SQLITE_DONE {
+            let message = CStr::from_ptr(sqlite3_errstr(step)).to_string_lossy();
+            bail!("Failed to create database snapshot: {message}");
+        }
+        if finish != SQLITE_OK {
+            let message = CStr::from_ptr(sqlite3_errmsg(target_db)).to_string_lossy();
+            bail!("Failed to finish database snapshot: {message}");
+        }
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use pretty_assertions::assert_eq;
+    use rstest::rstest;
+
+    use super::*;
+
+    async fn create_database(dir: &TempDir) -> Result<String> {
+        let path = dir.path().join("mixxxdb.sqlite");
+        let options = SqliteConnectOptions::new()
+            .filename(&path)
+            .create_if_missing(true);
+        let mut con = SqliteConnection::connect_with(&options).await?;
+        sqlx::raw_sql(
+            "CREATE TABLE library (id INTEGER PRIMARY KEY); INSERT INTO library VALUES (1), (2);",
+        )
+        .execute(&mut con)
+        .await?;
+        con.close().await?;
+
+        Ok(path.to_string_lossy().to_string())
+    }
+
+    #[rstest]
+    #[case(DbAccess::ReadOnly)]
+    #[case(DbAccess::Immutable)]
+    #[case(DbAccess::Snapshot)]
+    #[tokio::test]
+    async fn open_without_write_access(#[case] access: DbAccess) -> Result<()> {
+        let dir = TempDir::new()?;
+        let path = create_database(&dir).await?;
+
+        let mut db = new_connection(&path, access, Duration::from_millis(100)).await?;
+        let count: i64 = sqlx::query_scalar("SELECT COUNT(*) FROM library")
+            .fetch_one(&mut db.connection)
+            .await?;
+        assert_eq!(count, 2);
+
+        // Snapshots are writable, but the original database must never be touched.
+        let _ = sqlx::query("DELETE FROM library")
+            .execute(&mut db.connection)
+            .await;
+        let mut original = new_connection(&path, DbAccess::ReadOnly, Duration::ZERO).await?;
+        let count: i64 = sqlx::query_scalar("SELECT COUNT(*) FROM library")
+            .fetch_one(&mut original.connection)
+            .await?;
+        assert_eq!(count, 2);
+
+        Ok(())
+    }
 }
@@ -0,0 +1,547 @@
+use std::{
+    collections::{BTreeMap, HashSet},
+    fmt,
+};
+
+use serde_derive::Serialize;
+use serde_json::Value;
+
+use crate::mixxx::{
+    helper::{CUE_SAMPLE_RATE, CueType, convert_mixxx_position},
+    library::{Library, Track},
+    schema::cue::Cue,
+};
+
+/// Everything that differs between two libraries.
+///
+/// Tracks are matched by their id, playlists and crates by their name. Playlists that share
+/// a name are matched in their order and listed as e.g. `Warmup (2)`.
+#[derive(Serialize, Debug, Default, Clone, PartialEq)]
+pub struct LibraryDiff {
+    pub added_tracks: Vec<TrackSummary>,
+    pub removed_tracks: Vec<TrackSummary>,
+    pub changed_tracks: Vec<TrackDiff>,
+    pub added_playlists: Vec<String>,
+    pub removed_playlists: Vec<String>,
+    pub changed_playlists: Vec<PlaylistDiff>,
+}
+
+/// The info that's needed to identify a track.
+#[derive(Serialize, Debug, Clone, PartialEq)]
+pub struct TrackSummary {
+    pub id: usize,
+    pub artist: String,
+    pub title: String,
+}
+
+#[derive(Serialize, Debug, Clone, PartialEq)]
+pub struct TrackDiff {
+    pub track: TrackSummary,
+    pub fields: Vec<FieldChange>,
+    pub added_cues: Vec<CueSummary>,
+    pub removed_cues: Vec<CueSummary>,
+    /// Cues whose position or length changed.
+    pub moved_cues: Vec<CueMove>,
+    /// Cues whose label or colour changed.
+    pub changed_cues: Vec<CueSummary>,
+}
+
+/// A metadata field of a track, e.g. `technical_info.bpm`.
+#[derive(Serialize, Debug, Clone, PartialEq)]
+pub struct FieldChange {
+    pub field: String,
+    pub old: Value,
+    pub new: Value,
+}
+
+#[derive(Serialize, Debug, Clone, PartialEq)]
+pub struct CueSummary {
+    pub cue_type: CueType,
+    /// The hotcue number or -1, if the cue isn't a hotcue.
+    pub hotcue: i64,
+    /// The position in seconds.
+    pub position: f64,
+    pub label: String,
+}
+
+#[derive(Serialize, Debug, Clone, PartialEq)]
+pub struct CueMove {
+    pub cue: CueSummary,
+    /// The previous position in seconds.
+    pub old_position: f64,
+}
+
+/// The changes of a playlist or crate that exists in both libraries.
+#[derive(Serialize, Debug, Clone, PartialEq)]
+pub struct PlaylistDiff {
+    pub name: String,
+    pub added_tracks: Vec<usize>,
+    pub removed_tracks: Vec<usize>,
+    /// Whether the tracks that're in both versions of the playlist have a different order.
+    pub reordered: bool,
+}
+
+impl LibraryDiff {
+    pub fn is_empty(&self) -> bool {
+        self == &LibraryDiff::default()
+    }
+}
+
+/// Compare the `old` with the `new` library.
+pub fn diff_libraries(old: &Library, new: &Library) -> LibraryDiff {
+    let mut diff = LibraryDiff::default();
+
+    for (id, track) in &new.tracks {
+        match old.tracks.get(id) {
+            None => diff.added_tracks.push(TrackSummary::from(track)),
+            Some(old_track) => {
+                if let Some(track_diff) = diff_tracks(old_track, track) {
+                    diff.changed_tracks.push(track_diff);
+                }
+            }
+        }
+    }
+    diff.removed_tracks = old
+        .tracks
+        .iter()
+        .filter(|(id, _)| !new.tracks.contains_key(id))
+        .map(|(_, track)| TrackSummary::from(track))
+        .collect();
+
+    let old_playlists = playlists_by_name(old);
+    let new_playlists = playlists_by_name(new);
+    for (name, track_ids) in &new_playlists {
+        match old_playlists.get(name) {
+            None => diff.added_playlists.push(name.clone()),
+            Some(old_track_ids) => {
+                if let Some(playlist_diff) = diff_playlists(name, old_track_ids, track_ids) {
+                    diff.changed_playlists.push(playlist_diff);
+                }
+            }
+        }
+    }
+    diff.removed_playlists = old_playlists
+        .keys()
+        .filter(|name| !new_playlists.contains_key(*name))
+        .cloned()
+        .collect();
+
+    diff
+}
+
+impl From<&Track> for TrackSummary {
+    fn from(track: &Track) -> Self {
+        TrackSummary {
+            id: track.id,
+            artist: track.artist.clone(),
+            title: track.title.clone(),
+        }
+    }
+}
+
+impl From<&Cue> for CueSummary {
+    fn from(cue: &Cue) -> Self {
+        CueSummary {
+            cue_type: CueType::from_mixxx(cue.cue_type),
+            hotcue: cue.hotcue,
+            position: cue_position(cue.position),
+            label: cue.label.clone(),
+        }
+    }
+}
+
+fn cue_position(position: i64) -> f64 {
+    convert_mixxx_position(position as f64, CUE_SAMPLE_RATE)
+}
+
+/// Compare two versions of a track. Returns `None`, if nothing changed.
+fn diff_tracks(old: &Track, new: &Track) -> Option<TrackDiff> {
+    let mut old_fields = BTreeMap::new();
+    let mut new_fields = BTreeMap::new();
+    flatten_track(old, &mut old_fields);
+    flatten_track(new, &mut new_fields);
+
+    let mut fields = Vec::new();
+    for (field, new_value) in &new_fields {
+        let old_value = old_fields.get(field).cloned().unwrap_or(Value::Null);
+        if &old_value != new_value {
+            fields.push(FieldChange {
+                field: field.clone(),
+                old: old_value,
+                new: new_value.clone(),
+            });
+        }
+    }
+
+    let mut diff = TrackDiff {
+        track: TrackSummary::from(new),
+        fields,
+        added_cues: Vec::new(),
+        removed_cues: Vec::new(),
+        moved_cues: Vec::new(),
+        changed_cues: Vec::new(),
+    };
+
+    let old_cues = cues_by_key(&old.cues);
+    let new_cues = cues_by_key(&new.cues);
+    for (key, cue) in &new_cues {
+        let Some(old_cue) = old_cues.get(key) else {
+            diff.added_cues.push(CueSummary::from(*cue));
+            continue;
+        };
+
+        if old_cue.position != cue.position || old_cue.length != cue.length {
+            diff.moved_cues.push(CueMove {
+                cue: CueSummary::from(*cue),
+                old_position: cue_position(old_cue.position),
+            });
+        } else if old_cue.label != cue.label || old_cue.color != cue.color {
+            diff.changed_cues.push(CueSummary::from(*cue));
+        }
+    }
+    diff.removed_cues = old_cues
+        .iter()
+        .filter(|(key, _)| !new_cues.contains_key(*key))
+        .map(|(_, cue)| CueSummary::from(*cue))
+        .collect();
+
+    let unchanged = diff.fields.is_empty()
+        && diff.added_cues.is_empty()
+        && diff.removed_cues.is_empty()
+        && diff.moved_cues.is_empty()
+        && diff.changed_cues.is_empty();
+    if unchanged { None } else { Some(diff) }
+}
+
+/// Fields that depend on the files on disk or on a previous export instead of the library.
+const IGNORED_FIELDS: &[&str] = &["location.filesize", "cover_art.exported"];
+
+/// Flatten all metadata of a track into `field path -> value`.
+/// Cues are compared separately and the id is what tracks are matched by.
+fn flatten_track(track: &Track, fields: &mut BTreeMap<String, Value>) {
+    let Ok(Value::Object(object)) = serde_json::to_value(track) else {
+        return;
+    };
+    for (key, value) in object {
+        if key == "id" || key == "cues" {
+            continue;
+        }
+        flatten_value(key, value, fields);
+    }
+    for field in IGNORED_FIELDS {
+        fields.remove(*field);
+    }
+}
+
+fn flatten_value(path: String, value: Value, fields: &mut BTreeMap<String, Value>) {
+    match value {
+        Value::Object(object) => {
+            for (key, value) in object {
+                flatten_value(format!("{path}.{key}"), value, fields);
+            }
+        }
+        value => {
+            fields.insert(path, value);
+        }
+    }
+}
+
+/// Cues are identified by their type, their hotcue number and the order of cues
+/// with the same type and number.
+fn cues_by_key(cues: &[Cue]) -> BTreeMap<(i64, i64, usize), &Cue> {
+    let mut by_key = BTreeMap::new();
+    for cue in cues {
+        let mut index = 0;
+        while by_key.contains_key(&(cue.cue_type, cue.hotcue, index)) {
+            index += 1;
+        }
+        by_key.insert((cue.cue_type, cue.hotcue, index), cue);
+    }
+
+    by_key
+}
+
+/// All playlists and crates by their name. Crates get a `crate:` prefix, as they may have the
+/// same name as a playlist.
+fn playlists_by_name(library: &Library) -> BTreeMap<String, &Vec<usize>> {
+    let playlists = library
+        .playlists
+        .iter()
+        .map(|playlist| (playlist.name.clone(), &playlist.track_ids));
+    let crates = library.crates.iter().map(|mixxx_crate| {
+        (
+            format!("crate:{}", mixxx_crate.name),
+            &mixxx_crate.track_ids,
+        )
+    });
+
+    // Names aren't unique, so later playlists with the same name get their number appended.
+    let mut by_name = BTreeMap::new();
+    for (name, track_ids) in playlists.chain(crates) {
+        let mut key = name.clone();
+        let mut number = 1;
+        while by_name.contains_key(&key) {
+            number += 1;
+            key = format!("{name} ({number})");
+        }
+        by_name.insert(key, track_ids);
+    }
+
+    by_name
+}
+
+/// Compare two versions of a playlist. Returns `None`, if nothing changed.
+fn diff_playlists(name: &str, old: &[usize], new: &[usize]) -> Option<PlaylistDiff> {
+    if old == new {
+        return None;
+    }
+
+    let old_ids: HashSet<usize> = old.iter().copied().collect();
+    let new_ids: HashSet<usize> = new.iter().copied().collect();
+    let added_tracks = new
+        .iter()
+        .copied()
+        .filter(|id| !old_ids.contains(id))
+        .collect();
+    let removed_tracks = old
+        .iter()
+        .copied()
+        .filter(|id| !new_ids.contains(id))
+        .collect();
+
+    // Only look at the tracks that're in both versions to detect reordering.
+    let old_order: Vec<usize> = old
+        .iter()
+        .copied()
+        .filter(|id| new_ids.contains(id))
+        .collect();
+    let new_order: Vec<usize> = new
+        .iter()
+        .copied()
+        .filter(|id| old_ids.contains(id))
+        .collect();
+
+    Some(PlaylistDiff {
+        name: name.to_string(),
+        added_tracks,
+        removed_tracks,
+        reordered: old_order != new_order,
+    })
+}
+
+/// Arrays such as the beatgrid are too big to be printed as a whole.
+fn display_value(value: &Value) -> String {
+    match value {
+        Value::Array(entries) => format!("[{} entries]", entries.len()),
+        value => value.to_string(),
+    }
+}
+
+impl fmt::Display for TrackSummary {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} {} - {}", self.id, self.artist, self.title)
+    }
+}
+
+impl fmt::Display for CueSummary {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.cue_type)?;
+        if self.hotcue >= 0 {
+            write!(f, " {}", self.hotcue + 1)?;
+        }
+        write!(f, " at {:.3}s", self.position)?;
+        if !self.label.is_empty() {
+            write!(f, " '{}'", self.label)?;
+        }
+
+        Ok(())
+    }
+}
+
+impl fmt::Display for LibraryDiff {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.is_empty() {
+            return writeln!(f, "The libraries are identical.");
+        }
+
+        writeln!(
+            f,
+            "Tracks: {} added, {} removed, {} changed",
+            self.added_tracks.len(),
+            self.removed_tracks.len(),
+            self.changed_tracks.len()
+        )?;
+        for track in &self.added_tracks {
+            writeln!(f, "+ {track}")?;
+        }
+        for track in &self.removed_tracks {
+            writeln!(f, "- {track}")?;
+        }
+        for track in &self.changed_tracks {
+            writeln!(f, "~ {}", track.track)?;
+            for field in &track.fields {
+                writeln!(
+                    f,
+                    "    {}: {} -> {}",
+                    field.field,
+                    display_value(&field.old),
+                    display_value(&field.new)
+                )?;
+            }
+            for cue in &track.added_cues {
+                writeln!(f, "    + {cue}")?;
+            }
+            for cue in &track.removed_cues {
+                writeln!(f, "    - {cue}")?;
+            }
+            for cue_move in &track.moved_cues {
+                writeln!(
+                    f,
+                    "    ~ {} (was at {:.3}s)",
+                    cue_move.cue, cue_move.old_position
+                )?;
+            }
+            for cue in &track.changed_cues {
+                writeln!(f, "    ~ {cue}")?;
+            }
+        }
+
+        writeln!(
+            f,
+            "Playlists: {} added, {} removed, {} changed",
+            self.added_playlists.len(),
+            self.removed_playlists.len(),
+            self.changed_playlists.len()
+        )?;
+        for name in &self.added_playlists {
+            writeln!(f, "+ {name}")?;
+        }
+        for name in &self.removed_playlists {
+            writeln!(f, "- {name}")?;
+        }
+        for playlist in &self.changed_playlists {
+            write!(
+                f,
+                "~ {}: {} added, {} removed",
+                playlist.name,
+                playlist.added_tracks.len(),
+                playlist.removed_tracks.len()
+            )?;
+            if playlist.reordered {
+                write!(f, ", reordered")?;
+            }
+            writeln!(f)?;
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use anyhow::Result;
+    use pretty_assertions::assert_eq;
+
+    use super::*;
+    use crate::mixxx::{aggregator::read_library, fixture::sample_library};
+
+    #[tokio::test]
+    async fn diff_sample_library() -> Result<()> {
+        let mut fixture = sample_library().await?;
+        let old = read_library(&mut fixture.connection).await?;
+        assert!(diff_libraries(&old, &old).is_empty());
+
+        let mut new = old.clone();
+        let removed = new.tracks.remove(&3).unwrap();
+        let track = new.tracks.get_mut(&1).unwrap();
+        track.technical_info.bpm = 130.0;
+        track.cues[0].position += CUE_SAMPLE_RATE;
+        let removed_cue = track.cues.pop().unwrap();
+        new.playlists[0].track_ids.reverse();
+        new.crates.clear();
+
+        let diff = diff_libraries(&old, &new);
+        assert_eq!(diff.removed_tracks, vec![TrackSummary::from(&removed)]);
+        assert_eq!(diff.changed_tracks.len(), 1);
+        let track_diff = &diff.changed_tracks[0];
+        assert_eq!(
+            track_diff.fields,
+            vec![FieldChange {
+                field: "technical_info.bpm".into(),
+                old: Value::from(old.tracks[&1].technical_info.bpm),
+                new: Value::from(130.0),
+            }]
+        );
+        assert_eq!(
+            track_diff.removed_cues,
+            vec![CueSummary::from(&removed_cue)]
+        );
+        assert_eq!(track_diff.moved_cues.len(), 1);
+        assert_eq!(
+            track_diff.moved_cues[0].cue.position - track_diff.moved_cues[0].old_position,
+            1.0
+        );
+        assert_eq!(diff.removed_playlists, vec!["crate:Techno"]);
+        assert_eq!(
+            diff.changed_playlists,
+            vec![PlaylistDiff {
+                name: "Warmup".into(),
+                added_tracks: vec![],
+                removed_tracks: vec![],
+                reordered: true,
+            }]
+        );
+
+        let text = diff.to_string();
+        assert!(text.contains("Tracks: 0 added, 1 removed, 1 changed"));
+        assert!(text.contains("~ Warmup: 0 added, 0 removed, reordered"));
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn ignore_export_specific_fields() -> Result<()> {
+        let mut fixture = sample_library().await?;
+        let old = read_library(&mut fixture.connection).await?;
+
+        let mut new = old.clone();
+        let track = new.tracks.get_mut(&1).unwrap();
+        track.location.filesize = Some(1234);
+        track.cover_art.exported = Some("artwork/cover.jpg".into());
+
+        assert!(diff_libraries(&old, &new).is_empty());
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn playlists_with_the_same_name() -> Result<()> {
+        let mut fixture = sample_library().await?;
+        let mut old = read_library(&mut fixture.connection).await?;
+        let mut duplicate = old.playlists[0].clone();
+        duplicate.id = 100;
+        duplicate.track_ids = vec![1];
+        old.playlists.push(duplicate);
+
+        let mut new = old.clone();
+        new.playlists.last_mut().unwrap().track_ids.push(2);
+
+        let diff = diff_libraries(&old, &new);
+        assert_eq!(
+            diff.changed_playlists,
+            vec![PlaylistDiff {
+                name: "Warmup (2)".into(),
+                added_tracks: vec![2],
+                removed_tracks: vec![],
+                reordered: false,
+            }]
+        );
+
+        new.playlists.pop();
+        let diff = diff_libraries(&old, &new);
+        assert_eq!(diff.removed_playlists, vec!["Warmup (2)"]);
+        assert!(diff.changed_playlists.is_empty());
+
+        Ok(())
+    }
+}
@@ -0,0 +1,437 @@
+use std::{
+    cmp::Reverse,
+    collections::{BTreeMap, HashMap, HashSet, hash_map::Entry},
+    fmt,
+    io::Write,
+    path::Path,
+};
+
+use anyhow::Result;
+use log::warn;
+use rayon::prelude::*;
+use serde_derive::Serialize;
+
+use crate::{
+    diff::TrackSummary,
+    export::output::AtomicFile,
+    mixxx::library::{Library, Track},
+    tags::reader::hash_audio_content,
+};
+
+/// Words after which an artist name only lists featured artists.
+const FEATURING: [&str; 3] = ["feat", "ft", "featuring"];
+
+/// How tracks are considered to be the same song.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct DuplicateOptions {
+    /// The maximum difference in seconds between the durations of two copies.
+    pub duration_tolerance: f64,
+    /// Also compare the hashes of the audio streams, which finds copies with different tags.
+    /// This reads every file, so it's slow for big libraries.
+    pub content_hash: bool,
+}
+
+impl Default for DuplicateOptions {
+    fn default() -> Self {
+        DuplicateOptions {
+            duration_tolerance: 2.0,
+            content_hash: false,
+        }
+    }
+}
+
+/// Multiple tracks that are most likely the same song.
+#[derive(Serialize, Debug, Clone, PartialEq)]
+pub struct DuplicateGroup {
+    pub reason: DuplicateReason,
+    /// The copy that's used the most comes first.
+    pub tracks: Vec<DuplicateTrack>,
+}
+
+#[derive(Serialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum DuplicateReason {
+    /// The same artist and title with a similar duration.
+    Metadata,
+    /// The same audio stream.
+    Content,
+}
+
+/// A single copy of a duplicate with the info that helps to decide which copy to keep.
+#[derive(Serialize, Debug, Clone, PartialEq)]
+pub struct DuplicateTrack {
+    pub track: TrackSummary,
+    pub location: String,
+    pub filetype: String,
+    /// The duration in seconds.
+    pub duration: f64,
+    pub cues: usize,
+    pub plays: i64,
+    /// The amount of playlists and crates that contain the track.
+    pub memberships: usize,
+}
+
+impl DuplicateTrack {
+    /// Copies with more cues, plays and memberships are more valuable.
+    fn usage(&self) -> (usize, i64, usize) {
+        (self.cues, self.plays, self.memberships)
+    }
+}
+
+/// Find all groups of tracks that are the same song.
+/// Tracks that've been deleted in Mixxx are ignored.
+pub fn find_duplicates(library: &Library, options: &DuplicateOptions) -> Vec<DuplicateGroup> {
+    let tracks: Vec<_> = library
+        .tracks
+        .values()
+        .filter(|track| !track.metadata.deleted)
+        .collect();
+    let memberships = count_memberships(library);
+    let describe = |track: &Track| DuplicateTrack {
+        track: TrackSummary::from(track),
+        location: track.location.location.clone(),
+        filetype: track.filetype.clone(),
+        duration: track.technical_info.duration,
+        cues: track.cues.len(),
+        plays: track.metadata.timesplayed,
+        memberships: memberships.get(&track.id).copied().unwrap_or_default(),
+    };
+
+    // Clusters of the same song and the index of the cluster each track belongs to.
+    let mut clusters: Vec<(DuplicateReason, Vec<&Track>)> = Vec::new();
+    let mut cluster_of: HashMap<usize, usize> = HashMap::new();
+
+    let mut by_name: BTreeMap<(String, String), Vec<&Track>> = BTreeMap::new();
+    for track in &tracks {
+        let artist = normalize_artist(&track.artist);
+        let title = normalize_title(&track.title);
+        // Tracks without metadata would all end up in the same group.
+        if artist.is_empty() && title.is_empty() {
+            continue;
+        }
+        by_name.entry((artist, title)).or_default().push(track);
+    }
+    for tracks in by_name.into_values() {
+        for cluster in cluster_by_duration(tracks, options.duration_tolerance) {
+            for track in &cluster {
+                cluster_of.insert(track.id, clusters.len());
+            }
+            clusters.push((DuplicateReason::Metadata, cluster));
+        }
+    }
+
+    if options.content_hash {
+        let hashes: Vec<_> = tracks
+            .par_iter()
+            .filter_map(
+                |track| match hash_audio_content(Path::new(&track.location.location)) {
+                    Ok(hash) => Some((hash, *track)),
+                    Err(err) => {
+                        warn!("Failed to hash track {}: {err:#}", track.id);
+                        None
+                    }
+                },
+            )
+            .collect();
+        let mut by_hash: BTreeMap<String, Vec<&Track>> = BTreeMap::new();
+        for (hash, track) in hashes {
+            by_hash.entry(hash).or_default().push(track);
+        }
+        for tracks in by_hash.into_values().filter(|tracks| tracks.len() > 1) {
+            let mut overlapping: Vec<_> = tracks
+                .iter()
+                .filter_map(|track| cluster_of.get(&track.id).copied())
+                .collect();
+            overlapping.sort();
+            overlapping.dedup();
+
+            let Some(&target) = overlapping.first() else {
+                for track in &tracks {
+                    cluster_of.insert(track.id, clusters.len());
+                }
+                clusters.push((DuplicateReason::Content, tracks));
+                continue;
+            };
+
+            // Don't report the same copies twice. Clusters that share a copy are the same song,
+            // so they're merged into the first one.
+            for &index in &overlapping[1..] {
+                let moved = std::mem::take(&mut clusters[index].1);
+                for track in &moved {
+                    cluster_of.insert(track.id, target);
+                }
+                clusters[target].1.extend(moved);
+            }
+            for track in tracks {
+                if let Entry::Vacant(entry) = cluster_of.entry(track.id) {
+                    entry.insert(target);
+                    clusters[target].1.push(track);
+                }
+            }
+        }
+    }
+
+    clusters
+        .into_iter()
+        .filter(|(_, tracks)| !tracks.is_empty())
+        .map(|(reason, mut tracks)| {
+            tracks.sort_by_key(|track| track.id);
+            group(reason, &tracks, &describe)
+        })
+        .collect()
+}
+
+fn group(
+    reason: DuplicateReason,
+    tracks: &[&Track],
+    describe: &impl Fn(&Track) -> DuplicateTrack,
+) -> DuplicateGroup {
+    let mut tracks: Vec<_> = tracks.iter().map(|track| describe(track)).collect();
+    // The sort is stable, so copies with the same usage stay ordered by id.
+    tracks.sort_by_key(|track| Reverse(track.usage()));
+
+    DuplicateGroup { reason, tracks }
+}
+
+/// Split tracks with the same name into clusters of similar durations.
+/// Only clusters with at least two tracks are returned.
+fn cluster_by_duration(mut tracks: Vec<&Track>, tolerance: f64) -> Vec<Vec<&Track>> {
+    tracks.sort_by(|left, right| {
+        left.technical_info
+            .duration
+            .total_cmp(&right.technical_info.duration)
+    });
+
+    let mut clusters: Vec<Vec<&Track>> = Vec::new();
+    for track in tracks {
+        match clusters.last_mut() {
+            Some(cluster)
+                if track.technical_info.duration
+                    - cluster[cluster.len() - 1].technical_info.duration
+                    <= tolerance =>
+            {
+                cluster.push(track)
+            }
+            _ => clusters.push(vec![track]),
+        }
+    }
+
+    clusters
+        .into_iter()
+        .filter(|cluster| cluster.len() > 1)
+        .map(|mut cluster| {
+            cluster.sort_by_key(|track| track.id);
+            cluster
+        })
+        .collect()
+}
+
+/// The amount of playlists and crates that contain each track.
+fn count_memberships(library: &Library) -> HashMap<usize, usize> {
+    let lists = library
+        .playlists
+        .iter()
+        .map(|playlist| &playlist.track_ids)
+        .chain(
+            library
+                .crates
+                .iter()
+                .map(|mixxx_crate| &mixxx_crate.track_ids),
+        );
+
+    let mut memberships = HashMap::new();
+    for track_ids in lists {
+        let unique: HashSet<_> = track_ids.iter().collect();
+        for id in unique {
+            *memberships.entry(*id).or_insert(0) += 1;
+        }
+    }
+
+    memberships
+}
+
+/// Lowercase words without any punctuation, e.g. `Daft Punk!` becomes `daft punk`.
+fn normalize(text: &str) -> Vec<String> {
+    text.to_lowercase()
+        .split(|c: char| !c.is_alphanumeric())
+        .filter(|word| !word.is_empty())
+        .map(str::to_string)
+        .collect()
+}
+
+/// Featured artists are often only mentioned in some copies.
+fn normalize_artist(artist: &str) -> String {
+    normalize(artist)
+        .into_iter()
+        .take_while(|word| !FEATURING.contains(&word.as_str()))
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+/// Stores often add `(Original Mix)` to titles, which is the same as no version at all.
+fn normalize_title(title: &str) -> String {
+    let mut words = normalize(title);
+    if words.ends_with(&["original".to_string(), "mix".to_string()]) {
+        words.truncate(words.len() - 2);
+    }
+
+    words.join(" ")
+}
+
+/// Write all copies as an M3U playlist, which can be imported into Mixxx for the cleanup.
+pub fn write_playlist(groups: &[DuplicateGroup], path: &Path) -> Result<()> {
+    let mut file = AtomicFile::create(path)?;
+    writeln!(file, "#EXTM3U")?;
+    for group in groups {
+        for copy in &group.tracks {
+            writeln!(
+                file,
+                "#EXTINF:{},{} - {}",
+                copy.duration.round(),
+                copy.track.artist,
+                copy.track.title
+            )?;
+            writeln!(file, "{}", copy.location)?;
+        }
+    }
+
+    file.commit(0)
+}
+
+impl fmt::Display for DuplicateGroup {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.reason {
+            DuplicateReason::Metadata => writeln!(f, "Same artist and title:")?,
+            DuplicateReason::Content => writeln!(f, "Same audio:")?,
+        }
+        for (index, copy) in self.tracks.iter().enumerate() {
+            let marker = if index == 0 { '*' } else { ' ' };
+            writeln!(
+                f,
+                "  {marker} {} ({}, {:.0}s, {} cues, {} plays, {} playlists and crates)",
+                copy.track, copy.filetype, copy.duration, copy.cues, copy.plays, copy.memberships
+            )?;
+            writeln!(f, "      {}", copy.location)?;
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use anyhow::Result;
+    use pretty_assertions::assert_eq;
+    use rstest::rstest;
+    use tempfile::TempDir;
+
+    use super::*;
+    use crate::mixxx::{
+        aggregator::read_library,
+        fixture::{CueFixture, LibraryBuilder, Schema, TrackFixture, audio_fixture},
+    };
+
+    #[rstest]
+    #[case("Daft Punk feat. Pharrell", "daft punk")]
+    #[case("DAFT-PUNK", "daft punk")]
+    #[case("Beyoncé ft Jay-Z", "beyoncé")]
+    fn normalize_artists(#[case] artist: &str, #[case] expected: &str) {
+        assert_eq!(normalize_artist(artist), expected);
+    }
+
+    #[rstest]
+    #[case("Strings of Life (Original Mix)", "strings of life")]
+    #[case("Strings of Life - Remix", "strings of life remix")]
+    fn normalize_titles(#[case] title: &str, #[case] expected: &str) {
+        assert_eq!(normalize_title(title), expected);
+    }
+
+    #[tokio::test]
+    async fn group_copies() -> Result<()> {
+        let mut fixture = LibraryBuilder::new()
+            .track(TrackFixture::new(
+                1,
+                "Rhythim Is Rhythim",
+                "Strings of Life",
+            ))
+            .track(TrackFixture {
+                path: "/music/Strings of Life.flac".into(),
+                filetype: "flac".into(),
+                duration: 301.5,
+                timesplayed: 3,
+                cues: vec![CueFixture::hotcue(0, 1.0)],
+                ..TrackFixture::new(2, "rhythim is rhythim", "Strings Of Life (Original Mix)")
+            })
+            // A different edit of the same song.
+            .track(TrackFixture {
+                path: "/music/Strings of Life (Edit).mp3".into(),
+                duration: 420.0,
+                ..TrackFixture::new(3, "Rhythim Is Rhythim", "Strings of Life")
+            })
+            .track(TrackFixture::new(4, "Rhythim Is Rhythim", "Nude Photo"))
+            .playlist("Classics", &[1, 2])
+            .mixxx_crate("Detroit", &[2])
+            .build(Schema::Latest)
+            .await?;
+        let library = read_library(&mut fixture.connection).await?;
+
+        let groups = find_duplicates(&library, &DuplicateOptions::default());
+
+        assert_eq!(groups.len(), 1);
+        assert_eq!(groups[0].reason, DuplicateReason::Metadata);
+        let copies: Vec<_> = groups[0]
+            .tracks
+            .iter()
+            .map(|copy| (copy.track.id, copy.cues, copy.plays, copy.memberships))
+            .collect();
+        assert_eq!(copies, vec![(2, 1, 3, 2), (1, 0, 0, 1)]);
+
+        let strict = DuplicateOptions {
+            duration_tolerance: 1.0,
+            ..Default::default()
+        };
+        assert!(find_duplicates(&library, &strict).is_empty());
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn merge_overlapping_groups() -> Result<()> {
+        let dir = TempDir::new()?;
+        let copy = |name: &str| -> Result<String> {
+            let path = dir.path().join(name);
+            std::fs::copy(audio_fixture("tagged.mp3"), &path)?;
+            Ok(path.to_string_lossy().into_owned())
+        };
+        // All files have the same audio, but only the first two have the same name.
+        let mut fixture = LibraryBuilder::new()
+            .track(TrackFixture {
+                path: copy("one.mp3")?,
+                ..TrackFixture::new(1, "Artist", "Song")
+            })
+            .track(TrackFixture {
+                path: copy("two.mp3")?,
+                ..TrackFixture::new(2, "Artist", "Song")
+            })
+            .track(TrackFixture {
+                path: copy("three.mp3")?,
+                ..TrackFixture::new(3, "Unknown", "Track 01")
+            })
+            .build(Schema::Latest)
+            .await?;
+        let library = read_library(&mut fixture.connection).await?;
+
+        let options = DuplicateOptions {
+            content_hash: true,
+            ..Default::default()
+        };
+        let groups = find_duplicates(&library, &options);
+
+        assert_eq!(groups.len(), 1);
+        assert_eq!(groups[0].reason, DuplicateReason::Metadata);
+        let ids: Vec<_> = groups[0].tracks.iter().map(|copy| copy.track.id).collect();
+        assert_eq!(ids, vec![1, 2, 3]);
+
+        Ok(())
+    }
+}
@@ -0,0 +1,58 @@
+use anyhow::Result;
+use log::info;
+use schemars::{JsonSchema, Schema};
+use serde_derive::Deserialize;
+
+use super::{Exporter, Profile, Report, options_schema, output::AtomicFile, parse_options};
+use crate::{import::json::LibraryFileRef, mixxx::library::Library};
+
+const FILE_NAME: &str = "mixxx_library.json";
+
+/// Options of the json exporter.
+#[derive(Deserialize, JsonSchema, Debug, Default)]
+#[serde(deny_unknown_fields)]
+pub struct JsonOptions {
+    /// Indent the JSON, so it's readable by humans.
+    #[serde(default)]
+    pub pretty: bool,
+}
+
+/// Writes the whole library into a `mixxx_library.json` file.
+/// The file can be read again by the json importer.
+pub struct JsonExporter;
+
+impl Exporter for JsonExporter {
+    fn name(&self) -> &'static str {
+        "json"
+    }
+
+    fn description(&self) -> &'static str {
+        "The raw library as JSON, e.g. for scripts and other tools."
+    }
+
+    fn config_schema(&self) -> Schema {
+        options_schema::<JsonOptions>()
+    }
+
+    fn export(&self, library: &Library, profile: &Profile) -> Result<Report> {
+        let options: JsonOptions = parse_options(profile)?;
+
+        let json_target_file = profile.output_path(FILE_NAME);
+        info!("Writing json library to {json_target_file:?}");
+
+        let mut file = AtomicFile::create(&json_target_file)?;
+        let library_file = LibraryFileRef::from(library);
+        if options.pretty {
+            serde_json::to_writer_pretty(&mut file, &library_file)?;
+        } else {
+            serde_json::to_writer(&mut file, &library_file)?;
+        }
+        file.commit(profile.backups)?;
+
+        Ok(Report {
+            files: vec![json_target_file],
+            tracks: library.tracks.len(),
+            changes: None,
+        })
+    }
+}
@@ -0,0 +1,222 @@
+use std::{
+    collections::BTreeMap,
+    fs::read_to_string,
+    io::Write,
+    path::{Path, PathBuf},
+};
+
+use anyhow::{Context, Result};
+use log::{info, warn};
+use serde_derive::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+
+use super::output::AtomicFile;
+
+/// The version of the manifest format.
+/// Manifests of other versions are ignored, which results in a full export.
+pub const MANIFEST_VERSION: u32 = 1;
+
+/// The content hashes of all exported tracks and playlists.
+///
+/// The manifest is saved beside an export, so the next export can detect what has changed.
+/// Hashes are computed over the converted entries, so they cover exactly the data that ends up
+/// in the export.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct Manifest {
+    pub version: u32,
+    /// Hashes of all tracks by their id.
+    pub tracks: BTreeMap<String, String>,
+    /// Hashes of all playlists, including their track membership, by their name.
+    pub playlists: BTreeMap<String, String>,
+}
+
+/// The difference between two manifests.
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct Changes {
+    pub added_tracks: Vec<String>,
+    pub changed_tracks: Vec<String>,
+    pub removed_tracks: Vec<String>,
+    pub added_playlists: Vec<String>,
+    pub changed_playlists: Vec<String>,
+    pub removed_playlists: Vec<String>,
+}
+
+impl Default for Manifest {
+    fn default() -> Self {
+        Manifest {
+            version: MANIFEST_VERSION,
+            tracks: BTreeMap::new(),
+            playlists: BTreeMap::new(),
+        }
+    }
+}
+
+impl Manifest {
+    /// The path of the manifest that belongs to the export at `export_path`,
+    /// e.g. `mixxx_rekordbox_export.manifest.json`.
+    pub fn path(export_path: &Path) -> PathBuf {
+        let stem = export_path
+            .file_stem()
+            .unwrap_or_default()
+            .to_string_lossy();
+        export_path.with_file_name(format!("{stem}.manifest.json"))
+    }
+
+    /// Read the manifest at `path`.
+    /// Returns `None`, if there's no manifest yet or if it has been written by another version.
+    pub fn read(path: &Path) -> Result<Option<Manifest>> {
+        if !path.exists() {
+            info!("No manifest found at {path:?}, all entries are treated as new");
+            return Ok(None);
+        }
+
+        let content = read_to_string(path).context(format!("Failed to read manifest {path:?}"))?;
+        let manifest: Manifest =
+            serde_json::from_str(&content).context(format!("Invalid manifest at {path:?}"))?;
+        if manifest.version != MANIFEST_VERSION {
+            warn!(
+                "Manifest at {path:?} has version {}, expected {MANIFEST_VERSION}. Ignoring it.",
+                manifest.version
+            );
+            return Ok(None);
+        }
+
+        Ok(Some(manifest))
+    }
+
+    /// Write the manifest to `path`.
+    pub fn write(&self, path: &Path) -> Result<()> {
+        let mut file = AtomicFile::create(path)?;
+        serde_json::to_writer_pretty(&mut file, self)?;
+        file.write_all(b"\n")?;
+        file.commit(0)
+    }
+
+    /// Add the hash of a track.
+    pub fn insert_track(&mut self, id: &str, track: &impl serde::Serialize) -> Result<()> {
+        self.tracks.insert(id.to_string(), hash(track)?);
+        Ok(())
+    }
+
+    /// Add the hash of a playlist.
+    /// Playlists with the same name, e.g. a playlist and a crate, are told apart by a suffix.
+    pub fn insert_playlist(
+        &mut self,
+        name: &str,
+        playlist: &impl serde::Serialize,
+    ) -> Result<String> {
+        let mut key = name.to_string();
+        let mut count = 1;
+        while self.playlists.contains_key(&key) {
+            count += 1;
+            key = format!("{name} #{count}");
+        }
+        self.playlists.insert(key.clone(), hash(playlist)?);
+
+        Ok(key)
+    }
+
+    /// Everything that has changed between the `previous` and this manifest.
+    /// Without a previous manifest, all entries are added.
+    pub fn changes_since(&self, previous: Option<&Manifest>) -> Changes {
+        let empty = Manifest::default();
+        let previous = previous.unwrap_or(&empty);
+
+        let (added_tracks, changed_tracks, removed_tracks) =
+            diff_entries(&previous.tracks, &self.tracks);
+        let (added_playlists, changed_playlists, removed_playlists) =
+            diff_entries(&previous.playlists, &self.playlists);
+
+        Changes {
+            added_tracks,
+            changed_tracks,
+            removed_tracks,
+            added_playlists,
+            changed_playlists,
+            removed_playlists,
+        }
+    }
+}
+
+impl Changes {
+    pub fn is_empty(&self) -> bool {
+        self == &Changes::default()
+    }
+}
+
+/// Compute the SHA-256 hash of the JSON representation of `value`.
+fn hash(value: &impl serde::Serialize) -> Result<String> {
+    let json = serde_json::to_vec(value).context("Failed to serialize entry for hashing")?;
+    Ok(format!("{:x}", Sha256::digest(json)))
+}
+
+/// Get the added, changed and removed keys between two sets of hashes.
+fn diff_entries(
+    previous: &BTreeMap<String, String>,
+    current: &BTreeMap<String, String>,
+) -> (Vec<String>, Vec<String>, Vec<String>) {
+    let mut added = Vec::new();
+    let mut changed = Vec::new();
+    for (key, hash) in current {
+        match previous.get(key) {
+            None => added.push(key.clone()),
+            Some(previous_hash) if previous_hash != hash => changed.push(key.clone()),
+            Some(_) => {}
+        }
+    }
+    let removed = previous
+        .keys()
+        .filter(|key| !current.contains_key(*key))
+        .cloned()
+        .collect();
+
+    (added, changed, removed)
+}
+
+#[cfg(test)]
+mod tests {
+    use pretty_assertions::assert_eq;
+    use tempfile::TempDir;
+
+    use super::*;
+
+    #[test]
+    fn detect_changes() -> Result<()> {
+        let mut previous = Manifest::default();
+        previous.insert_track("1", &"unchanged")?;
+        previous.insert_track("2", &"old title")?;
+        previous.insert_track("3", &"removed")?;
+        previous.insert_playlist("Warmup", &[1, 2])?;
+        previous.insert_playlist("Peak", &[3])?;
+
+        let mut current = Manifest::default();
+        current.insert_track("1", &"unchanged")?;
+        current.insert_track("2", &"new title")?;
+        current.insert_track("4", &"added")?;
+        current.insert_playlist("Warmup", &[1, 2])?;
+        current.insert_playlist("Peak", &[4])?;
+        assert_eq!(current.insert_playlist("Peak", &[1])?, "Peak #2");
+
+        // The manifest survives a round trip through its file.
+        let dir = TempDir::new()?;
+        let path = Manifest::path(&dir.path().join("export.xml"));
+        assert_eq!(path, dir.path().join("export.manifest.json"));
+        previous.write(&path)?;
+        let previous = Manifest::read(&path)?;
+
+        assert_eq!(
+            current.changes_since(previous.as_ref()),
+            Changes {
+                added_tracks: vec!["4".into()],
+                changed_tracks: vec!["2".into()],
+                removed_tracks: vec!["3".into()],
+                added_playlists: vec!["Peak #2".into()],
+                changed_playlists: vec!["Peak".into()],
+                removed_playlists: vec![],
+            }
+        );
+        assert!(current.changes_since(Some(&current)).is_empty());
+
+        Ok(())
+    }
+}
@@ -0,0 +1,143 @@
+use std::path::PathBuf;
+
+use anyhow::{Context, Result};
+use schemars::{JsonSchema, Schema, schema_for};
+use serde::de::DeserializeOwned;
+
+use self::manifest::Changes;
+use crate::mixxx::library::Library;
+
+/// Export the library as the raw JSON representation of our [Library].
+pub mod json;
+/// Change detection between exports.
+pub mod manifest;
+/// Atomic writes of export files.
+pub mod output;
+/// The settings of a single export.
+pub mod profile;
+/// Export the library in a documented and versioned JSON format.
+pub mod public_json;
+/// Export the library as rekordbox XML.
+pub mod rekordbox;
+/// Export a track listing as CSV or spreadsheet.
+pub mod spreadsheet;
+
+pub use profile::Profile;
+
+/// A target format, into which the library can be exported.
+///
+/// Exporters are registered in the [Registry](crate::registry::Registry), which looks them up
+/// by the `format` of a [Profile].
+pub trait Exporter: Send + Sync {
+    /// The unique name of the format, which is used in the `format` field of profiles.
+    fn name(&self) -> &'static str;
+
+    /// A short, human readable description of the format.
+    fn description(&self) -> &'static str;
+
+    /// The JSON schema of the exporter specific `options` of a profile.
+    fn config_schema(&self) -> Schema;
+
+    /// Export the library with the settings of the given profile.
+    fn export(&self, library: &Library, profile: &Profile) -> Result<Report>;
+}
+
+/// A summary of a single export.
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct Report {
+    /// All files that've been written.
+    pub files: Vec<PathBuf>,
+    /// The amount of exported tracks.
+    pub tracks: usize,
+    /// What has changed since the previous export, if the exporter keeps track of it.
+    pub changes: Option<Changes>,
+}
+
+/// Deserialize the `options` of a profile into the exporter's own options.
+/// Missing options are treated like an empty set of options.
+pub fn parse_options<T: DeserializeOwned>(profile: &Profile) -> Result<T> {
+    let options = match &profile.options {
+        serde_json::Value::Null => serde_json::Value::Object(Default::default()),
+        options => options.clone(),
+    };
+
+    serde_json::from_value(options).context(format!(
+        "Invalid options for format '{}' in profile '{}'",
+        profile.format, profile.name
+    ))
+}
+
+/// The config schema of an options struct.
+pub fn options_schema<T: JsonSchema>() -> Schema {
+    schema_for!(T)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs::create_dir;
+
+    use pretty_assertions::assert_eq;
+    use tempfile::TempDir;
+
+    use super::{json::JsonOptions, *};
+    use crate::{mixxx::fixture::write_config, registry::Registry};
+
+    #[test]
+    fn profiles_fall_back_to_global_settings() -> Result<()> {
+        let dir = TempDir::new()?;
+        create_dir(dir.path().join("out"))?;
+        create_dir(dir.path().join("usb"))?;
+        let config = write_config(
+            dir.path(),
+            &dir.path().join("mixxxdb.sqlite"),
+            &format!(
+                r#"
+                [[profiles]]
+                name = "usb"
+                format = "rekordbox"
+                target_directory = {:?}
+                target_library_root = "/Volumes/USB/Music"
+
+                [[profiles]]
+                name = "backup"
+                format = "json"
+                options = {{ pretty = true }}
+                "#,
+                dir.path().join("usb"),
+            ),
+        );
+        config.validate()?;
+
+        let profiles = config.profiles();
+        assert_eq!(profiles.len(), 2);
+        assert_eq!(profiles[0].target_directory, dir.path().join("usb"));
+        assert_eq!(
+            profiles[0].target_library_root,
+            PathBuf::from("/Volumes/USB/Music")
+        );
+        assert_eq!(profiles[1].target_directory, dir.path().join("out"));
+        assert_eq!(profiles[1].target_library_root, PathBuf::from("C:/Music"));
+        assert!(parse_options::<JsonOptions>(&profiles[1])?.pretty);
+
+        // All configured formats have to be known.
+        let registry = Registry::default();
+        for profile in &profiles {
+            registry.exporter(&profile.format)?;
+        }
+        assert!(registry.exporter("traktor").is_err());
+
+        Ok(())
+    }
+
+    #[test]
+    fn reject_unknown_options() -> Result<()> {
+        let dir = TempDir::new()?;
+        let mut profile = write_config(dir.path(), &dir.path().join("mixxxdb.sqlite"), "")
+            .default_profile("json");
+        profile.options = serde_json::json!({ "prety": true });
+
+        assert!(parse_options::<JsonOptions>(&profile).is_err());
+
+        Ok(())
+    }
+}
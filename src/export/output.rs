@@ -0,0 +1,251 @@
+#[cfg(unix)]
+use std::{fs::Permissions, os::unix::fs::PermissionsExt};
+use std::{
+    fs::{copy, hard_link, read_dir, remove_file},
+    io::{BufWriter, Write},
+    path::{Path, PathBuf},
+};
+
+use anyhow::{Context, Result};
+use chrono::{DateTime, Local, NaiveDateTime};
+use log::{debug, info};
+use tempfile::{Builder, NamedTempFile};
+
+/// The timestamp format of backups. It doesn't contain colons, as Windows doesn't allow them.
+const BACKUP_TIMESTAMP_FORMAT: &str = "%Y-%m-%dT%H-%M-%S";
+
+/// A file that's written next to its target and only replaces the target once it's complete.
+///
+/// If the export fails or panics midway, the temporary file is removed and the previous export
+/// stays untouched. Sync tools thereby never see a missing or half-written export.
+pub struct AtomicFile {
+    target: PathBuf,
+    writer: BufWriter<NamedTempFile>,
+}
+
+impl AtomicFile {
+    /// Create a temporary file in the directory of `target`.
+    pub fn create(target: &Path) -> Result<Self> {
+        let directory = target
+            .parent()
+            .context(format!("Export file doesn't have a directory: {target:?}"))?;
+        let file_name = target
+            .file_name()
+            .context(format!("Export file doesn't have a filename: {target:?}"))?;
+        let prefix = format!(".{}.", file_name.to_string_lossy());
+        let mut builder = Builder::new();
+        builder.prefix(&prefix).suffix(".tmp");
+        // Temporary files are only readable by the owner by default, but exports are usually
+        // read by other programs and users.
+        #[cfg(unix)]
+        builder.permissions(Permissions::from_mode(0o644));
+        let file = builder
+            .tempfile_in(directory)
+            .context(format!("Failed to create temporary file in {directory:?}"))?;
+
+        Ok(AtomicFile {
+            target: target.to_path_buf(),
+            writer: BufWriter::new(file),
+        })
+    }
+
+    /// Replace the target with the written file.
+    /// The previous export is kept as a timestamped backup, if `backups` is greater than 0.
+    /// Only the newest `backups` backups are kept.
+    pub fn commit(self, backups: usize) -> Result<()> {
+        let file = self
+            .writer
+            .into_inner()
+            .context(format!("Failed to write {:?}", self.target))?;
+        file.as_file()
+            .sync_all()
+            .context(format!("Failed to write {:?}", self.target))?;
+
+        if backups > 0 && self.target.exists() {
+            rotate_backups(&self.target, backups, Local::now())?;
+        }
+
+        file.persist(&self.target)
+            .context(format!("Failed to move export to {:?}", self.target))?;
+
+        Ok(())
+    }
+}
+
+impl Write for AtomicFile {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        self.writer.write(buf)
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        self.writer.flush()
+    }
+}
+
+/// Keep the current `target` as a backup with the timestamp `now` and remove all but the newest
+/// `backups` backups.
+///
+/// Backups are placed next to the target, e.g. `export.2024-01-02T10-00-00.xml`.
+/// The target itself stays in place, so it's only ever replaced atomically by the new export.
+fn rotate_backups(target: &Path, backups: usize, now: DateTime<Local>) -> Result<()> {
+    let (stem, extension) = split_file_name(target)?;
+    let directory = target
+        .parent()
+        .context(format!("Export file doesn't have a directory: {target:?}"))?;
+
+    let timestamp = now.format(BACKUP_TIMESTAMP_FORMAT);
+    let backup = directory.join(format!("{stem}.{timestamp}{extension}"));
+    info!("Keeping previous export as backup at {backup:?}");
+    if backup.exists() {
+        remove_file(&backup).context(format!("Failed to replace backup at {backup:?}"))?;
+    }
+    // Linking is instant, but not supported by all file systems, e.g. FAT32.
+    if hard_link(target, &backup).is_err() {
+        copy(target, &backup).context(format!("Failed to create backup at {backup:?}"))?;
+    }
+
+    // Find all existing backups of this target.
+    let mut existing = Vec::new();
+    for entry in read_dir(directory).context(format!("Failed to read directory {directory:?}"))? {
+        let path = entry?.path();
+        let Some(name) = path.file_name().and_then(|name| name.to_str()) else {
+            continue;
+        };
+        let Some(timestamp) = name
+            .strip_prefix(&format!("{stem}."))
+            .and_then(|rest| rest.strip_suffix(&extension))
+        else {
+            continue;
+        };
+        if NaiveDateTime::parse_from_str(timestamp, BACKUP_TIMESTAMP_FORMAT).is_ok() {
+            existing.push(path);
+        }
+    }
+
+    // The timestamps sort chronologically, so the oldest backups come first.
+    existing.sort();
+    let outdated = existing.len().saturating_sub(backups);
+    for path in existing.into_iter().take(outdated) {
+        debug!("Removing old backup {path:?}");
+        remove_file(&path).context(format!("Failed to remove old backup {path:?}"))?;
+    }
+
+    Ok(())
+}
+
+/// Split a file name into its stem and its extension including the leading dot.
+/// The extension is empty, if the file doesn't have one.
+fn split_file_name(path: &Path) -> Result<(String, String)> {
+    let stem = path
+        .file_stem()
+        .context(format!("File doesn't have a filename: {path:?}"))?
+        .to_string_lossy()
+        .to_string();
+    let extension = path
+        .extension()
+        .map(|extension| format!(".{}", extension.to_string_lossy()))
+        .unwrap_or_default();
+
+    Ok((stem, extension))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs::{read_to_string, write};
+
+    use chrono::{Duration, TimeZone};
+    use pretty_assertions::assert_eq;
+    use tempfile::TempDir;
+
+    use super::*;
+
+    fn write_export(target: &Path, content: &str, backups: usize) -> Result<()> {
+        let mut file = AtomicFile::create(target)?;
+        file.write_all(content.as_bytes())?;
+        file.commit(backups)
+    }
+
+    fn file_names(dir: &Path) -> Result<Vec<String>> {
+        let mut names = read_dir(dir)?
+            .map(|entry| Ok(entry?.file_name().to_string_lossy().to_string()))
+            .collect::<Result<Vec<_>>>()?;
+        names.sort();
+        Ok(names)
+    }
+
+    #[test]
+    fn failed_export_keeps_previous_file() -> Result<()> {
+        let dir = TempDir::new()?;
+        let target = dir.path().join("export.xml");
+        write_export(&target, "first", 0)?;
+
+        // An export that's dropped before it's committed, e.g. due to an error.
+        let mut file = AtomicFile::create(&target)?;
+        file.write_all(b"half")?;
+        drop(file);
+
+        assert_eq!(read_to_string(&target)?, "first");
+        assert_eq!(file_names(dir.path())?, vec!["export.xml"]);
+
+        write_export(&target, "second", 0)?;
+        assert_eq!(read_to_string(&target)?, "second");
+        assert_eq!(file_names(dir.path())?, vec!["export.xml"]);
+
+        Ok(())
+    }
+
+    #[test]
+    fn backups_keep_target_in_place() -> Result<()> {
+        let dir = TempDir::new()?;
+        let target = dir.path().join("export.xml");
+        write_export(&target, "first", 1)?;
+
+        // The previous export must still exist while the new one is moved into place.
+        let now = Local.with_ymd_and_hms(2024, 1, 2, 10, 0, 0).unwrap();
+        rotate_backups(&target, 1, now)?;
+        assert_eq!(read_to_string(&target)?, "first");
+
+        write_export(&target, "second", 1)?;
+        assert_eq!(read_to_string(&target)?, "second");
+        let names = file_names(dir.path())?;
+        assert_eq!(names.len(), 2);
+        assert!(names.contains(&"export.xml".to_string()));
+        let backup = names
+            .iter()
+            .find(|name| *name != "export.xml")
+            .expect("Backup exists");
+        assert_eq!(read_to_string(dir.path().join(backup))?, "first");
+
+        Ok(())
+    }
+
+    #[test]
+    fn keep_newest_backups() -> Result<()> {
+        let dir = TempDir::new()?;
+        let target = dir.path().join("export.xml");
+        // Files that only look similar to backups must be left alone.
+        write(dir.path().join("export.notes.xml"), "")?;
+
+        let start = Local.with_ymd_and_hms(2024, 1, 2, 10, 0, 0).unwrap();
+        for day in 0..4 {
+            write(&target, format!("export {day}"))?;
+            rotate_backups(&target, 2, start + Duration::days(day))?;
+        }
+
+        assert_eq!(
+            file_names(dir.path())?,
+            vec![
+                "export.2024-01-04T10-00-00.xml",
+                "export.2024-01-05T10-00-00.xml",
+                "export.notes.xml",
+                "export.xml",
+            ]
+        );
+        assert_eq!(
+            read_to_string(dir.path().join("export.2024-01-05T10-00-00.xml"))?,
+            "export 3"
+        );
+
+        Ok(())
+    }
+}
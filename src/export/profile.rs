@@ -0,0 +1,77 @@
+use std::path::PathBuf;
+
+use anyhow::{Context, Result, bail};
+use path_slash::PathBufExt;
+
+use crate::{
+    color::{RgbColor, parse_palette},
+    sync::SyncConfig,
+    validate::TargetFilesystem,
+};
+
+/// The fully resolved settings of a single export.
+///
+/// Profiles are created from the config via [Config::profiles](crate::Config::profiles).
+#[derive(Debug, Clone)]
+pub struct Profile {
+    /// The unique name of this profile.
+    pub name: String,
+    /// The name of the exporter that's used.
+    pub format: String,
+    /// The directory to which the library will be exported to.
+    pub target_directory: PathBuf,
+    /// The root directory of all music files on this machine.
+    pub source_library_root: String,
+    /// The root directory of all music files on the target machine.
+    pub target_library_root: PathBuf,
+    /// Whether a playlist with all tracks should be created.
+    pub create_all_playlist: bool,
+    /// The name of the exported file. Each exporter has its own default name.
+    pub file_name: Option<String>,
+    /// How many previous exports are kept as timestamped backups.
+    pub backups: usize,
+    /// The colours, to which track colours are mapped.
+    pub track_color_palette: Option<Vec<String>>,
+    /// The file system of the target library root, against which paths are validated.
+    pub target_filesystem: Option<TargetFilesystem>,
+    /// Copy the music files into a directory before exporting.
+    pub sync: Option<SyncConfig>,
+    /// Exporter specific options.
+    /// See [Exporter::config_schema](super::Exporter::config_schema) for the expected format.
+    pub options: serde_json::Value,
+}
+
+impl Profile {
+    /// The path of the exported file, which is `default_file_name` unless the profile sets its
+    /// own file name.
+    pub fn output_path(&self, default_file_name: &str) -> PathBuf {
+        let file_name = self.file_name.as_deref().unwrap_or(default_file_name);
+        self.target_directory.join(file_name)
+    }
+
+    /// Map the Mixxx location of a track from the `source_library_root` to the
+    /// `target_library_root`.
+    pub fn map_location(&self, location: &str) -> Result<PathBuf> {
+        let unix_path = PathBuf::from_slash(location);
+        let source_root = PathBuf::from_slash(&self.source_library_root);
+
+        if !unix_path.starts_with(&source_root) {
+            bail!("Mixxx path '{unix_path:?}' is not in source_library_root {source_root:?}");
+        }
+
+        let relative_path = unix_path
+            .strip_prefix(&source_root)
+            .context("Failed to strip prefix {source_root:?} from '{unix_path:?}'")?;
+
+        // Start at the target library root and add the relative path to the actual track.
+        Ok(self.target_library_root.join(relative_path))
+    }
+
+    /// Get the configured track colour palette or fall back to the given default palette.
+    pub fn track_color_palette(&self, default: &[RgbColor]) -> Result<Vec<RgbColor>> {
+        match &self.track_color_palette {
+            Some(colors) => parse_palette(colors).context("Invalid track_color_palette"),
+            None => Ok(default.to_vec()),
+        }
+    }
+}
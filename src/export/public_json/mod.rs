@@ -0,0 +1,173 @@
+use std::io::Write;
+
+use anyhow::Result;
+use log::info;
+use schemars::{JsonSchema, Schema};
+use serde_derive::Deserialize;
+
+use self::schema::{Document, Header, Record, convert_playlists, convert_track};
+use super::{Exporter, Profile, Report, options_schema, output::AtomicFile, parse_options};
+use crate::mixxx::library::Library;
+
+/// The types of the public json format.
+pub mod schema;
+
+const FILE_NAME: &str = "mixxx_library_export.json";
+const NDJSON_FILE_NAME: &str = "mixxx_library_export.ndjson";
+
+/// Options of the public json exporter.
+#[derive(Deserialize, JsonSchema, Debug, Default)]
+#[serde(deny_unknown_fields)]
+pub struct PublicJsonOptions {
+    /// Indent the JSON, so it's readable by humans. Ignored for NDJSON.
+    #[serde(default)]
+    pub pretty: bool,
+    /// Write one JSON object per line instead of a single document.
+    /// The first line is the header, followed by one line per track and playlist.
+    #[serde(default)]
+    pub ndjson: bool,
+}
+
+/// Writes the library in a documented and versioned JSON format.
+///
+/// Unlike the `json` format, this format doesn't expose any internal structures, so it's
+/// meant to be consumed by other tools. Its JSON Schema is saved in the `schema` directory.
+pub struct PublicJsonExporter;
+
+impl Exporter for PublicJsonExporter {
+    fn name(&self) -> &'static str {
+        "public_json"
+    }
+
+    fn description(&self) -> &'static str {
+        "A documented JSON or NDJSON format with a stable schema, e.g. for dashboards."
+    }
+
+    fn config_schema(&self) -> Schema {
+        options_schema::<PublicJsonOptions>()
+    }
+
+    fn export(&self, library: &Library, profile: &Profile) -> Result<Report> {
+        let options: PublicJsonOptions = parse_options(profile)?;
+
+        let default_file_name = if options.ndjson {
+            NDJSON_FILE_NAME
+        } else {
+            FILE_NAME
+        };
+        let target_file = profile.output_path(default_file_name);
+        info!("Writing json library to {target_file:?}");
+
+        let mut file = AtomicFile::create(&target_file)?;
+        if options.ndjson {
+            // Tracks are converted one by one, so the export is never held in memory.
+            write_record(&mut file, &Record::Header(Header::new()))?;
+            for track in library.tracks.values() {
+                write_record(
+                    &mut file,
+                    &Record::Track(Box::new(convert_track(profile, track))),
+                )?;
+            }
+            for playlist in convert_playlists(library) {
+                write_record(&mut file, &Record::Playlist(playlist))?;
+            }
+        } else {
+            let document = Document {
+                header: Header::new(),
+                tracks: library
+                    .tracks
+                    .values()
+                    .map(|track| convert_track(profile, track))
+                    .collect(),
+                playlists: convert_playlists(library),
+            };
+            if options.pretty {
+                serde_json::to_writer_pretty(&mut file, &document)?;
+            } else {
+                serde_json::to_writer(&mut file, &document)?;
+            }
+            file.write_all(b"\n")?;
+        }
+        file.commit(profile.backups)?;
+
+        Ok(Report {
+            files: vec![target_file],
+            tracks: library.tracks.len(),
+            changes: None,
+        })
+    }
+}
+
+fn write_record(writer: &mut impl Write, record: &Record) -> Result<()> {
+    serde_json::to_writer(&mut *writer, record)?;
+    writer.write_all(b"\n")?;
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs::{create_dir_all, read_to_string};
+
+    use chrono::{TimeZone, Utc};
+    use pretty_assertions::assert_eq;
+
+    use super::{schema::*, *};
+    use crate::mixxx::{
+        aggregator::read_library,
+        fixture::{assert_golden, assert_schema, sample_library},
+    };
+
+    #[tokio::test]
+    async fn export_sample_library() -> Result<()> {
+        let mut fixture = sample_library().await?;
+        let profile = fixture.config().default_profile("public_json");
+        let library = read_library(&mut fixture.connection).await?;
+
+        // Use a fixed time, so the output is stable.
+        let document = Document {
+            header: Header {
+                version: PUBLIC_JSON_VERSION,
+                exported_at: Utc.with_ymd_and_hms(2024, 1, 2, 10, 0, 0).unwrap(),
+            },
+            tracks: library
+                .tracks
+                .values()
+                .map(|track| convert_track(&profile, track))
+                .collect(),
+            playlists: convert_playlists(&library),
+        };
+        assert_golden(
+            "public_library.json",
+            &(serde_json::to_string_pretty(&document)? + "\n"),
+        );
+
+        // Every line of the NDJSON variant is a complete record.
+        let mut profile = profile;
+        profile.options = serde_json::json!({ "ndjson": true });
+        create_dir_all(&profile.target_directory)?;
+        let report = PublicJsonExporter.export(&library, &profile)?;
+        let ndjson = read_to_string(&report.files[0])?;
+        let types = ndjson
+            .lines()
+            .map(|line| {
+                let record: serde_json::Value = serde_json::from_str(line)?;
+                Ok(record["type"].as_str().unwrap_or_default().to_string())
+            })
+            .collect::<Result<Vec<_>>>()?;
+        assert_eq!(
+            types,
+            vec![
+                "header", "track", "track", "track", "playlist", "playlist", "playlist"
+            ]
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn schemas_are_up_to_date() {
+        assert_schema("public_library.schema.json", &document_schema());
+        assert_schema("public_library_record.schema.json", &record_schema());
+    }
+}
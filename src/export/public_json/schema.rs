@@ -0,0 +1,275 @@
+use chrono::{DateTime, Utc};
+use log::warn;
+use schemars::{JsonSchema, Schema, schema_for};
+use serde_derive::Serialize;
+
+use crate::{
+    color::RgbColor,
+    export::Profile,
+    mixxx::{
+        helper::{CUE_SAMPLE_RATE, CueType, convert_mixxx_position},
+        library::{self, Beats, Library},
+        schema::cue::Cue as MixxxCue,
+    },
+};
+
+/// The version of the public json format.
+///
+/// It's increased for every change that may break consumers, i.e. anything but new fields.
+pub const PUBLIC_JSON_VERSION: u32 = 1;
+
+/// The whole library as a single json document.
+#[derive(Serialize, JsonSchema, Debug)]
+#[schemars(title = "Mixxx library export")]
+pub struct Document {
+    #[serde(flatten)]
+    pub header: Header,
+    pub tracks: Vec<Track>,
+    pub playlists: Vec<Playlist>,
+}
+
+/// A single line of the NDJSON variant.
+/// The first line is always the header, followed by all tracks and then all playlists.
+#[derive(Serialize, JsonSchema, Debug)]
+#[serde(tag = "type", rename_all = "snake_case")]
+#[schemars(title = "Mixxx library export record")]
+pub enum Record {
+    Header(Header),
+    Track(Box<Track>),
+    Playlist(Playlist),
+}
+
+#[derive(Serialize, JsonSchema, Debug, Clone)]
+pub struct Header {
+    /// The version of this format.
+    pub version: u32,
+    /// When the export has been created.
+    pub exported_at: DateTime<Utc>,
+}
+
+#[derive(Serialize, JsonSchema, Debug)]
+pub struct Track {
+    /// The Mixxx id of the track, which is referenced by playlists.
+    pub id: usize,
+    pub title: String,
+    pub artist: String,
+    pub album: Option<String>,
+    pub composer: Option<String>,
+    pub genre: Option<String>,
+    pub grouping: Option<String>,
+    pub year: Option<String>,
+    pub track_number: Option<String>,
+    pub disc_number: Option<String>,
+    pub label: Option<String>,
+    pub remixer: Option<String>,
+    pub comment: Option<String>,
+    /// The length of the track in seconds.
+    pub duration: f64,
+    /// `None`, if the track hasn't been analysed yet.
+    pub bpm: Option<f64>,
+    /// The musical key in Mixxx's notation, e.g. `8A` or `Am`.
+    pub key: Option<String>,
+    /// The rating from 0 to 5 stars.
+    pub rating: i64,
+    pub play_count: i64,
+    /// The track colour in the form of `#RRGGBB`.
+    pub color: Option<String>,
+    /// When the track has been added to the library, in UTC.
+    pub date_added: DateTime<Utc>,
+    pub file: File,
+    pub beatgrid: Option<Beatgrid>,
+    pub cues: Vec<Cue>,
+    /// The path of the exported cover art relative to the target directory.
+    pub cover_art: Option<String>,
+}
+
+#[derive(Serialize, JsonSchema, Debug)]
+pub struct File {
+    /// The path of the file on the target machine, i.e. below `target_library_root`.
+    /// `None`, if the file isn't inside the `source_library_root`.
+    pub path: Option<String>,
+    /// The path of the file as it's known to Mixxx.
+    pub source_path: String,
+    /// The file type, e.g. `mp3`.
+    pub file_type: String,
+    /// The size of the file in bytes.
+    pub size: Option<u64>,
+    /// The bitrate in kbit/s.
+    pub bitrate: i64,
+    /// The sample rate in Hz.
+    pub sample_rate: i64,
+}
+
+#[derive(Serialize, JsonSchema, Debug)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum Beatgrid {
+    /// The track has a constant tempo.
+    Constant {
+        bpm: f64,
+        /// The position of the first beat in seconds.
+        first_beat: f64,
+    },
+    /// The track has a variable tempo.
+    Variable {
+        /// The positions of all beats in seconds.
+        beats: Vec<f64>,
+    },
+}
+
+#[derive(Serialize, JsonSchema, Debug)]
+pub struct Cue {
+    #[serde(rename = "type")]
+    pub cue_type: CueType,
+    /// The hotcue number, starting at 1. `None`, if the cue isn't a hotcue.
+    pub number: Option<i64>,
+    /// The start of the cue in seconds.
+    pub start: f64,
+    /// The end of the cue in seconds, e.g. for loops.
+    pub end: Option<f64>,
+    pub label: Option<String>,
+    /// The cue colour in the form of `#RRGGBB`.
+    pub color: Option<String>,
+}
+
+#[derive(Serialize, JsonSchema, Debug)]
+pub struct Playlist {
+    pub name: String,
+    pub kind: PlaylistKind,
+    /// Hidden playlists are e.g. the Auto DJ queue.
+    pub hidden: bool,
+    /// The ids of all tracks in their order.
+    pub track_ids: Vec<usize>,
+}
+
+#[derive(Serialize, JsonSchema, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum PlaylistKind {
+    Playlist,
+    Crate,
+}
+
+/// The JSON Schema of the document variant.
+pub fn document_schema() -> Schema {
+    schema_for!(Document)
+}
+
+/// The JSON Schema of a single line of the NDJSON variant.
+pub fn record_schema() -> Schema {
+    schema_for!(Record)
+}
+
+impl Header {
+    pub fn new() -> Self {
+        Header {
+            version: PUBLIC_JSON_VERSION,
+            exported_at: Utc::now(),
+        }
+    }
+}
+
+impl Default for Header {
+    fn default() -> Self {
+        Header::new()
+    }
+}
+
+/// Convert a track of the library into its public representation.
+pub fn convert_track(profile: &Profile, track: &library::Track) -> Track {
+    let info = &track.technical_info;
+
+    let beatgrid = match info.decode_beats() {
+        Ok(Some(Beats::Grid { bpm, first_beat })) => Some(Beatgrid::Constant { bpm, first_beat }),
+        Ok(Some(Beats::Map(beats))) => Some(Beatgrid::Variable { beats }),
+        Ok(None) => None,
+        Err(err) => {
+            warn!("Failed to decode beats of track {}: {err:?}", track.id);
+            None
+        }
+    };
+
+    let path = match profile.map_location(&track.location.location) {
+        Ok(path) => Some(path.to_string_lossy().replace('\\', "/")),
+        Err(err) => {
+            warn!("Track {}: {err}", track.id);
+            None
+        }
+    };
+
+    Track {
+        id: track.id,
+        title: track.title.clone(),
+        artist: track.artist.clone(),
+        album: non_empty(track.album.as_deref()),
+        composer: non_empty(Some(&track.composer)),
+        genre: non_empty(track.genre.as_deref()),
+        grouping: non_empty(track.grouping.as_deref()),
+        year: non_empty(Some(&track.year)),
+        track_number: non_empty(track.tracknumber.as_deref()),
+        disc_number: non_empty(track.disc_number.as_deref()),
+        label: non_empty(track.label.as_deref()),
+        remixer: non_empty(track.remixer.as_deref()),
+        comment: non_empty(track.comment.as_deref()),
+        duration: info.duration,
+        bpm: (info.bpm > 0.0).then_some(info.bpm),
+        key: non_empty(Some(&info.key)),
+        rating: track.metadata.rating,
+        play_count: track.metadata.timesplayed,
+        color: track.color.map(hex_color),
+        date_added: track.metadata.datetime_added.and_utc(),
+        file: File {
+            path,
+            source_path: track.location.location.clone(),
+            file_type: track.filetype.clone(),
+            size: track.location.filesize,
+            bitrate: info.bitrate,
+            sample_rate: info.samplerate,
+        },
+        beatgrid,
+        cues: track.cues.iter().map(convert_cue).collect(),
+        cover_art: track.cover_art.exported.clone(),
+    }
+}
+
+fn convert_cue(cue: &MixxxCue) -> Cue {
+    let start = convert_mixxx_position(cue.position as f64, CUE_SAMPLE_RATE);
+    let end = (cue.length > 0)
+        .then(|| convert_mixxx_position((cue.position + cue.length) as f64, CUE_SAMPLE_RATE));
+
+    Cue {
+        cue_type: CueType::from_mixxx(cue.cue_type),
+        number: (cue.hotcue >= 0).then_some(cue.hotcue + 1),
+        start,
+        end,
+        label: non_empty(Some(&cue.label)),
+        color: RgbColor::from_cue_color(cue.color).map(hex_color),
+    }
+}
+
+/// Convert all playlists and crates into their public representation.
+pub fn convert_playlists(library: &Library) -> Vec<Playlist> {
+    let playlists = library.playlists.iter().map(|playlist| Playlist {
+        name: playlist.name.clone(),
+        kind: PlaylistKind::Playlist,
+        hidden: playlist.hidden,
+        track_ids: playlist.track_ids.clone(),
+    });
+    let crates = library.crates.iter().map(|mixxx_crate| Playlist {
+        name: mixxx_crate.name.clone(),
+        kind: PlaylistKind::Crate,
+        hidden: mixxx_crate.hidden,
+        track_ids: mixxx_crate.track_ids.clone(),
+    });
+
+    playlists.chain(crates).collect()
+}
+
+fn hex_color(color: RgbColor) -> String {
+    format!("#{:06X}", color.0 & 0xFF_FFFF)
+}
+
+/// Mixxx often saves empty strings instead of `NULL`.
+fn non_empty(value: Option<&str>) -> Option<String> {
+    value
+        .filter(|value| !value.trim().is_empty())
+        .map(str::to_string)
+}
@@ -0,0 +1,262 @@
+use std::{
+    collections::HashSet,
+    io::Write,
+    path::{Path, PathBuf},
+};
+
+use anyhow::Result;
+use log::info;
+use schemars::{JsonSchema, Schema};
+use serde_derive::Deserialize;
+
+use super::{
+    Exporter,
+    Profile,
+    Report,
+    manifest::Manifest,
+    options_schema,
+    output::AtomicFile,
+    parse_options,
+};
+use crate::{
+    mixxx::library::Library,
+    rekordbox::{
+        convert_library,
+        schema::{
+            playlists::{Playlist, Playlists},
+            tracks::Track,
+        },
+        write_library,
+        writer::LibraryWriter,
+    },
+};
+
+const FILE_NAME: &str = "mixxx_rekordbox_export.xml";
+
+/// Options of the rekordbox exporter.
+#[derive(Deserialize, JsonSchema, Debug, Default)]
+#[serde(deny_unknown_fields)]
+pub struct RekordboxOptions {
+    /// Keep a manifest of all exported tracks and playlists beside the export.
+    /// In addition to the full export, a `.delta.xml` file is written, which only contains
+    /// the tracks and playlists that've been added or changed since the last export.
+    /// Importing that file is a lot faster than re-importing the whole library.
+    #[serde(default)]
+    pub incremental: bool,
+}
+
+/// Writes a rekordbox XML library, which can be imported by rekordbox and most other DJ software.
+pub struct RekordboxExporter;
+
+impl Exporter for RekordboxExporter {
+    fn name(&self) -> &'static str {
+        "rekordbox"
+    }
+
+    fn description(&self) -> &'static str {
+        "A rekordbox XML library, which can be imported by rekordbox and most other DJ software."
+    }
+
+    fn config_schema(&self) -> Schema {
+        options_schema::<RekordboxOptions>()
+    }
+
+    fn export(&self, library: &Library, profile: &Profile) -> Result<Report> {
+        let options: RekordboxOptions = parse_options(profile)?;
+
+        let xml_target_file = profile.output_path(FILE_NAME);
+        info!("Writing rekordbox library to {xml_target_file:?}");
+
+        if options.incremental {
+            return export_incremental(library, profile, &xml_target_file);
+        }
+
+        // Stream the library into a temporary file, which replaces the previous export once
+        // it's complete.
+        let file = AtomicFile::create(&xml_target_file)?;
+        let file = write_library(profile, library, file)?;
+        file.commit(profile.backups)?;
+
+        Ok(Report {
+            files: vec![xml_target_file],
+            tracks: library.tracks.len(),
+            changes: None,
+        })
+    }
+}
+
+/// Write the full export, a delta export and the manifest of the current export.
+///
+/// Rekordbox XML cannot express removals, so removed tracks and playlists are only reported.
+fn export_incremental(
+    library: &Library,
+    profile: &Profile,
+    xml_target_file: &Path,
+) -> Result<Report> {
+    let (tracks, playlists) = convert_library(profile, library)?;
+
+    // Hash everything that ends up in the export.
+    let mut manifest = Manifest::default();
+    for track in &tracks {
+        manifest.insert_track(&track.track_id, track)?;
+    }
+    let mut playlist_keys = Vec::new();
+    for playlist in &playlists {
+        playlist_keys.push(manifest.insert_playlist(playlist.name(), playlist)?);
+    }
+
+    let manifest_path = Manifest::path(xml_target_file);
+    let previous = Manifest::read(&manifest_path)?;
+    let changes = manifest.changes_since(previous.as_ref());
+
+    // The delta contains all added and changed playlists, as well as all added or changed
+    // tracks. Tracks of changed playlists are included as well, as rekordbox drops playlist
+    // entries that aren't part of the collection.
+    let changed_playlists: HashSet<&String> = changes
+        .added_playlists
+        .iter()
+        .chain(&changes.changed_playlists)
+        .collect();
+    let delta_playlists: Vec<&Playlist> = playlists
+        .iter()
+        .zip(&playlist_keys)
+        .filter(|(_, key)| changed_playlists.contains(key))
+        .map(|(playlist, _)| playlist)
+        .collect();
+
+    let mut delta_track_ids: HashSet<String> = changes
+        .added_tracks
+        .iter()
+        .chain(&changes.changed_tracks)
+        .cloned()
+        .collect();
+    for playlist in &delta_playlists {
+        delta_track_ids.extend(playlist.track_keys().map(|key| key.to_string()));
+    }
+    let delta_tracks: Vec<&Track> = tracks
+        .iter()
+        .filter(|track| delta_track_ids.contains(&track.track_id))
+        .collect();
+
+    info!(
+        "Changes since the last export: {} added, {} changed, {} removed tracks; \
+        {} added, {} changed, {} removed playlists",
+        changes.added_tracks.len(),
+        changes.changed_tracks.len(),
+        changes.removed_tracks.len(),
+        changes.added_playlists.len(),
+        changes.changed_playlists.len(),
+        changes.removed_playlists.len(),
+    );
+
+    let delta_target_file = delta_path(xml_target_file);
+    write_document(
+        xml_target_file,
+        tracks.iter(),
+        playlists.iter(),
+        profile.backups,
+    )?;
+    write_document(
+        &delta_target_file,
+        delta_tracks.into_iter(),
+        delta_playlists.into_iter(),
+        0,
+    )?;
+    // The manifest is written last, so it never describes an export that doesn't exist.
+    manifest.write(&manifest_path)?;
+
+    Ok(Report {
+        files: vec![
+            xml_target_file.to_path_buf(),
+            delta_target_file,
+            manifest_path,
+        ],
+        tracks: tracks.len(),
+        changes: Some(changes),
+    })
+}
+
+/// The path of the delta export, e.g. `mixxx_rekordbox_export.delta.xml`.
+fn delta_path(xml_target_file: &Path) -> PathBuf {
+    let stem = xml_target_file
+        .file_stem()
+        .unwrap_or_default()
+        .to_string_lossy();
+    xml_target_file.with_file_name(format!("{stem}.delta.xml"))
+}
+
+/// Atomically write a rekordbox document with the given tracks and playlists.
+fn write_document<'a>(
+    path: &Path,
+    tracks: impl ExactSizeIterator<Item = &'a Track>,
+    playlists: impl Iterator<Item = &'a Playlist>,
+    backups: usize,
+) -> Result<()> {
+    let file = AtomicFile::create(path)?;
+    let mut writer = LibraryWriter::start(file, tracks.len())?;
+    for track in tracks {
+        writer.write_track(track)?;
+    }
+    let mut file = writer.finish(&Playlists::new(playlists.cloned().collect()))?;
+    file.flush()?;
+    file.commit(backups)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs::{create_dir_all, read_to_string};
+
+    use pretty_assertions::assert_eq;
+
+    use super::*;
+    use crate::mixxx::{aggregator::read_library, fixture::sample_library};
+
+    #[tokio::test]
+    async fn export_only_changes() -> Result<()> {
+        let mut fixture = sample_library().await?;
+        let mut profile = fixture.config().default_profile("rekordbox");
+        profile.options = serde_json::json!({ "incremental": true });
+        create_dir_all(&profile.target_directory)?;
+        let delta_file = profile
+            .target_directory
+            .join("mixxx_rekordbox_export.delta.xml");
+
+        // Without a manifest, everything is new.
+        let library = read_library(&mut fixture.connection).await?;
+        let report = RekordboxExporter.export(&library, &profile)?;
+        let changes = report.changes.unwrap();
+        assert_eq!(changes.added_tracks, vec!["1", "2", "3"]);
+        assert_eq!(changes.added_playlists, vec!["Techno", "Warmup", "all"]);
+        assert_eq!(
+            read_to_string(&delta_file)?,
+            read_to_string(&report.files[0])?
+        );
+
+        // Nothing changed, so the delta is empty.
+        let report = RekordboxExporter.export(&library, &profile)?;
+        assert!(report.changes.unwrap().is_empty());
+        assert!(read_to_string(&delta_file)?.contains(r#"<COLLECTION Entries="0"/>"#));
+
+        // Track 2 is only part of the changed playlist.
+        sqlx::query("UPDATE library SET title = 'Renamed' WHERE id = 3")
+            .execute(&mut fixture.connection)
+            .await?;
+        sqlx::query("DELETE FROM PlaylistTracks WHERE track_id = 1")
+            .execute(&mut fixture.connection)
+            .await?;
+        let library = read_library(&mut fixture.connection).await?;
+        let report = RekordboxExporter.export(&library, &profile)?;
+        let changes = report.changes.unwrap();
+        assert_eq!(changes.changed_tracks, vec!["3"]);
+        assert_eq!(changes.changed_playlists, vec!["Warmup"]);
+
+        let delta = read_to_string(&delta_file)?;
+        assert!(delta.contains(r#"<COLLECTION Entries="2">"#));
+        assert!(delta.contains(r#"Name="Renamed""#));
+        assert!(delta.contains(r#"TrackID="2""#));
+        assert!(delta.contains(r#"Name="Warmup""#));
+        assert!(!delta.contains(r#"Name="Techno""#));
+
+        Ok(())
+    }
+}
@@ -0,0 +1,111 @@
+use std::{
+    io::Write,
+    path::{Path, PathBuf},
+};
+
+use anyhow::{Context, Result};
+
+use super::{LIBRARY_SHEET, Sheet};
+use crate::export::output::AtomicFile;
+
+const UTF8_BOM: &[u8] = b"\xEF\xBB\xBF";
+
+/// Write the library sheet to `target` and every other sheet to a file next to it,
+/// e.g. `mixxx_library - Warmup.csv`.
+pub fn write(
+    target: &Path,
+    sheets: &[Sheet],
+    utf8_bom: bool,
+    backups: usize,
+) -> Result<Vec<PathBuf>> {
+    let mut files = Vec::new();
+    for sheet in sheets {
+        let path = if sheet.name == LIBRARY_SHEET {
+            target.to_path_buf()
+        } else {
+            sheet_path(target, &sheet.name)
+        };
+
+        let mut file = AtomicFile::create(&path)?;
+        if utf8_bom {
+            file.write_all(UTF8_BOM)?;
+        }
+        write_sheet(&mut file, sheet).context(format!("Failed to write {path:?}"))?;
+        file.commit(backups)?;
+
+        files.push(path);
+    }
+
+    Ok(files)
+}
+
+fn sheet_path(target: &Path, name: &str) -> PathBuf {
+    let stem = target
+        .file_stem()
+        .map(|stem| stem.to_string_lossy())
+        .unwrap_or_default();
+    let extension = target
+        .extension()
+        .map(|extension| extension.to_string_lossy())
+        .unwrap_or_default();
+
+    target.with_file_name(format!("{stem} - {name}.{extension}"))
+}
+
+/// Write a sheet as RFC 4180 CSV.
+/// Fields with commas, quotes or line breaks are quoted, everything else is written as UTF-8.
+pub fn write_sheet(writer: impl Write, sheet: &Sheet) -> Result<()> {
+    let mut writer = csv::Writer::from_writer(writer);
+    writer.write_record(&sheet.header)?;
+    for row in &sheet.rows {
+        writer.write_record(row.iter().map(|cell| cell.to_text()))?;
+    }
+    writer.flush()?;
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use pretty_assertions::assert_eq;
+
+    use super::{super::Cell, *};
+
+    #[test]
+    fn escape_fields() -> Result<()> {
+        let sheet = Sheet {
+            name: LIBRARY_SHEET.into(),
+            header: vec!["Artist", "Title", "BPM"],
+            rows: vec![
+                vec![
+                    Cell::Text("Björk, Sigur Rós".into()),
+                    Cell::Text("Say \"Hi\"\nTwice".into()),
+                    Cell::Number(92.5),
+                ],
+                vec![
+                    Cell::Text("坂本龍一".into()),
+                    Cell::Text("Plain".into()),
+                    Cell::Empty,
+                ],
+            ],
+        };
+
+        let mut output = Vec::new();
+        write_sheet(&mut output, &sheet)?;
+
+        assert_eq!(
+            String::from_utf8(output)?,
+            "Artist,Title,BPM\n\"Björk, Sigur Rós\",\"Say \"\"Hi\"\"\nTwice\",92.5\n坂本龍一,Plain,\n"
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn name_files_after_sheets() {
+        assert_eq!(
+            sheet_path(Path::new("/out/listing.csv"), "Warmup"),
+            PathBuf::from("/out/listing - Warmup.csv")
+        );
+    }
+}
@@ -0,0 +1,483 @@
+use std::collections::{BTreeMap, HashSet};
+
+use anyhow::Result;
+use log::info;
+use schemars::{JsonSchema, Schema};
+use serde_derive::Deserialize;
+
+use super::{Exporter, Profile, Report, options_schema, parse_options};
+use crate::{
+    key::{KeyNotation, format_key},
+    mixxx::library::{Library, Track},
+};
+
+/// Writes sheets as CSV files.
+pub mod csv;
+/// Writes sheets as an OpenDocument spreadsheet.
+#[cfg(feature = "ods")]
+pub mod ods;
+/// Writes sheets as an Excel workbook.
+#[cfg(feature = "xlsx")]
+pub mod xlsx;
+
+/// The name of the sheet that contains the whole library.
+const LIBRARY_SHEET: &str = "Library";
+/// Excel doesn't allow longer sheet names.
+const MAX_SHEET_NAME_LENGTH: usize = 31;
+
+/// A column of the track listing.
+#[derive(Deserialize, JsonSchema, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum Column {
+    Artist,
+    Title,
+    Album,
+    Genre,
+    Bpm,
+    /// The key in the configured `key_notation`.
+    Key,
+    /// The rating from 0 to 5 stars.
+    Rating,
+    /// How often the track has been played.
+    Plays,
+    DateAdded,
+    /// The names of all visible crates that contain the track.
+    Crates,
+    /// The names of all visible playlists that contain the track.
+    Playlists,
+    /// The location of the file, mapped to the `target_library_root`.
+    Path,
+}
+
+impl Column {
+    pub fn header(&self) -> &'static str {
+        match self {
+            Column::Artist => "Artist",
+            Column::Title => "Title",
+            Column::Album => "Album",
+            Column::Genre => "Genre",
+            Column::Bpm => "BPM",
+            Column::Key => "Key",
+            Column::Rating => "Rating",
+            Column::Plays => "Plays",
+            Column::DateAdded => "Date Added",
+            Column::Crates => "Crates",
+            Column::Playlists => "Playlists",
+            Column::Path => "Path",
+        }
+    }
+}
+
+fn default_columns() -> Vec<Column> {
+    vec![
+        Column::Artist,
+        Column::Title,
+        Column::Album,
+        Column::Genre,
+        Column::Bpm,
+        Column::Key,
+        Column::Rating,
+        Column::Plays,
+        Column::DateAdded,
+        Column::Crates,
+        Column::Playlists,
+        Column::Path,
+    ]
+}
+
+/// Options of the spreadsheet exporters.
+#[derive(Deserialize, JsonSchema, Debug)]
+#[serde(deny_unknown_fields)]
+pub struct SpreadsheetOptions {
+    /// The columns of the listing in their order.
+    #[serde(default = "default_columns")]
+    pub columns: Vec<Column>,
+    /// The notation in which keys are written.
+    #[serde(default)]
+    pub key_notation: KeyNotation,
+    /// Add a sheet for each visible playlist and crate.
+    /// CSV exports write an additional file per playlist instead.
+    #[serde(default)]
+    pub playlist_sheets: bool,
+    /// Start CSV files with a byte order mark, as Excel otherwise doesn't detect UTF-8.
+    /// Ignored by the other formats.
+    #[serde(default)]
+    pub utf8_bom: bool,
+}
+
+/// The file formats of the spreadsheet exporter.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SpreadsheetFormat {
+    Csv,
+    #[cfg(feature = "xlsx")]
+    Xlsx,
+    #[cfg(feature = "ods")]
+    Ods,
+}
+
+impl SpreadsheetFormat {
+    fn file_name(&self) -> &'static str {
+        match self {
+            SpreadsheetFormat::Csv => "mixxx_library.csv",
+            #[cfg(feature = "xlsx")]
+            SpreadsheetFormat::Xlsx => "mixxx_library.xlsx",
+            #[cfg(feature = "ods")]
+            SpreadsheetFormat::Ods => "mixxx_library.ods",
+        }
+    }
+}
+
+/// Writes a listing of all tracks, e.g. for people that only work with spreadsheets.
+/// Each [SpreadsheetFormat] is registered as its own format.
+pub struct SpreadsheetExporter(pub SpreadsheetFormat);
+
+impl Exporter for SpreadsheetExporter {
+    fn name(&self) -> &'static str {
+        match self.0 {
+            SpreadsheetFormat::Csv => "csv",
+            #[cfg(feature = "xlsx")]
+            SpreadsheetFormat::Xlsx => "xlsx",
+            #[cfg(feature = "ods")]
+            SpreadsheetFormat::Ods => "ods",
+        }
+    }
+
+    fn description(&self) -> &'static str {
+        match self.0 {
+            SpreadsheetFormat::Csv => "A track listing as CSV with configurable columns.",
+            #[cfg(feature = "xlsx")]
+            SpreadsheetFormat::Xlsx => {
+                "A track listing as Excel workbook with configurable columns."
+            }
+            #[cfg(feature = "ods")]
+            SpreadsheetFormat::Ods => {
+                "A track listing as OpenDocument spreadsheet with configurable columns."
+            }
+        }
+    }
+
+    fn config_schema(&self) -> Schema {
+        options_schema::<SpreadsheetOptions>()
+    }
+
+    fn export(&self, library: &Library, profile: &Profile) -> Result<Report> {
+        let options: SpreadsheetOptions = parse_options(profile)?;
+
+        let target_file = profile.output_path(self.0.file_name());
+        info!("Writing track listing to {target_file:?}");
+
+        let sheets = build_sheets(library, profile, &options);
+        let files = match self.0 {
+            SpreadsheetFormat::Csv => {
+                csv::write(&target_file, &sheets, options.utf8_bom, profile.backups)?
+            }
+            #[cfg(feature = "xlsx")]
+            SpreadsheetFormat::Xlsx => {
+                xlsx::write(&target_file, &sheets, profile.backups)?;
+                vec![target_file]
+            }
+            #[cfg(feature = "ods")]
+            SpreadsheetFormat::Ods => {
+                ods::write(&target_file, &sheets, profile.backups)?;
+                vec![target_file]
+            }
+        };
+
+        Ok(Report {
+            files,
+            tracks: library.tracks.len(),
+            changes: None,
+        })
+    }
+}
+
+/// A single value of the listing.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Cell {
+    Text(String),
+    Number(f64),
+    Empty,
+}
+
+impl Cell {
+    fn optional(value: Option<&str>) -> Cell {
+        match value {
+            Some(value) if !value.is_empty() => Cell::Text(value.to_string()),
+            _ => Cell::Empty,
+        }
+    }
+
+    /// The cell's value as it's shown in a plain text file.
+    pub fn to_text(&self) -> String {
+        match self {
+            Cell::Text(text) => text.clone(),
+            Cell::Number(number) => number.to_string(),
+            Cell::Empty => String::new(),
+        }
+    }
+}
+
+/// A table with a header row, which becomes a sheet or a file.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Sheet {
+    /// The unique name of the sheet, which is valid in all supported formats.
+    pub name: String,
+    pub header: Vec<&'static str>,
+    pub rows: Vec<Vec<Cell>>,
+}
+
+/// Build the library sheet and, if enabled, one sheet per visible playlist and crate.
+pub fn build_sheets(
+    library: &Library,
+    profile: &Profile,
+    options: &SpreadsheetOptions,
+) -> Vec<Sheet> {
+    let header: Vec<_> = options.columns.iter().map(Column::header).collect();
+
+    // Hidden playlists are internal to Mixxx, e.g. the Auto DJ queue.
+    let playlists: Vec<_> = library
+        .playlists
+        .iter()
+        .filter(|playlist| !playlist.hidden)
+        .map(|playlist| (playlist.name.as_str(), &playlist.track_ids))
+        .collect();
+    let crates: Vec<_> = library
+        .crates
+        .iter()
+        .filter(|mixxx_crate| !mixxx_crate.hidden)
+        .map(|mixxx_crate| (mixxx_crate.name.as_str(), &mixxx_crate.track_ids))
+        .collect();
+    let memberships = Memberships {
+        playlists: memberships(&playlists),
+        crates: memberships(&crates),
+    };
+
+    let row = |track: &Track| {
+        options
+            .columns
+            .iter()
+            .map(|column| cell(*column, track, profile, options, &memberships))
+            .collect::<Vec<_>>()
+    };
+
+    let mut names = SheetNames::default();
+    let mut sheets = vec![Sheet {
+        name: names.unique(LIBRARY_SHEET),
+        header: header.clone(),
+        rows: library.tracks.values().map(row).collect(),
+    }];
+
+    if options.playlist_sheets {
+        for (name, track_ids) in playlists.iter().chain(&crates) {
+            sheets.push(Sheet {
+                name: names.unique(name),
+                header: header.clone(),
+                rows: track_ids
+                    .iter()
+                    .filter_map(|id| library.tracks.get(id))
+                    .map(row)
+                    .collect(),
+            });
+        }
+    }
+
+    sheets
+}
+
+/// The names of the playlists and crates of each track.
+struct Memberships<'a> {
+    playlists: BTreeMap<usize, Vec<&'a str>>,
+    crates: BTreeMap<usize, Vec<&'a str>>,
+}
+
+fn memberships<'a>(lists: &[(&'a str, &Vec<usize>)]) -> BTreeMap<usize, Vec<&'a str>> {
+    let mut memberships: BTreeMap<usize, Vec<&str>> = BTreeMap::new();
+    for (name, track_ids) in lists {
+        for id in track_ids.iter() {
+            let names = memberships.entry(*id).or_default();
+            // A track can be in the same playlist multiple times.
+            if !names.contains(name) {
+                names.push(name);
+            }
+        }
+    }
+
+    memberships
+}
+
+fn cell(
+    column: Column,
+    track: &Track,
+    profile: &Profile,
+    options: &SpreadsheetOptions,
+    memberships: &Memberships,
+) -> Cell {
+    let join = |names: Option<&Vec<&str>>| {
+        Cell::Text(names.map(|names| names.join("; ")).unwrap_or_default())
+    };
+
+    match column {
+        Column::Artist => Cell::Text(track.artist.clone()),
+        Column::Title => Cell::Text(track.title.clone()),
+        Column::Album => Cell::optional(track.album.as_deref()),
+        Column::Genre => Cell::optional(track.genre.as_deref()),
+        Column::Bpm if track.technical_info.bpm > 0.0 => {
+            Cell::Number((track.technical_info.bpm * 100.0).round() / 100.0)
+        }
+        Column::Bpm => Cell::Empty,
+        Column::Key => Cell::optional(Some(&format_key(
+            &track.technical_info.key,
+            options.key_notation,
+        ))),
+        Column::Rating => Cell::Number(track.metadata.rating as f64),
+        Column::Plays => Cell::Number(track.metadata.timesplayed as f64),
+        Column::DateAdded => Cell::Text(
+            track
+                .metadata
+                .datetime_added
+                .format("%Y-%m-%d %H:%M:%S")
+                .to_string(),
+        ),
+        Column::Crates => join(memberships.crates.get(&track.id)),
+        Column::Playlists => join(memberships.playlists.get(&track.id)),
+        // Tracks outside of the source library root are listed with their original location.
+        Column::Path => Cell::Text(
+            profile
+                .map_location(&track.location.location)
+                .map(|path| path.display().to_string())
+                .unwrap_or_else(|_| track.location.location.clone()),
+        ),
+    }
+}
+
+/// Hands out sheet names that are unique and valid in Excel, LibreOffice and as file names.
+#[derive(Default)]
+struct SheetNames {
+    /// Lowercase, as sheet names are case insensitive.
+    taken: HashSet<String>,
+}
+
+impl SheetNames {
+    fn unique(&mut self, name: &str) -> String {
+        let name: String = name
+            .chars()
+            .map(|c| match c {
+                '[' | ']' | ':' | '*' | '?' | '/' | '\\' | '<' | '>' | '|' | '"' => '_',
+                c if c.is_control() => '_',
+                c => c,
+            })
+            .collect();
+        // Excel doesn't allow apostrophes around the name.
+        let name = name.trim().trim_matches('\'');
+        let name = if name.is_empty() { "Playlist" } else { name };
+
+        let mut candidate = truncate(name, MAX_SHEET_NAME_LENGTH);
+        let mut counter = 1;
+        // Excel reserves the name `History`.
+        while self.taken.contains(&candidate.to_lowercase())
+            || candidate.eq_ignore_ascii_case("history")
+        {
+            counter += 1;
+            let suffix = format!(" ({counter})");
+            candidate = truncate(name, MAX_SHEET_NAME_LENGTH - suffix.len()) + &suffix;
+        }
+        self.taken.insert(candidate.to_lowercase());
+
+        candidate
+    }
+}
+
+fn truncate(text: &str, max_chars: usize) -> String {
+    text.chars()
+        .take(max_chars)
+        .collect::<String>()
+        .trim_end()
+        .to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use pretty_assertions::assert_eq;
+    use rstest::rstest;
+
+    use super::*;
+    use crate::mixxx::{aggregator::read_library, fixture::sample_library};
+
+    #[rstest]
+    #[case(&["Library"], "Library", "Library (2)")]
+    #[case(&[], "Sets/2024:Summer", "Sets_2024_Summer")]
+    #[case(&["Sets_2024_Summer"], "Sets/2024:Summer", "Sets_2024_Summer (2)")]
+    #[case(&["SETS_2024_SUMMER", "sets_2024_summer (2)"], "Sets/2024:Summer", "Sets_2024_Summer (3)")]
+    fn unique_sheet_names(#[case] taken: &[&str], #[case] name: &str, #[case] expected: &str) {
+        let mut names = SheetNames::default();
+        for name in taken {
+            names.unique(name);
+        }
+
+        assert_eq!(names.unique(name), expected);
+    }
+
+    #[test]
+    fn shorten_long_sheet_names() {
+        let mut names = SheetNames::default();
+        let name = "A very long playlist name with ünicode";
+        assert_eq!(names.unique(name), "A very long playlist name with");
+        assert_eq!(names.unique(name), "A very long playlist name w (2)");
+        assert_eq!(names.unique("History"), "History (2)");
+        assert_eq!(names.unique("'[]'"), "__");
+    }
+
+    #[tokio::test]
+    async fn list_sample_library() -> Result<()> {
+        let mut fixture = sample_library().await?;
+        let profile = fixture.config().default_profile("csv");
+        let library = read_library(&mut fixture.connection).await?;
+        let options = SpreadsheetOptions {
+            columns: vec![
+                Column::Title,
+                Column::Bpm,
+                Column::Key,
+                Column::Crates,
+                Column::Playlists,
+                Column::Path,
+            ],
+            key_notation: KeyNotation::OpenKey,
+            playlist_sheets: true,
+            utf8_bom: false,
+        };
+
+        let sheets = build_sheets(&library, &profile, &options);
+
+        // The hidden playlist doesn't get a sheet.
+        let names: Vec<_> = sheets.iter().map(|sheet| sheet.name.as_str()).collect();
+        assert_eq!(names, vec!["Library", "Warmup", "Techno"]);
+        assert_eq!(
+            sheets[0].header,
+            vec!["Title", "BPM", "Key", "Crates", "Playlists", "Path"]
+        );
+        assert_eq!(
+            sheets[0].rows[0],
+            vec![
+                Cell::Text("First Track".into()),
+                Cell::Number(128.0),
+                Cell::Text("1m".into()),
+                Cell::Text("Techno".into()),
+                Cell::Text("Warmup".into()),
+                Cell::Text(
+                    profile
+                        .map_location(&library.tracks[&1].location.location)?
+                        .display()
+                        .to_string()
+                ),
+            ]
+        );
+        assert_eq!(sheets[0].rows[2][1], Cell::Empty);
+        assert_eq!(sheets[0].rows[2][2], Cell::Empty);
+
+        // Playlist sheets keep the order of the playlist.
+        let titles: Vec<_> = sheets[1].rows.iter().map(|row| row[0].to_text()).collect();
+        assert_eq!(titles, vec!["Live Drums", "First Track"]);
+
+        Ok(())
+    }
+}
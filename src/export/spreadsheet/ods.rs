@@ -0,0 +1,173 @@
+use std::{
+    io::{Cursor, Write},
+    path::Path,
+};
+
+use anyhow::{Context, Result};
+use quick_xml::{
+    Writer,
+    events::{BytesDecl, BytesEnd, BytesStart, BytesText, Event},
+};
+use zip::{CompressionMethod, ZipWriter, write::SimpleFileOptions};
+
+use super::{Cell, Sheet};
+use crate::export::output::AtomicFile;
+
+const MIME_TYPE: &str = "application/vnd.oasis.opendocument.spreadsheet";
+
+const MANIFEST: &str = r#"<?xml version="1.0" encoding="UTF-8"?>
+<manifest:manifest xmlns:manifest="urn:oasis:names:tc:opendocument:xmlns:manifest:1.0" manifest:version="1.2">
+ <manifest:file-entry manifest:full-path="/" manifest:version="1.2" manifest:media-type="application/vnd.oasis.opendocument.spreadsheet"/>
+ <manifest:file-entry manifest:full-path="content.xml" manifest:media-type="text/xml"/>
+</manifest:manifest>
+"#;
+
+/// Write all sheets into a single OpenDocument spreadsheet.
+pub fn write(target: &Path, sheets: &[Sheet], backups: usize) -> Result<()> {
+    let bytes = to_bytes(sheets)?;
+
+    let mut file = AtomicFile::create(target)?;
+    file.write_all(&bytes)?;
+    file.commit(backups)
+}
+
+/// An OpenDocument file is a zip archive, whose first entry has to be the uncompressed
+/// mime type, so the format can be detected by its first bytes.
+fn to_bytes(sheets: &[Sheet]) -> Result<Vec<u8>> {
+    let mut zip = ZipWriter::new(Cursor::new(Vec::new()));
+    let stored = SimpleFileOptions::default().compression_method(CompressionMethod::Stored);
+    let deflated = SimpleFileOptions::default().compression_method(CompressionMethod::Deflated);
+
+    zip.start_file("mimetype", stored)?;
+    zip.write_all(MIME_TYPE.as_bytes())?;
+    zip.start_file("META-INF/manifest.xml", deflated)?;
+    zip.write_all(MANIFEST.as_bytes())?;
+    zip.start_file("content.xml", deflated)?;
+    write_content(&mut zip, sheets).context("Failed to write ods content")?;
+
+    Ok(zip.finish()?.into_inner())
+}
+
+fn write_content(inner: impl Write, sheets: &[Sheet]) -> Result<()> {
+    let mut writer = Writer::new(inner);
+    writer.write_event(Event::Decl(BytesDecl::new("1.0", Some("UTF-8"), None)))?;
+
+    let root = BytesStart::new("office:document-content").with_attributes([
+        (
+            "xmlns:office",
+            "urn:oasis:names:tc:opendocument:xmlns:office:1.0",
+        ),
+        (
+            "xmlns:table",
+            "urn:oasis:names:tc:opendocument:xmlns:table:1.0",
+        ),
+        (
+            "xmlns:text",
+            "urn:oasis:names:tc:opendocument:xmlns:text:1.0",
+        ),
+        ("office:version", "1.2"),
+    ]);
+    writer.write_event(Event::Start(root))?;
+    writer.write_event(Event::Start(BytesStart::new("office:body")))?;
+    writer.write_event(Event::Start(BytesStart::new("office:spreadsheet")))?;
+
+    for sheet in sheets {
+        let table =
+            BytesStart::new("table:table").with_attributes([("table:name", sheet.name.as_str())]);
+        writer.write_event(Event::Start(table))?;
+
+        let header = sheet
+            .header
+            .iter()
+            .map(|header| Cell::Text(header.to_string()))
+            .collect::<Vec<_>>();
+        for row in std::iter::once(&header).chain(&sheet.rows) {
+            writer.write_event(Event::Start(BytesStart::new("table:table-row")))?;
+            for cell in row {
+                write_cell(&mut writer, cell)?;
+            }
+            writer.write_event(Event::End(BytesEnd::new("table:table-row")))?;
+        }
+
+        writer.write_event(Event::End(BytesEnd::new("table:table")))?;
+    }
+
+    writer.write_event(Event::End(BytesEnd::new("office:spreadsheet")))?;
+    writer.write_event(Event::End(BytesEnd::new("office:body")))?;
+    writer.write_event(Event::End(BytesEnd::new("office:document-content")))?;
+
+    Ok(())
+}
+
+fn write_cell<W: Write>(writer: &mut Writer<W>, cell: &Cell) -> Result<()> {
+    let number;
+    let element = match cell {
+        Cell::Text(_) => {
+            BytesStart::new("table:table-cell").with_attributes([("office:value-type", "string")])
+        }
+        Cell::Number(value) => {
+            number = value.to_string();
+            BytesStart::new("table:table-cell").with_attributes([
+                ("office:value-type", "float"),
+                ("office:value", number.as_str()),
+            ])
+        }
+        Cell::Empty => {
+            writer.write_event(Event::Empty(BytesStart::new("table:table-cell")))?;
+            return Ok(());
+        }
+    };
+
+    writer.write_event(Event::Start(element))?;
+    // Every line of a cell is its own paragraph.
+    for line in cell.to_text().split('\n') {
+        writer.write_event(Event::Start(BytesStart::new("text:p")))?;
+        writer.write_event(Event::Text(BytesText::new(line)))?;
+        writer.write_event(Event::End(BytesEnd::new("text:p")))?;
+    }
+    writer.write_event(Event::End(BytesEnd::new("table:table-cell")))?;
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Read;
+
+    use pretty_assertions::assert_eq;
+    use zip::ZipArchive;
+
+    use super::*;
+
+    #[test]
+    fn write_sheets() -> Result<()> {
+        let sheet = Sheet {
+            name: "Library".into(),
+            header: vec!["Artist", "BPM", "Key"],
+            rows: vec![vec![
+                Cell::Text("Tom & Jerry <Live>\nEncore".into()),
+                Cell::Number(92.5),
+                Cell::Empty,
+            ]],
+        };
+
+        let bytes = to_bytes(&[sheet])?;
+
+        let mut archive = ZipArchive::new(Cursor::new(bytes))?;
+        assert_eq!(archive.by_index(0)?.name(), "mimetype");
+        let mut content = String::new();
+        archive
+            .by_name("content.xml")?
+            .read_to_string(&mut content)?;
+        assert!(content.contains(r#"<table:table table:name="Library">"#));
+        assert!(
+            content
+                .contains("<text:p>Tom &amp; Jerry &lt;Live&gt;</text:p><text:p>Encore</text:p>")
+        );
+        assert!(content.contains(
+            r#"<table:table-cell office:value-type="float" office:value="92.5"><text:p>92.5</text:p></table:table-cell><table:table-cell/>"#
+        ));
+
+        Ok(())
+    }
+}
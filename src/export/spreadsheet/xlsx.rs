@@ -0,0 +1,90 @@
+use std::{io::Write, path::Path};
+
+use anyhow::{Context, Result};
+use rust_xlsxwriter::{Format, Workbook};
+
+use super::{Cell, Sheet};
+use crate::export::output::AtomicFile;
+
+/// Write all sheets into a single workbook.
+pub fn write(target: &Path, sheets: &[Sheet], backups: usize) -> Result<()> {
+    let bytes = to_bytes(sheets)?;
+
+    let mut file = AtomicFile::create(target)?;
+    file.write_all(&bytes)?;
+    file.commit(backups)
+}
+
+fn to_bytes(sheets: &[Sheet]) -> Result<Vec<u8>> {
+    let mut workbook = Workbook::new();
+    let bold = Format::new().set_bold();
+
+    for sheet in sheets {
+        let worksheet = workbook.add_worksheet();
+        worksheet
+            .set_name(&sheet.name)
+            .context(format!("Invalid sheet name '{}'", sheet.name))?;
+
+        for (column, header) in sheet.header.iter().enumerate() {
+            worksheet.write_string_with_format(0, column as u16, *header, &bold)?;
+        }
+        for (row, cells) in sheet.rows.iter().enumerate() {
+            let row = row as u32 + 1;
+            for (column, cell) in cells.iter().enumerate() {
+                let column = column as u16;
+                match cell {
+                    Cell::Text(text) => worksheet.write_string(row, column, text)?,
+                    Cell::Number(number) => worksheet.write_number(row, column, *number)?,
+                    Cell::Empty => continue,
+                };
+            }
+        }
+
+        // Keep the header visible while scrolling.
+        worksheet.set_freeze_panes(1, 0)?;
+        worksheet.autofit();
+    }
+
+    workbook
+        .save_to_buffer()
+        .context("Failed to create xlsx workbook")
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::{Cursor, Read};
+
+    use zip::ZipArchive;
+
+    use super::*;
+
+    #[test]
+    fn write_sheets() -> Result<()> {
+        let sheet = |name: &str| Sheet {
+            name: name.into(),
+            header: vec!["Artist", "BPM"],
+            rows: vec![vec![
+                Cell::Text("Björk, Sigur Rós".into()),
+                Cell::Number(92.5),
+            ]],
+        };
+
+        let bytes = to_bytes(&[sheet("Library"), sheet("Warmup")])?;
+
+        let mut archive = ZipArchive::new(Cursor::new(bytes))?;
+        let mut workbook = String::new();
+        archive
+            .by_name("xl/workbook.xml")?
+            .read_to_string(&mut workbook)?;
+        assert!(workbook.contains(r#"name="Library""#));
+        assert!(workbook.contains(r#"name="Warmup""#));
+
+        let mut strings = String::new();
+        archive
+            .by_name("xl/sharedStrings.xml")?
+            .read_to_string(&mut strings)?;
+        assert!(strings.contains("Björk, Sigur Rós"));
+
+        Ok(())
+    }
+}
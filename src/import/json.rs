@@ -0,0 +1,199 @@
+use std::{
+    collections::BTreeMap,
+    fs::File,
+    io::{BufReader, Read, stdin},
+};
+
+use anyhow::{Context, Result, bail};
+use log::warn;
+use schemars::{JsonSchema, Schema, schema_for};
+use serde_derive::{Deserialize, Serialize};
+
+use super::{ImportFuture, Importer};
+use crate::{
+    config::Config,
+    mixxx::library::{Crate, Library, Playlist, Track},
+};
+
+/// The version of the json library format.
+///
+/// It's increased whenever the format changes in a way that older readers cannot handle.
+/// Files without a version have been written before the format was versioned and are read as
+/// version 1.
+pub const LIBRARY_FILE_VERSION: u32 = 1;
+
+/// The json representation of a [Library], as it's written by the `json` exporter.
+///
+/// The JSON Schema of this format is saved in `schema/mixxx_library.schema.json`.
+#[derive(Serialize, Deserialize, JsonSchema, Debug)]
+#[schemars(
+    title = "Mixxx library",
+    description = "A Mixxx library as it's exported by the json format of mixxx-library-exporter."
+)]
+pub struct LibraryFile {
+    /// The version of this format. See [LIBRARY_FILE_VERSION].
+    #[serde(default)]
+    #[schemars(
+        description = "The version of this format. Files without a version are read as version 1."
+    )]
+    pub version: u32,
+    /// All tracks by their Mixxx id.
+    pub tracks: BTreeMap<usize, Track>,
+    pub playlists: Vec<Playlist>,
+    pub crates: Vec<Crate>,
+}
+
+/// The same as [LibraryFile], but borrows the library, so it can be written without a copy.
+#[derive(Serialize)]
+pub struct LibraryFileRef<'a> {
+    pub version: u32,
+    pub tracks: &'a BTreeMap<usize, Track>,
+    pub playlists: &'a [Playlist],
+    pub crates: &'a [Crate],
+}
+
+impl<'a> From<&'a Library> for LibraryFileRef<'a> {
+    fn from(library: &'a Library) -> Self {
+        LibraryFileRef {
+            version: LIBRARY_FILE_VERSION,
+            tracks: &library.tracks,
+            playlists: &library.playlists,
+            crates: &library.crates,
+        }
+    }
+}
+
+/// The JSON Schema of the json library format.
+pub fn library_file_schema() -> Schema {
+    schema_for!(LibraryFile)
+}
+
+/// Only the version of a json library, which is checked before the rest is parsed.
+#[derive(Deserialize)]
+struct LibraryFileVersion {
+    #[serde(default)]
+    version: u32,
+}
+
+/// Parse a json library and check that its version is supported.
+///
+/// The version is checked first, so files of newer versions are rejected with a clear error,
+/// even if their structure can't be parsed anymore.
+pub fn read_library_file(mut reader: impl Read) -> Result<Library> {
+    let mut json = Vec::new();
+    reader.read_to_end(&mut json)?;
+
+    let LibraryFileVersion { version } = serde_json::from_slice(&json)?;
+    match version {
+        0 => warn!("The json library doesn't have a version. Reading it as version 1."),
+        LIBRARY_FILE_VERSION => {}
+        version => bail!(
+            "Version {version} of the json library isn't supported. \
+            The newest supported version is {LIBRARY_FILE_VERSION}, \
+            please update mixxx-library-exporter to read this file."
+        ),
+    }
+
+    let file: LibraryFile = serde_json::from_slice(&json)?;
+
+    Ok(Library {
+        tracks: file.tracks,
+        playlists: file.playlists,
+        crates: file.crates,
+    })
+}
+
+/// Reads a library that has been exported by the `json` exporter.
+/// The source `-` reads the library from stdin.
+pub struct JsonImporter;
+
+impl Importer for JsonImporter {
+    fn name(&self) -> &'static str {
+        "json"
+    }
+
+    fn description(&self) -> &'static str {
+        "A library that has been exported with the json format. Use `-` to read from stdin."
+    }
+
+    fn extensions(&self) -> &'static [&'static str] {
+        &["json"]
+    }
+
+    fn import<'a>(&'a self, source: &'a str, _config: &'a Config) -> ImportFuture<'a> {
+        Box::pin(async move {
+            if source == "-" {
+                return read_library_file(stdin().lock())
+                    .context("Failed to read json library from stdin");
+            }
+
+            let file = File::open(source).context(format!("Failed to open {source}"))?;
+            read_library_file(BufReader::new(file))
+                .context(format!("Failed to read json library {source}"))
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs::create_dir_all;
+
+    use pretty_assertions::assert_eq;
+    use rstest::rstest;
+
+    use super::*;
+    use crate::{
+        export::{Exporter, json::JsonExporter},
+        mixxx::{
+            aggregator::read_library,
+            fixture::{assert_schema, sample_library},
+        },
+    };
+
+    #[tokio::test]
+    async fn read_exported_library() -> Result<()> {
+        let mut fixture = sample_library().await?;
+        let config = fixture.config();
+        let library = read_library(&mut fixture.connection).await?;
+
+        let profile = config.default_profile("json");
+        create_dir_all(&profile.target_directory)?;
+        let report = JsonExporter.export(&library, &profile)?;
+        let source = report.files[0].to_string_lossy().to_string();
+
+        let imported = JsonImporter.import(&source, &config).await?;
+        assert_eq!(
+            serde_json::to_value(&imported)?,
+            serde_json::to_value(&library)?
+        );
+
+        Ok(())
+    }
+
+    #[rstest]
+    #[case::unversioned(r#"{"tracks": {}, "playlists": [], "crates": []}"#, true)]
+    #[case::current(r#"{"version": 1, "tracks": {}, "playlists": [], "crates": []}"#, true)]
+    #[case::newer(
+        r#"{"version": 2, "tracks": {}, "playlists": [], "crates": []}"#,
+        false
+    )]
+    fn check_version(#[case] json: &str, #[case] supported: bool) {
+        assert_eq!(read_library_file(json.as_bytes()).is_ok(), supported);
+    }
+
+    #[test]
+    fn reject_newer_layout() {
+        // A future version may change the structure, which must still report the version.
+        let json = r#"{"version": 2, "tracks": [{"id": "new"}]}"#;
+        let err = read_library_file(json.as_bytes()).unwrap_err();
+        assert!(
+            err.to_string()
+                .starts_with("Version 2 of the json library isn't supported.")
+        );
+    }
+
+    #[test]
+    fn schema_is_up_to_date() {
+        assert_schema("mixxx_library.schema.json", &library_file_schema());
+    }
+}
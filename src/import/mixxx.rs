@@ -0,0 +1,34 @@
+use std::time::Duration;
+
+use super::{ImportFuture, Importer};
+use crate::{config::Config, db::new_connection, mixxx::aggregator::read_library};
+
+/// Reads the library from a `mixxxdb.sqlite` database.
+pub struct MixxxImporter;
+
+impl Importer for MixxxImporter {
+    fn name(&self) -> &'static str {
+        "mixxx"
+    }
+
+    fn description(&self) -> &'static str {
+        "The mixxxdb.sqlite database of Mixxx 2.2 or newer."
+    }
+
+    fn extensions(&self) -> &'static [&'static str] {
+        &["sqlite", "db"]
+    }
+
+    fn import<'a>(&'a self, source: &'a str, config: &'a Config) -> ImportFuture<'a> {
+        Box::pin(async move {
+            let mut db = new_connection(
+                source,
+                config.mixxx_db_access,
+                Duration::from_millis(config.mixxx_db_busy_timeout),
+            )
+            .await?;
+
+            read_library(&mut db.connection).await
+        })
+    }
+}
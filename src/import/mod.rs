@@ -0,0 +1,30 @@
+use std::{future::Future, pin::Pin};
+
+use anyhow::Result;
+
+use crate::{config::Config, mixxx::library::Library};
+
+/// Read a library from a json export.
+pub mod json;
+/// Read the library from a Mixxx database.
+pub mod mixxx;
+
+/// The future that's returned by [Importer::import].
+pub type ImportFuture<'a> = Pin<Box<dyn Future<Output = Result<Library>> + Send + 'a>>;
+
+/// A source format, from which a library can be read.
+///
+/// Importers are registered in the [Registry](crate::registry::Registry).
+pub trait Importer: Send + Sync {
+    /// The unique name of the format.
+    fn name(&self) -> &'static str;
+
+    /// A short, human readable description of the format.
+    fn description(&self) -> &'static str;
+
+    /// The file extensions of sources that're read by this importer, e.g. `json`.
+    fn extensions(&self) -> &'static [&'static str];
+
+    /// Read the library from `source`, e.g. the path to a database.
+    fn import<'a>(&'a self, source: &'a str, config: &'a Config) -> ImportFuture<'a>;
+}
@@ -0,0 +1,172 @@
+use std::fmt;
+
+use schemars::JsonSchema;
+use serde_derive::{Deserialize, Serialize};
+
+/// The musical names of all keys in the order of the Camelot wheel, i.e. `1A`, `1B`, `2A`, ...
+/// These are the names that're used by rekordbox.
+const MUSICAL_KEYS: [&str; 24] = [
+    "G#m/Abm", "B", "Ebm", "Gb/F#", "Bbm/A#m", "Db", "Fm", "Ab/G#", "Cm", "Eb/D#", "Gm", "Bb",
+    "Dm", "F", "Am", "C", "Em", "G", "Bm", "D", "F#m", "A", "C#m/Dbm", "E",
+];
+
+/// The notations in which keys can be written.
+#[derive(Serialize, Deserialize, JsonSchema, Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum KeyNotation {
+    /// The Camelot wheel, e.g. `8A` for A minor.
+    #[default]
+    Camelot,
+    /// The Open Key notation, e.g. `1m` for A minor.
+    OpenKey,
+    /// The musical name, e.g. `Am`.
+    Musical,
+}
+
+/// A musical key, which is saved as its position on the Camelot wheel.
+/// Keys are ordered like the wheel, i.e. `1A`, `1B`, `2A`, ...
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Key {
+    /// The number on the Camelot wheel from 1 to 12.
+    number: u8,
+    major: bool,
+}
+
+impl Key {
+    /// Parse a key in any of the supported notations.
+    /// Mixxx usually saves keys in the Camelot notation, but that depends on its settings.
+    pub fn parse(text: &str) -> Option<Key> {
+        let text = text.trim();
+        if text.is_empty() {
+            return None;
+        }
+
+        // Camelot and Open Key are a number followed by a single letter.
+        let (suffix_start, _) = text.char_indices().last()?;
+        let (number, suffix) = text.split_at(suffix_start);
+        if let Ok(number) = number.parse::<u8>()
+            && (1..=12).contains(&number)
+        {
+            match suffix {
+                "A" | "a" => {
+                    return Some(Key {
+                        number,
+                        major: false,
+                    });
+                }
+                "B" | "b" => {
+                    return Some(Key {
+                        number,
+                        major: true,
+                    });
+                }
+                // Open Key is shifted by 7 steps, i.e. `1m` is `8A`.
+                "m" | "d" => {
+                    return Some(Key {
+                        number: (number + 6) % 12 + 1,
+                        major: suffix == "d",
+                    });
+                }
+                _ => {}
+            }
+        }
+
+        // Musical names may contain alternatives, e.g. `G#m/Abm`.
+        let index = MUSICAL_KEYS
+            .iter()
+            .position(|names| names.split('/').any(|name| name == text))?;
+        Some(Key {
+            number: index as u8 / 2 + 1,
+            major: index % 2 == 1,
+        })
+    }
+
+    /// The key in the Camelot notation, e.g. `8A`.
+    pub fn camelot(&self) -> String {
+        let letter = if self.major { 'B' } else { 'A' };
+        format!("{}{letter}", self.number)
+    }
+
+    /// The key in the Open Key notation, e.g. `1m`.
+    pub fn open_key(&self) -> String {
+        let letter = if self.major { 'd' } else { 'm' };
+        format!("{}{letter}", (self.number + 4) % 12 + 1)
+    }
+
+    /// The musical name of the key, e.g. `Am`.
+    pub fn musical(&self) -> &'static str {
+        MUSICAL_KEYS[(self.number as usize - 1) * 2 + self.major as usize]
+    }
+
+    pub fn format(&self, notation: KeyNotation) -> String {
+        match notation {
+            KeyNotation::Camelot => self.camelot(),
+            KeyNotation::OpenKey => self.open_key(),
+            KeyNotation::Musical => self.musical().to_string(),
+        }
+    }
+}
+
+impl fmt::Display for Key {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.camelot())
+    }
+}
+
+/// Many programs use something called the Camelot wheel to categorize tonal keys.
+/// Rekordbox uses the musical names instead, which we have to map to.
+/// Returns an empty string for unknown keys.
+pub fn translate_key(mixxx_key: &str) -> &'static str {
+    Key::parse(mixxx_key)
+        .map(|key| key.musical())
+        .unwrap_or_default()
+}
+
+/// Write a key of the Mixxx library in the given notation.
+/// Keys that cannot be parsed are kept as they are.
+pub fn format_key(mixxx_key: &str, notation: KeyNotation) -> String {
+    match Key::parse(mixxx_key) {
+        Some(key) => key.format(notation),
+        None => mixxx_key.to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use pretty_assertions::assert_eq;
+    use rstest::rstest;
+
+    use super::*;
+
+    #[rstest]
+    #[case("8A", "8A", "1m", "Am")]
+    #[case("8B", "8B", "1d", "C")]
+    #[case("1A", "1A", "6m", "G#m/Abm")]
+    #[case("12B", "12B", "5d", "E")]
+    #[case("1m", "8A", "1m", "Am")]
+    #[case("6d", "1B", "6d", "B")]
+    #[case("Abm", "1A", "6m", "G#m/Abm")]
+    #[case("F#", "2B", "7d", "Gb/F#")]
+    fn convert_notations(
+        #[case] input: &str,
+        #[case] camelot: &str,
+        #[case] open_key: &str,
+        #[case] musical: &str,
+    ) {
+        let key = Key::parse(input).unwrap();
+        assert_eq!(key.format(KeyNotation::Camelot), camelot);
+        assert_eq!(key.format(KeyNotation::OpenKey), open_key);
+        assert_eq!(key.format(KeyNotation::Musical), musical);
+    }
+
+    #[rstest]
+    #[case("")]
+    #[case("13A")]
+    #[case("0B")]
+    #[case("H")]
+    #[case("A♭")]
+    fn reject_unknown_keys(#[case] input: &str) {
+        assert_eq!(Key::parse(input), None);
+        assert_eq!(translate_key(input), "");
+    }
+}
@@ -0,0 +1,135 @@
+//! Export a Mixxx library into the formats of other DJ software.
+//!
+//! The usual flow is to open the Mixxx database with [new_connection], read it into a
+//! [Library] with [read_library] and hand that library to one of the converters, e.g.
+//! [write_rekordbox_library].
+//!
+//! All formats are also available as [Importer]s and [Exporter]s via the [Registry], which is
+//! what the binary uses.
+//!
+//! Only the items that're re-exported here and the [rekordbox] module are part of the public
+//! API. Every type that appears in one of their signatures or fields is re-exported as well,
+//! e.g. to build a [Library] by hand. Everything else is an implementation detail.
+
+/// Colour handling shared by all exporters.
+pub(crate) mod color;
+/// Configuration file.
+pub(crate) mod config;
+/// Cover art extraction.
+pub(crate) mod cover_art;
+/// Low-level DB related logic
+pub(crate) mod db;
+/// Comparison of two libraries.
+pub(crate) mod diff;
+/// Detection of tracks that exist multiple times.
+pub(crate) mod duplicates;
+/// The exporter interface and all built-in export formats.
+pub(crate) mod export;
+/// The importer interface and all built-in import formats.
+pub(crate) mod import;
+/// Musical keys and their notations.
+pub(crate) mod key;
+/// Merging the libraries of multiple Mixxx databases.
+pub(crate) mod merge;
+/// All mixxx facing logic.
+pub(crate) mod mixxx;
+/// Lookup of all known import and export formats.
+pub(crate) mod registry;
+/// Rekordbox related logic.
+pub mod rekordbox;
+/// Statistics and health report of a library.
+pub(crate) mod stats;
+/// Copying the music files to the export target.
+pub(crate) mod sync;
+/// Reading metadata from the audio files' tags.
+pub(crate) mod tags;
+/// Checks of the music files and their exported paths.
+pub(crate) mod validate;
+
+pub use color::RgbColor;
+pub use config::{Config, ProfileConfig};
+pub use cover_art::{CoverArtReport, export_cover_art};
+pub use db::{Database, DbAccess, new_connection};
+pub use diff::{
+    CueMove,
+    CueSummary,
+    FieldChange,
+    LibraryDiff,
+    PlaylistDiff,
+    TrackDiff,
+    TrackSummary,
+    diff_libraries,
+};
+pub use duplicates::{
+    DuplicateGroup,
+    DuplicateOptions,
+    DuplicateReason,
+    DuplicateTrack,
+    find_duplicates,
+    write_playlist as write_duplicates_playlist,
+};
+pub use export::{
+    Exporter,
+    Profile,
+    Report,
+    manifest::Changes,
+    options_schema,
+    parse_options,
+    public_json::schema::{
+        document_schema as public_json_schema,
+        record_schema as public_json_record_schema,
+    },
+};
+pub use import::{
+    ImportFuture,
+    Importer,
+    json::{LIBRARY_FILE_VERSION, LibraryFile, library_file_schema, read_library_file},
+};
+pub use key::translate_key;
+pub use merge::{CuePrecedence, MergeSource, MixxxSource, merge_libraries};
+/// Synthetic Mixxx libraries for tests, which are written into temporary databases.
+/// Requires the `test-fixtures` feature.
+#[cfg(feature = "test-fixtures")]
+pub use mixxx::fixture;
+pub use mixxx::{
+    aggregator::read_library,
+    helper::CueType,
+    library::{
+        Beats,
+        CoverArt,
+        CoverArtSource,
+        CoverArtType,
+        Crate,
+        Library,
+        Playlist,
+        Track,
+        TrackLocation,
+        TrackMetadata,
+        TrackTechnicalInfo,
+    },
+    schema::{beats, cue::Cue},
+};
+pub use registry::Registry;
+pub use rekordbox::{mixxx_to_rekordbox, write_rekordbox_library};
+pub use stats::{
+    Count,
+    DeletedTracks,
+    LibraryStats,
+    PlayCount,
+    html::write_report as write_stats_report,
+    library_stats,
+};
+pub use sync::{SyncCompare, SyncConfig, SyncReport, SyncedLibrary, sync_files};
+pub use tags::{
+    merge::{TagConflict, TagMergeReport, TagPrecedence, merge_file_tags},
+    writer::{TagWriteReport, TagWriterOptions, write_library_tags},
+};
+pub use validate::{
+    InvalidPath,
+    PathProblem,
+    ProfileReport,
+    TargetFilesystem,
+    TrackPath,
+    ValidationReport,
+    validate_library,
+};
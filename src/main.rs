@@ -1,27 +1,33 @@
-use std::{
-    fs::{File, remove_file},
-    io::Write,
-};
-
-use anyhow::{Context, Result};
+use anyhow::{Context, Result, bail};
 use clap::Parser;
-use cli::CliArguments;
-use log::{LevelFilter, info};
+use cli::{CliArguments, SubCommand};
+use log::{LevelFilter, info, warn};
+use mixxx_library_exporter::{
+    Changes,
+    Config,
+    DuplicateOptions,
+    Library,
+    MergeSource,
+    Profile,
+    Registry,
+    TagWriteReport,
+    TagWriterOptions,
+    diff_libraries,
+    export_cover_art,
+    find_duplicates,
+    library_stats,
+    merge_file_tags,
+    merge_libraries,
+    sync_files,
+    validate_library,
+    write_duplicates_playlist,
+    write_library_tags,
+    write_stats_report,
+};
 use pretty_env_logger::env_logger::Builder;
-use rekordbox::mixxx_to_rekordbox;
-
-use crate::{config::Config, mixxx::aggregator::read_library};
 
 /// Commandline argument parsing
 mod cli;
-/// Configuration file.
-mod config;
-/// Low-level DB related logic
-mod db;
-/// All mixxx facing logic.
-mod mixxx;
-/// Rekordbox related logic.
-mod rekordbox;
 
 #[tokio::main]
 async fn main() -> Result<()> {
@@ -31,56 +37,337 @@ async fn main() -> Result<()> {
     let opt = CliArguments::parse();
 
     // Initalize everything
-    init_app(opt.verbose)?;
+    init_app(opt.verbose, opt.jobs)?;
+
+    let registry = Registry::default();
+    if let Some(SubCommand::Formats) = opt.cmd {
+        return print_formats(&registry);
+    }
 
     let config = Config::read().context("Failed to read config file")?;
     config.validate()?;
 
-    // Read the mixxx library and convert it into our own clean format.
-    let mut con = db::new_connection(&config.mixxx_db).await?;
-    let library = read_library(&mut con).await?;
+    if let Some(SubCommand::Diff { old, new, json }) = &opt.cmd {
+        let old_library = registry.importer_for(old)?.import(old, &config).await?;
+        let new_library = registry.importer_for(new)?.import(new, &config).await?;
+
+        let diff = diff_libraries(&old_library, &new_library);
+        if *json {
+            println!("{}", serde_json::to_string_pretty(&diff)?);
+        } else {
+            print!("{diff}");
+        }
 
-    // Json export logic.
-    if opt.json_export {
-        // Get the target path for the json file.
-        let json_target_file = config.target_directory().join("mixxx_library.json");
+        return Ok(());
+    }
 
-        // Remove any existing json file
-        if json_target_file.exists() {
-            info!("Removing existing json library file at: {json_target_file:?}");
-            remove_file(&json_target_file)?;
+    // The `-j` flag predates profiles and only exports the json library.
+    let profiles = if opt.json_export {
+        vec![config.default_profile("json")]
+    } else {
+        config.profiles()
+    };
+    // Fail early on unknown formats, before the library is read.
+    for profile in &profiles {
+        registry.exporter(&profile.format)?;
+    }
+
+    // Read the library and convert it into our own clean format.
+    let mut library = import_library(&opt, &config, &registry).await?;
+
+    if let Some(SubCommand::Stats { json, html }) = opt.cmd {
+        let stats = library_stats(&library);
+        if json {
+            println!("{}", serde_json::to_string_pretty(&stats)?);
+        } else {
+            print!("{stats}");
+        }
+        if html {
+            let path = write_stats_report(&stats, &config.target_directory())?;
+            println!("Wrote report to {}", path.to_string_lossy());
         }
 
-        // Export the library.
-        let library_json = serde_json::to_string(&library)?;
-        let mut file = File::create(json_target_file)?;
-        file.write_all(library_json.as_bytes())?;
+        return Ok(());
+    }
+
+    if let Some(SubCommand::Validate { filesystem, json }) = opt.cmd {
+        let mut profiles = profiles;
+        for profile in &mut profiles {
+            profile.target_filesystem = filesystem.or(profile.target_filesystem);
+        }
+
+        let report = validate_library(&library, &profiles);
+        if json {
+            println!("{}", serde_json::to_string_pretty(&report)?);
+        } else {
+            print!("{report}");
+        }
+        if !report.is_empty() {
+            bail!("Found {} problems", report.problems());
+        }
+
+        return Ok(());
+    }
+
+    if let Some(SubCommand::Duplicates {
+        tolerance,
+        content_hash,
+        playlist,
+        json,
+    }) = &opt.cmd
+    {
+        let options = DuplicateOptions {
+            duration_tolerance: *tolerance,
+            content_hash: *content_hash,
+        };
+        let groups = find_duplicates(&library, &options);
+        if *json {
+            println!("{}", serde_json::to_string_pretty(&groups)?);
+        } else {
+            for group in &groups {
+                print!("{group}");
+            }
+            println!("Found {} groups of duplicates.", groups.len());
+        }
+        if let Some(path) = playlist {
+            write_duplicates_playlist(&groups, path)?;
+            info!("Wrote duplicates to {path:?}");
+        }
 
         return Ok(());
     }
 
-    let rekordbox_library = mixxx_to_rekordbox(&config, library)?;
+    // Merge metadata from the audio files' tags into the library.
+    if config.read_file_tags {
+        let report = merge_file_tags(&mut library, config.file_tag_precedence);
+        for conflict in &report.conflicts {
+            info!(
+                "Track {}: '{}' differs between Mixxx ('{}') and file ('{}')",
+                conflict.track_id, conflict.field, conflict.mixxx, conflict.file
+            );
+        }
+        if !report.conflicts.is_empty() || !report.failed.is_empty() {
+            warn!(
+                "Read tags of {} files. {} files couldn't be read, found {} conflicting fields.",
+                report.read,
+                report.failed.len(),
+                report.conflicts.len()
+            );
+        }
+    }
 
-    // Get the target path for the json file.
-    let xml_target_file = config.target_directory().join("mixxx_rekordbox_export.xml");
+    // Write the library's info into the audio files instead of exporting it.
+    if let Some(SubCommand::TagWriter { dry_run, backup }) = opt.cmd {
+        let report = write_library_tags(&library, TagWriterOptions { dry_run, backup });
+        print_tag_write_report(&report, dry_run);
 
-    // Remove any existing xml file
-    if xml_target_file.exists() {
-        info!("Removing existing xml library file at: {xml_target_file:?}");
-        remove_file(&xml_target_file)?;
+        return Ok(());
     }
 
-    // Export the library.
-    let rekordbox_xml = quick_xml::se::to_string(&rekordbox_library)?;
-    let mut file = File::create(xml_target_file)?;
-    file.write_all(rekordbox_xml.as_bytes())?;
+    // Report problems before exporting, but export anyway.
+    if config.validate_before_export {
+        let report = validate_library(&library, &profiles);
+        if !report.is_empty() {
+            print!("{report}");
+            warn!(
+                "Found {} problems in the library, see above.",
+                report.problems()
+            );
+        }
+    }
+
+    for profile in &profiles {
+        let exporter = registry.exporter(&profile.format)?;
+        info!(
+            "Exporting profile '{}' as {}",
+            profile.name,
+            exporter.name()
+        );
+
+        // The cover art is referenced relative to the export, so each profile gets its own copy.
+        let with_cover_art = config
+            .export_cover_art
+            .then(|| export_profile_cover_art(&library, profile, config.cover_art_size))
+            .transpose()?;
+        let library = with_cover_art.as_ref().unwrap_or(&library);
+
+        // Copy the music files first, so the export points to the copies.
+        let synced = match &profile.sync {
+            Some(sync) => {
+                let synced = sync_files(library, profile, sync).context(format!(
+                    "Failed to sync files of profile '{}'",
+                    profile.name
+                ))?;
+                info!(
+                    "Copied {} files into {:?}, {} unchanged, removed {} orphans.",
+                    synced.report.copied,
+                    sync.directory,
+                    synced.report.unchanged,
+                    synced.report.removed.len()
+                );
+                if !synced.report.failed.is_empty() {
+                    warn!(
+                        "Failed to copy {} tracks, they're left out of profile '{}'",
+                        synced.report.failed.len(),
+                        profile.name
+                    );
+                }
+                Some(synced)
+            }
+            None => None,
+        };
+        let (library, profile) = match &synced {
+            Some(synced) => (&synced.library, &synced.profile),
+            None => (library, profile),
+        };
+
+        let report = exporter
+            .export(library, profile)
+            .context(format!("Failed to export profile '{}'", profile.name))?;
+        info!(
+            "Exported {} tracks of profile '{}' into {:?}",
+            report.tracks, profile.name, report.files
+        );
+        if let Some(changes) = &report.changes {
+            print_changes(&profile.name, changes);
+        }
+    }
+
+    Ok(())
+}
+
+/// Export the cover art into the target directory of the profile.
+/// Returns a copy of the library, which references the exported images.
+fn export_profile_cover_art(
+    library: &Library,
+    profile: &Profile,
+    max_size: u32,
+) -> Result<Library> {
+    let mut library = library.clone();
+    let report = export_cover_art(&mut library, &profile.target_directory, max_size).context(
+        format!("Failed to export cover art of profile '{}'", profile.name),
+    )?;
+    info!(
+        "Exported {} cover images, reused {} existing images, {} tracks without cover art.",
+        report.exported, report.deduplicated, report.missing
+    );
+    if !report.failed.is_empty() {
+        warn!(
+            "Failed to export cover art of {} tracks",
+            report.failed.len()
+        );
+    }
+
+    Ok(library)
+}
+
+/// Read the library from `--input` or the configured Mixxx databases.
+async fn import_library(
+    opt: &CliArguments,
+    config: &Config,
+    registry: &Registry,
+) -> Result<Library> {
+    if opt.input.is_none() && !config.mixxx_sources.is_empty() {
+        let importer = registry.importer("mixxx")?;
+        let mut sources = Vec::new();
+        for source in &config.mixxx_sources {
+            let library = importer
+                .import(&source.mixxx_db, config)
+                .await
+                .context(format!("Failed to read Mixxx source '{}'", source.name))?;
+            sources.push(MergeSource {
+                library,
+                source_library_root: source.source_library_root.clone(),
+                playlist_prefix: source.playlist_prefix(),
+            });
+        }
+
+        return Ok(merge_libraries(
+            sources,
+            &config.source_library_root,
+            config.cue_precedence,
+        ));
+    }
+
+    let (importer, source) = match (&opt.input, &config.mixxx_db) {
+        (Some(input), _) => match &opt.input_format {
+            Some(format) => (registry.importer(format)?, input),
+            None if input == "-" => (registry.importer("json")?, input),
+            None => (registry.importer_for(input)?, input),
+        },
+        (None, Some(mixxx_db)) => (registry.importer("mixxx")?, mixxx_db),
+        (None, None) => bail!(
+            "Either set mixxx_db or mixxx_sources in the config or pass a library via --input"
+        ),
+    };
+
+    importer.import(source, config).await
+}
+
+/// Print everything that has changed since the previous export of a profile.
+fn print_changes(profile: &str, changes: &Changes) {
+    if changes.is_empty() {
+        println!("{profile}: Nothing changed since the last export.");
+        return;
+    }
+
+    println!("{profile}:");
+    let groups = [
+        ("Added track", &changes.added_tracks),
+        ("Changed track", &changes.changed_tracks),
+        ("Removed track", &changes.removed_tracks),
+        ("Added playlist", &changes.added_playlists),
+        ("Changed playlist", &changes.changed_playlists),
+        ("Removed playlist", &changes.removed_playlists),
+    ];
+    for (action, entries) in groups {
+        for entry in entries {
+            println!("  {action}: {entry}");
+        }
+    }
+}
+
+/// Print all formats of the registry with the options they accept.
+fn print_formats(registry: &Registry) -> Result<()> {
+    println!("Import formats:");
+    for importer in registry.importers() {
+        println!("  {}: {}", importer.name(), importer.description());
+    }
+
+    println!("\nExport formats:");
+    for exporter in registry.exporters() {
+        println!("  {}: {}", exporter.name(), exporter.description());
+        let schema = serde_json::to_string_pretty(&exporter.config_schema())?;
+        for line in schema.lines() {
+            println!("    {line}");
+        }
+    }
 
     Ok(())
 }
 
+/// Print a summary of all files that were touched by the tag writer.
+fn print_tag_write_report(report: &TagWriteReport, dry_run: bool) {
+    let action = if dry_run { "Would modify" } else { "Modified" };
+    for path in &report.modified {
+        println!("{action}: {}", path.to_string_lossy());
+    }
+    for (path, error) in &report.failed {
+        println!("Failed: {} ({error})", path.to_string_lossy());
+    }
+
+    println!(
+        "\n{action} {} files, {} unchanged, {} unsupported, {} failed.",
+        report.modified.len(),
+        report.unchanged.len(),
+        report.unsupported.len(),
+        report.failed.len()
+    );
+}
+
 /// Init better_panics
 /// Initialize logging
-fn init_app(verbosity: u8) -> Result<()> {
+fn init_app(verbosity: u8, jobs: Option<usize>) -> Result<()> {
     // Beautify panics for better debug output.
     better_panic::install();
 
@@ -91,7 +378,20 @@ fn init_app(verbosity: u8) -> Result<()> {
         2 => LevelFilter::Info,
         _ => LevelFilter::Debug,
     };
-    Builder::new().filter_level(level).init();
+    Builder::new()
+        .filter_level(level)
+        // Symphonia is very chatty when reading tags of broken files.
+        // We already report those errors ourselves.
+        .filter_module("symphonia", LevelFilter::Off)
+        .init();
+
+    // Tracks are processed on a thread pool, which uses all cores by default.
+    if let Some(jobs) = jobs {
+        rayon::ThreadPoolBuilder::new()
+            .num_threads(jobs)
+            .build_global()
+            .context("Failed to initialize thread pool")?;
+    }
 
     Ok(())
 }
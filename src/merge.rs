@@ -0,0 +1,251 @@
+use std::collections::{BTreeMap, HashMap};
+
+use serde_derive::Deserialize;
+
+use crate::mixxx::library::{Library, Track};
+
+/// Decides whose cues are kept, if multiple libraries contain the same file.
+#[derive(Deserialize, Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum CuePrecedence {
+    /// The cues of the library that comes first in the config win.
+    #[default]
+    First,
+    /// The cues of the library with the most cues for that track win.
+    MostCues,
+}
+
+/// A single Mixxx database that's merged with others, e.g. the library of one DJ.
+#[derive(Deserialize, Debug, Clone)]
+#[serde(deny_unknown_fields)]
+pub struct MixxxSource {
+    /// A unique name, e.g. the name of the DJ.
+    pub name: String,
+    /// The location of this library's mixxxdb.sqlite file.
+    pub mixxx_db: String,
+    /// The directory that contains all music files of this library.
+    /// It's replaced by the global `source_library_root`, so files that're located in
+    /// different directories on each machine can be matched.
+    pub source_library_root: Option<String>,
+    /// Prepended to the names of this library's playlists and crates.
+    /// Defaults to `<name> - `.
+    pub playlist_prefix: Option<String>,
+}
+
+impl MixxxSource {
+    pub fn playlist_prefix(&self) -> String {
+        self.playlist_prefix
+            .clone()
+            .unwrap_or_else(|| format!("{} - ", self.name))
+    }
+}
+
+/// A library that's about to be merged.
+#[derive(Debug)]
+pub struct MergeSource {
+    pub library: Library,
+    /// The root of this library's music files, which is replaced by `source_library_root`
+    /// of the merged library.
+    pub source_library_root: Option<String>,
+    pub playlist_prefix: String,
+}
+
+/// Merge several libraries into one.
+///
+/// - Tracks get new ids, as the ids of different databases collide.
+/// - Tracks that point to the same file are merged into a single track. Its metadata is taken from
+///   the first library that hasn't deleted the track, its cues are chosen by the [CuePrecedence].
+/// - Playlists and crates are prefixed with the `playlist_prefix` of their library.
+pub fn merge_libraries(
+    sources: Vec<MergeSource>,
+    source_library_root: &str,
+    precedence: CuePrecedence,
+) -> Library {
+    let mut merged = Library {
+        tracks: BTreeMap::new(),
+        playlists: Vec::new(),
+        crates: Vec::new(),
+    };
+    let mut ids_by_location: HashMap<String, usize> = HashMap::new();
+
+    for source in sources {
+        // Maps the ids of this library to the ids of the merged library.
+        let mut id_map = HashMap::new();
+
+        for (old_id, mut track) in source.library.tracks {
+            if let Some(root) = &source.source_library_root {
+                rebase_location(&mut track, root, source_library_root);
+            }
+
+            if let Some(id) = ids_by_location.get(&track.location.location) {
+                let existing = merged.tracks.get_mut(id).expect("Merged track exists");
+                if existing.metadata.deleted && !track.metadata.deleted {
+                    // The track has been deleted in a previous library, but is still in use by
+                    // this one. Its active copy replaces the deleted one.
+                    let cues = std::mem::take(&mut existing.cues);
+                    *existing = track;
+                    existing.id = *id;
+                    if precedence == CuePrecedence::MostCues && cues.len() > existing.cues.len() {
+                        existing.cues = cues;
+                    }
+                } else if precedence == CuePrecedence::MostCues
+                    && track.cues.len() > existing.cues.len()
+                {
+                    existing.cues = track.cues;
+                }
+                for cue in &mut existing.cues {
+                    cue.track_id = *id as i64;
+                }
+                id_map.insert(old_id, *id);
+                continue;
+            }
+
+            let id = merged.tracks.len() + 1;
+            track.id = id;
+            for cue in &mut track.cues {
+                cue.track_id = id as i64;
+            }
+            ids_by_location.insert(track.location.location.clone(), id);
+            id_map.insert(old_id, id);
+            merged.tracks.insert(id, track);
+        }
+
+        let map_ids = |track_ids: Vec<usize>| -> Vec<usize> {
+            track_ids
+                .into_iter()
+                .filter_map(|id| id_map.get(&id).copied())
+                .collect()
+        };
+        for mut playlist in source.library.playlists {
+            playlist.id = merged.playlists.len() + 1;
+            playlist.name = format!("{}{}", source.playlist_prefix, playlist.name);
+            playlist.track_ids = map_ids(playlist.track_ids);
+            merged.playlists.push(playlist);
+        }
+        for mut mixxx_crate in source.library.crates {
+            mixxx_crate.id = merged.crates.len() + 1;
+            mixxx_crate.name = format!("{}{}", source.playlist_prefix, mixxx_crate.name);
+            mixxx_crate.track_ids = map_ids(mixxx_crate.track_ids);
+            merged.crates.push(mixxx_crate);
+        }
+    }
+
+    merged
+}
+
+/// Move a track from the `from` directory into the `to` directory.
+/// Tracks outside of `from` are kept as they are.
+fn rebase_location(track: &mut Track, from: &str, to: &str) {
+    let from = from.trim_end_matches('/');
+    let to = to.trim_end_matches('/');
+
+    for path in [&mut track.location.location, &mut track.location.directory] {
+        if let Some(relative) = path.strip_prefix(from)
+            && (relative.is_empty() || relative.starts_with('/'))
+        {
+            *path = format!("{to}{relative}");
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use anyhow::Result;
+    use pretty_assertions::assert_eq;
+
+    use super::*;
+    use crate::mixxx::{
+        aggregator::read_library,
+        fixture::{CueFixture, LibraryBuilder, Schema, TrackFixture},
+    };
+
+    async fn dj_library(cues: usize, shared_path: &str) -> Result<Library> {
+        let mut fixture = LibraryBuilder::new()
+            .track(TrackFixture {
+                path: shared_path.into(),
+                cues: (0..cues)
+                    .map(|hotcue| CueFixture::hotcue(hotcue as i64, hotcue as f64))
+                    .collect(),
+                ..TrackFixture::new(1, "Shared", "Track")
+            })
+            .track(TrackFixture::new(2, "Own", &format!("Track {cues}")))
+            .playlist("Warmup", &[2, 1])
+            .build(Schema::Latest)
+            .await?;
+
+        read_library(&mut fixture.connection).await
+    }
+
+    #[tokio::test]
+    async fn merge_two_djs() -> Result<()> {
+        let alice = MergeSource {
+            library: dj_library(1, "/music/Shared/Track.mp3").await?,
+            source_library_root: None,
+            playlist_prefix: "Alice - ".into(),
+        };
+        let bob = MergeSource {
+            library: dj_library(3, "/home/bob/Music/Shared/Track.mp3").await?,
+            source_library_root: Some("/home/bob/Music/".into()),
+            playlist_prefix: "Bob - ".into(),
+        };
+
+        let merged = merge_libraries(vec![alice, bob], "/music", CuePrecedence::MostCues);
+
+        // The shared track only exists once.
+        let titles: Vec<_> = merged
+            .tracks
+            .iter()
+            .map(|(id, track)| (*id, track.title.as_str()))
+            .collect();
+        assert_eq!(titles, vec![(1, "Track"), (2, "Track 1"), (3, "Track 3")]);
+        assert_eq!(merged.tracks[&1].cues.len(), 3);
+        assert!(merged.tracks[&1].cues.iter().all(|cue| cue.track_id == 1));
+        assert_eq!(
+            merged.tracks[&3].location.location,
+            "/music/Own/Track 3.mp3"
+        );
+
+        let playlists: Vec<_> = merged
+            .playlists
+            .iter()
+            .map(|playlist| (playlist.name.as_str(), playlist.track_ids.clone()))
+            .collect();
+        assert_eq!(
+            playlists,
+            vec![("Alice - Warmup", vec![2, 1]), ("Bob - Warmup", vec![3, 1])]
+        );
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn prefer_active_copies() -> Result<()> {
+        let mut fixture = LibraryBuilder::new()
+            .track(TrackFixture::new(1, "Shared", "Track"))
+            .build(Schema::Latest)
+            .await?;
+        let mut deleted = read_library(&mut fixture.connection).await?;
+        deleted.tracks.get_mut(&1).unwrap().metadata.deleted = true;
+        let alice = MergeSource {
+            library: deleted,
+            source_library_root: None,
+            playlist_prefix: "Alice - ".into(),
+        };
+        let bob = MergeSource {
+            library: dj_library(2, "/music/Shared/Track.mp3").await?,
+            source_library_root: None,
+            playlist_prefix: "Bob - ".into(),
+        };
+
+        let merged = merge_libraries(vec![alice, bob], "/music", CuePrecedence::First);
+
+        let shared = &merged.tracks[&1];
+        assert!(!shared.metadata.deleted);
+        assert_eq!(shared.id, 1);
+        assert_eq!(shared.cues.len(), 2);
+        assert!(shared.cues.iter().all(|cue| cue.track_id == 1));
+        assert_eq!(merged.playlists[0].track_ids, vec![2, 1]);
+
+        Ok(())
+    }
+}
@@ -1,10 +1,17 @@
-use std::collections::BTreeMap;
+use std::{
+    collections::{BTreeMap, HashMap},
+    fs::metadata,
+};
 
 use anyhow::{Context, Result};
 use chrono::DateTime;
+use rayon::prelude::*;
 use sqlx::SqliteConnection;
 
 use super::library::{
+    CoverArt,
+    CoverArtSource,
+    CoverArtType,
     Crate,
     Library,
     Playlist,
@@ -13,10 +20,22 @@ use super::library::{
     TrackMetadata,
     TrackTechnicalInfo,
 };
-use crate::mixxx::storage;
+use crate::{
+    color::RgbColor,
+    mixxx::{
+        schema::cue::Cue,
+        storage::{
+            self,
+            version::{SchemaVersion, get_schema_version},
+        },
+    },
+};
 
 pub async fn read_library(con: &mut SqliteConnection) -> Result<Library> {
-    let tracks = get_tracks(con).await?;
+    // The queries differ between Mixxx versions.
+    let version = get_schema_version(con).await?;
+
+    let tracks = get_tracks(con, version).await?;
     let playlists = read_playlists(con, &tracks).await?;
     let crates = read_crates(con, &tracks).await?;
 
@@ -29,23 +48,40 @@ pub async fn read_library(con: &mut SqliteConnection) -> Result<Library> {
     Ok(library)
 }
 
-pub async fn get_tracks(con: &mut SqliteConnection) -> Result<BTreeMap<usize, Track>> {
+pub async fn get_tracks(
+    con: &mut SqliteConnection,
+    version: SchemaVersion,
+) -> Result<BTreeMap<usize, Track>> {
     let mut tracks = BTreeMap::new();
 
-    let raw_tracks = storage::track::get_tracks(con).await?;
-    for raw_track in raw_tracks.into_iter() {
-        let Some(location_id) = raw_track.location else {
-            log::debug!("No location for track {}", raw_track.id);
-            continue;
-        };
+    let raw_tracks = storage::track::get_tracks(con, version).await?;
+
+    // Fetch all cues at once and group them by their track.
+    let mut cues_by_track: HashMap<i64, Vec<Cue>> = HashMap::new();
+    for cue in storage::cue::get_cues(con, version).await? {
+        cues_by_track.entry(cue.track_id).or_default().push(cue);
+    }
 
-        let location = storage::track::get_track_location(con, location_id).await?;
-        let cues = storage::cue::get_track_cues(con, raw_track.id).await?;
+    for raw_track in raw_tracks.into_iter() {
+        let cues = cues_by_track.remove(&raw_track.id).unwrap_or_default();
 
         let location = TrackLocation {
-            location: location.location.unwrap(),
-            filename: location.filename.unwrap(),
-            directory: location.directory.unwrap(),
+            location: raw_track.location_path.unwrap(),
+            filename: raw_track.location_filename.unwrap(),
+            directory: raw_track.location_directory.unwrap(),
+            filesize: raw_track
+                .location_filesize
+                .and_then(|size| size.try_into().ok()),
+        };
+
+        let cover_art = CoverArt {
+            source: CoverArtSource::from_mixxx(raw_track.coverart_source.unwrap_or_default()),
+            cover_type: CoverArtType::from_mixxx(raw_track.coverart_type.unwrap_or_default()),
+            location: raw_track
+                .coverart_location
+                .filter(|location| !location.is_empty()),
+            hash: raw_track.coverart_hash.unwrap_or_default(),
+            exported: None,
         };
 
         let technical_info = TrackTechnicalInfo {
@@ -89,12 +125,26 @@ pub async fn get_tracks(con: &mut SqliteConnection) -> Result<BTreeMap<usize, Tr
             album: raw_track.album,
             year: raw_track.year.unwrap_or_default(),
             genre: raw_track.genre,
+            grouping: raw_track.grouping.filter(|grouping| !grouping.is_empty()),
             tracknumber: raw_track.tracknumber,
+            // Mixxx uses `//` as a placeholder for an unknown track total.
+            tracktotal: raw_track
+                .tracktotal
+                .filter(|total| !total.is_empty() && total != "//"),
+            disc_number: None,
+            label: None,
+            remixer: None,
             filetype: raw_track.filetype,
             comment: raw_track.comment,
             url: raw_track.url,
+            // Mixxx saves colours as `0xRRGGBB` integers.
+            color: raw_track
+                .color
+                .and_then(|color| u32::try_from(color).ok())
+                .map(RgbColor),
             cues,
             location,
+            cover_art,
             technical_info,
             metadata,
         };
@@ -102,6 +152,15 @@ pub async fn get_tracks(con: &mut SqliteConnection) -> Result<BTreeMap<usize, Tr
         tracks.insert(track.id, track);
     }
 
+    // Prefer the actual size of the file, as the size in Mixxx's database might be outdated.
+    // Mixxx's info is kept, if the file isn't reachable from this machine.
+    // Stat'ing thousands of files is slow on network shares, which is why it's done in parallel.
+    tracks.par_iter_mut().for_each(|(_, track)| {
+        if let Ok(metadata) = metadata(&track.location.location) {
+            track.location.filesize = Some(metadata.len());
+        }
+    });
+
     Ok(tracks)
 }
 
@@ -112,15 +171,13 @@ pub async fn read_playlists(
     let mut playlists = Vec::new();
 
     let raw_playlists = storage::playlist::get_playlists(con).await?;
+    let mut tracks_by_playlist =
+        group_track_ids(storage::playlist::get_playlist_tracks(con).await?);
+
     for raw_playlist in raw_playlists.into_iter() {
-        let track_ids: Vec<usize> = storage::playlist::get_playlist_tracks(con, raw_playlist.id)
-            .await?
-            .into_iter()
-            .map(|id| {
-                id.try_into()
-                    .expect("Got track id that doesn't fit into usize")
-            })
-            .collect();
+        let track_ids = tracks_by_playlist
+            .remove(&raw_playlist.id)
+            .unwrap_or_default();
 
         let playlist = Playlist {
             id: raw_playlist
@@ -148,15 +205,10 @@ pub async fn read_crates(
     let mut crates = Vec::new();
 
     let raw_crates = storage::mcrate::get_crates(con).await?;
+    let mut tracks_by_crate = group_track_ids(storage::mcrate::get_crate_tracks(con).await?);
+
     for raw_crate in raw_crates.into_iter() {
-        let track_ids: Vec<usize> = storage::mcrate::get_crate_tracks(con, raw_crate.id)
-            .await?
-            .into_iter()
-            .map(|id| {
-                id.try_into()
-                    .expect("Got track id that doesn't fit into usize")
-            })
-            .collect();
+        let track_ids = tracks_by_crate.remove(&raw_crate.id).unwrap_or_default();
 
         let mcrate = Crate {
             id: raw_crate
@@ -174,3 +226,245 @@ pub async fn read_crates(
 
     Ok(crates)
 }
+
+/// Group `(parent_id, track_id)` pairs by their parent, e.g. a playlist or crate.
+/// The order of the tracks is preserved.
+fn group_track_ids(pairs: Vec<(i64, i64)>) -> HashMap<i64, Vec<usize>> {
+    let mut groups: HashMap<i64, Vec<usize>> = HashMap::new();
+    for (parent_id, track_id) in pairs {
+        let track_id = track_id
+            .try_into()
+            .expect("Got track id that doesn't fit into usize");
+        groups.entry(parent_id).or_default().push(track_id);
+    }
+
+    groups
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Instant;
+
+    use pretty_assertions::assert_eq;
+    use rstest::rstest;
+    use tempfile::TempDir;
+
+    use super::*;
+    use crate::mixxx::fixture::{Schema, assert_golden, create_database, sample_library};
+
+    /// Create a Mixxx database with the given schema and `count` generated tracks inside of `dir`.
+    /// Each track has four hotcues and every playlist and crate contains 100 tracks.
+    async fn generate_library(
+        dir: &TempDir,
+        schema: Schema,
+        count: i64,
+    ) -> Result<SqliteConnection> {
+        let mut con = create_database(&dir.path().join("mixxxdb.sqlite"), schema).await?;
+
+        sqlx::query(
+            r#"
+            WITH RECURSIVE seq(n) AS (SELECT 1 UNION ALL SELECT n + 1 FROM seq WHERE n < $1)
+            INSERT INTO track_locations (id, location, filename, directory, filesize)
+            SELECT n, '/music/track_' || n || '.mp3', 'track_' || n || '.mp3', '/music', 1000
+            FROM seq
+            "#,
+        )
+        .bind(count)
+        .execute(&mut con)
+        .await?;
+
+        sqlx::query(
+            r#"
+            WITH RECURSIVE seq(n) AS (SELECT 1 UNION ALL SELECT n + 1 FROM seq WHERE n < $1)
+            INSERT INTO library (
+                id, artist, title, location, duration, bitrate, samplerate, bpm,
+                datetime_added, filetype, timesplayed, rating, key, composer
+            )
+            SELECT
+                n, 'Artist ' || (n % 500), 'Title ' || n, n, 300.0, 320, 44100, 124.0,
+                '2024-01-01T00:00:00Z', 'mp3', n % 10, n % 6, '8A', ''
+            FROM seq
+            "#,
+        )
+        .bind(count)
+        .execute(&mut con)
+        .await?;
+
+        sqlx::query(
+            r#"
+            WITH RECURSIVE seq(n) AS (SELECT 0 UNION ALL SELECT n + 1 FROM seq WHERE n < $1 * 4 - 1)
+            INSERT INTO cues (track_id, type, position, hotcue)
+            SELECT n / 4 + 1, 1, (n % 4) * 88200, n % 4
+            FROM seq
+            "#,
+        )
+        .bind(count)
+        .execute(&mut con)
+        .await?;
+
+        // Playlists are filled in reverse order, crates in normal order.
+        sqlx::query(
+            r#"
+            WITH RECURSIVE seq(n) AS (SELECT 1 UNION ALL SELECT n + 1 FROM seq WHERE n < $1 / 100)
+            INSERT INTO Playlists (id, name, position, hidden, date_created, date_modified)
+            SELECT n, 'Playlist ' || n, n, 0, '2024-01-01 00:00:00', '2024-01-01 00:00:00'
+            FROM seq;
+            INSERT INTO PlaylistTracks (playlist_id, track_id, position)
+            SELECT (id - 1) / 100 + 1, id, 100 - (id - 1) % 100 FROM library;
+            INSERT INTO crates (id, name, count, show)
+            SELECT id, 'Crate ' || id, 100, 1 FROM Playlists;
+            INSERT INTO crate_tracks (crate_id, track_id)
+            SELECT (id - 1) / 100 + 1, id FROM library;
+            "#,
+        )
+        .bind(count)
+        .execute(&mut con)
+        .await?;
+
+        Ok(con)
+    }
+
+    #[tokio::test]
+    async fn read_generated_library() -> Result<()> {
+        let dir = TempDir::new()?;
+        let mut con = generate_library(&dir, Schema::Latest, 250).await?;
+
+        let library = read_library(&mut con).await?;
+
+        assert_eq!(library.tracks.len(), 250);
+        assert!(library.tracks.values().all(|track| track.cues.len() == 4));
+        let hotcues: Vec<i64> = library.tracks[&1]
+            .cues
+            .iter()
+            .map(|cue| cue.hotcue)
+            .collect();
+        assert_eq!(hotcues, vec![0, 1, 2, 3]);
+
+        assert_eq!(library.playlists.len(), 2);
+        assert_eq!(library.crates.len(), 2);
+        // Playlist tracks are ordered by their position.
+        let expected: Vec<usize> = (101..=200).rev().collect();
+        assert_eq!(library.playlists[1].track_ids, expected);
+        let expected: Vec<usize> = (101..=200).collect();
+        assert_eq!(library.crates[1].track_ids, expected);
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn read_sample_library() -> Result<()> {
+        let mut fixture = sample_library().await?;
+
+        let library = read_library(&mut fixture.connection).await?;
+
+        assert_golden("library.json", &serde_json::to_string_pretty(&library)?);
+
+        Ok(())
+    }
+
+    #[rstest]
+    #[case::mixxx_2_2(Schema::Mixxx22, 1, None)]
+    #[case::mixxx_2_4(Schema::Latest, 0xC50A08, Some(RgbColor(0xFF0000)))]
+    #[tokio::test]
+    async fn read_schema_versions(
+        #[case] schema: Schema,
+        #[case] stored_cue_color: i64,
+        #[case] track_color: Option<RgbColor>,
+    ) -> Result<()> {
+        let dir = TempDir::new()?;
+        let mut con = generate_library(&dir, schema, 10).await?;
+        sqlx::query("UPDATE cues SET color = $1")
+            .bind(stored_cue_color)
+            .execute(&mut con)
+            .await?;
+        if let Some(color) = track_color {
+            sqlx::query("UPDATE library SET color = $1")
+                .bind(color.0)
+                .execute(&mut con)
+                .await?;
+        }
+
+        let library = read_library(&mut con).await?;
+
+        assert_eq!(library.tracks.len(), 10);
+        for track in library.tracks.values() {
+            assert_eq!(track.color, track_color);
+            assert!(track.cues.iter().all(|cue| cue.color == 0xC50A08));
+        }
+
+        Ok(())
+    }
+
+    /// There are no fixtures of the schemas between Mixxx 2.2 and 2.4, so the latest schema
+    /// stands in for the versions at the boundaries of the Mixxx 2.3 queries.
+    #[rstest]
+    #[case("29")]
+    #[case("38")]
+    #[tokio::test]
+    async fn read_intermediate_schema_versions(#[case] version: &str) -> Result<()> {
+        let dir = TempDir::new()?;
+        let mut con = generate_library(&dir, Schema::Latest, 10).await?;
+        sqlx::query("UPDATE settings SET value = $1 WHERE name = 'mixxx.schema.version'")
+            .bind(version)
+            .execute(&mut con)
+            .await?;
+        sqlx::query("UPDATE library SET color = 0xFF0000")
+            .execute(&mut con)
+            .await?;
+        sqlx::query("UPDATE cues SET color = 0xC50A08")
+            .execute(&mut con)
+            .await?;
+
+        let library = read_library(&mut con).await?;
+
+        assert_eq!(library.tracks.len(), 10);
+        for track in library.tracks.values() {
+            assert_eq!(track.color, Some(RgbColor(0xFF0000)));
+            assert!(track.cues.iter().all(|cue| cue.color == 0xC50A08));
+        }
+
+        Ok(())
+    }
+
+    #[rstest]
+    #[case("27")]
+    #[case("40")]
+    #[case("not a number")]
+    #[tokio::test]
+    async fn reject_unknown_schema_versions(#[case] version: &str) -> Result<()> {
+        let dir = TempDir::new()?;
+        let mut con = generate_library(&dir, Schema::Latest, 1).await?;
+        sqlx::query("UPDATE settings SET value = $1 WHERE name = 'mixxx.schema.version'")
+            .bind(version)
+            .execute(&mut con)
+            .await?;
+
+        let err = read_library(&mut con).await.unwrap_err();
+        assert!(format!("{err:#}").contains(version), "{err:#}");
+
+        Ok(())
+    }
+
+    /// Benchmark the library import with a large generated library.
+    ///
+    /// Run it with `cargo test --release -- --ignored --nocapture bench_read_library`.
+    #[tokio::test]
+    #[ignore]
+    async fn bench_read_library() -> Result<()> {
+        let dir = TempDir::new()?;
+        let mut con = generate_library(&dir, Schema::Latest, 40_000).await?;
+
+        let runs = 5;
+        let start = Instant::now();
+        for _ in 0..runs {
+            let library = read_library(&mut con).await?;
+            assert_eq!(library.tracks.len(), 40_000);
+        }
+        println!(
+            "Reading a library with 40k tracks took {:?} on average",
+            start.elapsed() / runs
+        );
+
+        Ok(())
+    }
+}
@@ -0,0 +1,557 @@
+use std::{
+    fs::{read_to_string, write},
+    path::{Path, PathBuf},
+};
+
+use anyhow::{Context, Result};
+use confique::Config as _;
+use prost::Message;
+use sqlx::{Connection, SqliteConnection, sqlite::SqliteConnectOptions};
+use tempfile::TempDir;
+
+use super::{
+    helper::CUE_SAMPLE_RATE,
+    schema::beats::{Beat, BeatGrid, BeatMap, Bpm},
+};
+use crate::config::Config;
+
+/// The schema with which a fixture database is created.
+#[derive(Debug, Clone, Copy)]
+pub enum Schema {
+    /// The schema of Mixxx 2.2, which is only kept as a fixture.
+    Mixxx22,
+    /// The latest schema, as created by the migrations in the repository.
+    Latest,
+}
+
+/// Create an empty Mixxx database with the given schema at `path`.
+pub async fn create_database(path: &Path, schema: Schema) -> Result<SqliteConnection> {
+    let options = SqliteConnectOptions::new()
+        .filename(path)
+        .create_if_missing(true)
+        // Mixxx's schema references `track_locations(location)` from `library.location`,
+        // although the column contains ids. Mixxx doesn't enforce foreign keys either.
+        .foreign_keys(false);
+    let mut con = SqliteConnection::connect_with(&options).await?;
+
+    match schema {
+        Schema::Mixxx22 => {
+            let sql = include_str!("../../tests/fixtures/schema/mixxx_28.sql");
+            sqlx::raw_sql(sql).execute(&mut con).await?;
+        }
+        Schema::Latest => sqlx::migrate!().run(&mut con).await?,
+    }
+
+    Ok(con)
+}
+
+/// The beats of a track, as they're saved after Mixxx's analysis.
+#[derive(Debug, Clone)]
+pub enum Beats {
+    /// A constant tempo, starting at the given frame.
+    Grid { bpm: f64, first_beat: i32 },
+    /// The frame of each single beat. Mixxx uses this for tracks with a variable tempo.
+    Map(Vec<i32>),
+}
+
+impl Beats {
+    /// Encode the beats into the protobuf blob and version that're saved by Mixxx.
+    fn encode(&self) -> (Vec<u8>, &'static str) {
+        let beat = |frame: i32| Beat {
+            frame_position: Some(frame),
+            enabled: None,
+            source: None,
+        };
+
+        match self {
+            Beats::Grid { bpm, first_beat } => {
+                let grid = BeatGrid {
+                    bpm: Some(Bpm {
+                        bpm: Some(*bpm),
+                        source: None,
+                    }),
+                    first_beat: Some(beat(*first_beat)),
+                };
+                (grid.encode_to_vec(), "BeatGrid-2.0")
+            }
+            Beats::Map(frames) => {
+                let map = BeatMap {
+                    beat: frames.iter().copied().map(beat).collect(),
+                };
+                (map.encode_to_vec(), "BeatMap-1.0")
+            }
+        }
+    }
+}
+
+/// A single cue point of a track.
+#[derive(Debug, Clone)]
+pub struct CueFixture {
+    pub cue_type: i64,
+    /// The position in Mixxx's unit, i.e. samples at 88.2 kHz. See [CueFixture::hotcue].
+    pub position: i64,
+    pub length: i64,
+    pub hotcue: i64,
+    pub label: String,
+    pub color: i64,
+}
+
+impl CueFixture {
+    /// A hotcue with the given number at `seconds` into the track.
+    pub fn hotcue(hotcue: i64, seconds: f64) -> Self {
+        CueFixture {
+            cue_type: 1,
+            position: to_position(seconds),
+            length: 0,
+            hotcue,
+            label: String::new(),
+            color: 0xC50A08,
+        }
+    }
+
+    /// The main cue point at `seconds` into the track.
+    pub fn main_cue(seconds: f64) -> Self {
+        CueFixture {
+            cue_type: 2,
+            hotcue: -1,
+            ..CueFixture::hotcue(-1, seconds)
+        }
+    }
+
+    /// A saved loop with the given hotcue number.
+    pub fn saved_loop(hotcue: i64, start: f64, end: f64) -> Self {
+        CueFixture {
+            cue_type: 4,
+            length: to_position(end) - to_position(start),
+            ..CueFixture::hotcue(hotcue, start)
+        }
+    }
+
+    pub fn label(mut self, label: &str) -> Self {
+        self.label = label.into();
+        self
+    }
+}
+
+fn to_position(seconds: f64) -> i64 {
+    (seconds * CUE_SAMPLE_RATE as f64).round() as i64
+}
+
+/// A track with its location, beats and cues.
+///
+/// Create it via [TrackFixture::new] and adjust the fields as needed, e.g.
+/// `TrackFixture { bpm: 174.0, ..TrackFixture::new(1, "Artist", "Title") }`.
+#[derive(Debug, Clone)]
+pub struct TrackFixture {
+    pub id: i64,
+    pub artist: String,
+    pub title: String,
+    pub album: Option<String>,
+    pub year: String,
+    pub genre: Option<String>,
+    pub grouping: Option<String>,
+    pub comment: Option<String>,
+    pub tracknumber: Option<String>,
+    /// The absolute path of the audio file.
+    pub path: String,
+    pub filesize: i64,
+    pub filetype: String,
+    pub duration: f64,
+    pub bitrate: i64,
+    pub samplerate: i64,
+    pub bpm: f64,
+    pub key: String,
+    pub rating: i64,
+    pub timesplayed: i64,
+    /// The date in RFC 3339 format, as it's saved by Mixxx.
+    pub datetime_added: String,
+    /// The track colour. Not supported by the [Schema::Mixxx22] schema.
+    pub color: Option<u32>,
+    pub beats: Option<Beats>,
+    pub cues: Vec<CueFixture>,
+}
+
+impl TrackFixture {
+    /// A five minute long MP3 at `/music/<artist>/<title>.mp3` without beats and cues.
+    pub fn new(id: i64, artist: &str, title: &str) -> Self {
+        TrackFixture {
+            id,
+            artist: artist.into(),
+            title: title.into(),
+            album: None,
+            year: String::new(),
+            genre: None,
+            grouping: None,
+            comment: None,
+            tracknumber: None,
+            path: format!("/music/{artist}/{title}.mp3"),
+            filesize: 7_200_000,
+            filetype: "mp3".into(),
+            duration: 300.0,
+            bitrate: 320,
+            samplerate: 44100,
+            bpm: 0.0,
+            key: String::new(),
+            rating: 0,
+            timesplayed: 0,
+            datetime_added: "2023-01-02T10:00:00Z".into(),
+            color: None,
+            beats: None,
+            cues: Vec::new(),
+        }
+    }
+}
+
+/// A playlist or crate with the ids of its tracks.
+#[derive(Debug, Clone)]
+struct ListFixture {
+    name: String,
+    hidden: bool,
+    track_ids: Vec<i64>,
+}
+
+/// Describes a Mixxx library, which is then written into a temporary database.
+///
+/// ```ignore
+/// let fixture = LibraryBuilder::new()
+///     .track(TrackFixture::new(1, "Artist", "Title"))
+///     .playlist("Warmup", &[1])
+///     .build(Schema::Latest)
+///     .await?;
+/// let library = read_library(&mut fixture.connection).await?;
+/// ```
+#[derive(Debug, Default)]
+pub struct LibraryBuilder {
+    tracks: Vec<TrackFixture>,
+    playlists: Vec<ListFixture>,
+    crates: Vec<ListFixture>,
+}
+
+impl LibraryBuilder {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn track(mut self, track: TrackFixture) -> Self {
+        self.tracks.push(track);
+        self
+    }
+
+    /// Add a playlist. The tracks are in the given order.
+    pub fn playlist(mut self, name: &str, track_ids: &[i64]) -> Self {
+        self.playlists.push(ListFixture {
+            name: name.into(),
+            hidden: false,
+            track_ids: track_ids.to_vec(),
+        });
+        self
+    }
+
+    /// Add a playlist that's hidden in Mixxx, such as the Auto DJ queue.
+    pub fn hidden_playlist(mut self, name: &str, track_ids: &[i64]) -> Self {
+        self.playlists.push(ListFixture {
+            name: name.into(),
+            hidden: true,
+            track_ids: track_ids.to_vec(),
+        });
+        self
+    }
+
+    pub fn mixxx_crate(mut self, name: &str, track_ids: &[i64]) -> Self {
+        self.crates.push(ListFixture {
+            name: name.into(),
+            hidden: false,
+            track_ids: track_ids.to_vec(),
+        });
+        self
+    }
+
+    /// Write the library into a new database in a temporary directory.
+    pub async fn build(self, schema: Schema) -> Result<MixxxFixture> {
+        let dir = TempDir::new()?;
+        let path = dir.path().join("mixxxdb.sqlite");
+        let mut connection = create_database(&path, schema).await?;
+
+        for track in &self.tracks {
+            insert_track(&mut connection, track)
+                .await
+                .context(format!("Failed to insert track {}", track.id))?;
+        }
+
+        for (index, playlist) in self.playlists.iter().enumerate() {
+            let id = index as i64 + 1;
+            sqlx::query(
+                "INSERT INTO Playlists (id, name, position, hidden, date_created, date_modified)
+                VALUES ($1, $2, $1, $3, '2023-01-02 10:00:00', '2023-01-03 10:00:00')",
+            )
+            .bind(id)
+            .bind(&playlist.name)
+            .bind(i64::from(playlist.hidden))
+            .execute(&mut connection)
+            .await?;
+
+            for (position, track_id) in playlist.track_ids.iter().enumerate() {
+                sqlx::query(
+                    "INSERT INTO PlaylistTracks (playlist_id, track_id, position)
+                    VALUES ($1, $2, $3)",
+                )
+                .bind(id)
+                .bind(track_id)
+                .bind(position as i64 + 1)
+                .execute(&mut connection)
+                .await?;
+            }
+        }
+
+        for (index, mixxx_crate) in self.crates.iter().enumerate() {
+            let id = index as i64 + 1;
+            sqlx::query("INSERT INTO crates (id, name, count, show) VALUES ($1, $2, $3, $4)")
+                .bind(id)
+                .bind(&mixxx_crate.name)
+                .bind(mixxx_crate.track_ids.len() as i64)
+                .bind(if mixxx_crate.hidden { -1 } else { 1 })
+                .execute(&mut connection)
+                .await?;
+
+            for track_id in &mixxx_crate.track_ids {
+                sqlx::query("INSERT INTO crate_tracks (crate_id, track_id) VALUES ($1, $2)")
+                    .bind(id)
+                    .bind(track_id)
+                    .execute(&mut connection)
+                    .await?;
+            }
+        }
+
+        Ok(MixxxFixture {
+            dir,
+            path,
+            connection,
+        })
+    }
+}
+
+async fn insert_track(con: &mut SqliteConnection, track: &TrackFixture) -> Result<()> {
+    let path = Path::new(&track.path);
+    let filename = path.file_name().unwrap_or_default().to_string_lossy();
+    let directory = path.parent().unwrap_or(Path::new("/")).to_string_lossy();
+
+    sqlx::query(
+        "INSERT INTO track_locations (id, location, filename, directory, filesize)
+        VALUES ($1, $2, $3, $4, $5)",
+    )
+    .bind(track.id)
+    .bind(&track.path)
+    .bind(filename)
+    .bind(directory)
+    .bind(track.filesize)
+    .execute(&mut *con)
+    .await?;
+
+    let (beats, beats_version) = match &track.beats {
+        Some(beats) => {
+            let (blob, version) = beats.encode();
+            (Some(blob), Some(version))
+        }
+        None => (None, None),
+    };
+
+    sqlx::query(
+        "INSERT INTO library (
+            id, artist, title, album, year, genre, grouping, comment, tracknumber, location,
+            filetype, duration, bitrate, samplerate, bpm, key, rating, timesplayed,
+            datetime_added, beats, beats_version, composer
+        )
+        VALUES (
+            $1, $2, $3, $4, $5, $6, $7, $8, $9, $1,
+            $10, $11, $12, $13, $14, $15, $16, $17,
+            $18, $19, $20, ''
+        )",
+    )
+    .bind(track.id)
+    .bind(&track.artist)
+    .bind(&track.title)
+    .bind(&track.album)
+    .bind(&track.year)
+    .bind(&track.genre)
+    .bind(&track.grouping)
+    .bind(&track.comment)
+    .bind(&track.tracknumber)
+    .bind(&track.filetype)
+    .bind(track.duration)
+    .bind(track.bitrate)
+    .bind(track.samplerate)
+    .bind(track.bpm)
+    .bind(&track.key)
+    .bind(track.rating)
+    .bind(track.timesplayed)
+    .bind(&track.datetime_added)
+    .bind(beats)
+    .bind(beats_version)
+    .execute(&mut *con)
+    .await?;
+
+    // The column doesn't exist in old schemas, so it's only touched if it's actually needed.
+    if let Some(color) = track.color {
+        sqlx::query("UPDATE library SET color = $1 WHERE id = $2")
+            .bind(color)
+            .bind(track.id)
+            .execute(&mut *con)
+            .await?;
+    }
+
+    for cue in &track.cues {
+        sqlx::query(
+            "INSERT INTO cues (track_id, type, position, length, hotcue, label, color)
+            VALUES ($1, $2, $3, $4, $5, $6, $7)",
+        )
+        .bind(track.id)
+        .bind(cue.cue_type)
+        .bind(cue.position)
+        .bind(cue.length)
+        .bind(cue.hotcue)
+        .bind(&cue.label)
+        .bind(cue.color)
+        .execute(&mut *con)
+        .await?;
+    }
+
+    Ok(())
+}
+
+/// A temporary Mixxx database, which is deleted once this is dropped.
+pub struct MixxxFixture {
+    pub dir: TempDir,
+    pub path: PathBuf,
+    pub connection: SqliteConnection,
+}
+
+impl MixxxFixture {
+    /// A config that reads this database and exports into the `out` directory next to it.
+    pub fn config(&self) -> Config {
+        write_config(self.dir.path(), &self.path, "")
+    }
+}
+
+/// Write a config into `dir`, which reads `mixxx_db` and exports into `dir/out`.
+/// All tracks are expected in `/music`, which is mapped to `C:/Music`.
+/// `extra` is appended to the TOML, e.g. to add profiles.
+pub fn write_config(dir: &Path, mixxx_db: &Path, extra: &str) -> Config {
+    let config_path = dir.join("config.toml");
+    let config = format!(
+        r#"
+        mixxx_db = {:?}
+        target_directory = {:?}
+        source_library_root = "/music"
+        target_library_root = "C:/Music"
+        create_all_playlist = true
+        {extra}
+        "#,
+        mixxx_db,
+        dir.join("out"),
+    );
+    write(&config_path, config).expect("Failed to write config");
+
+    Config::from_file(&config_path).expect("Failed to read config")
+}
+
+/// A small library that covers most features of Mixxx, which is used for golden tests.
+///
+/// - A track with a beatgrid, hotcues, a saved loop, a colour and most metadata.
+/// - A track with a variable tempo and a main cue.
+/// - A track without any analysis, whose name needs to be escaped.
+/// - A playlist, a hidden playlist and a crate.
+pub async fn sample_library() -> Result<MixxxFixture> {
+    LibraryBuilder::new()
+        .track(TrackFixture {
+            album: Some("First Album".into()),
+            year: "2021".into(),
+            genre: Some("Techno".into()),
+            grouping: Some("Peak".into()),
+            comment: Some("Great break at 2:00".into()),
+            tracknumber: Some("3".into()),
+            bpm: 128.0,
+            key: "8A".into(),
+            rating: 4,
+            timesplayed: 7,
+            color: Some(0xFF0000),
+            beats: Some(Beats::Grid {
+                bpm: 128.0,
+                first_beat: 2205,
+            }),
+            cues: vec![
+                CueFixture::hotcue(0, 0.05).label("Start"),
+                CueFixture::hotcue(1, 60.0),
+                CueFixture::saved_loop(2, 120.0, 127.5).label("Break"),
+            ],
+            ..TrackFixture::new(1, "Artist One", "First Track")
+        })
+        .track(TrackFixture {
+            bpm: 92.5,
+            key: "3B".into(),
+            beats: Some(Beats::Map(vec![441, 29_049, 57_657, 86_265])),
+            cues: vec![CueFixture::main_cue(0.01)],
+            ..TrackFixture::new(2, "Artist Two", "Live Drums")
+        })
+        .track(TrackFixture::new(
+            3,
+            "Artist & Friends",
+            "Quotes \"<Unanalyzed>\"",
+        ))
+        .playlist("Warmup", &[2, 1])
+        .hidden_playlist("Auto DJ", &[3])
+        .mixxx_crate("Techno", &[1, 3])
+        .build(Schema::Latest)
+        .await
+}
+
+/// The path of the audio file `tests/fixtures/audio/<name>`.
+pub fn audio_fixture(name: &str) -> PathBuf {
+    PathBuf::from(env!("CARGO_MANIFEST_DIR"))
+        .join("tests")
+        .join("fixtures")
+        .join("audio")
+        .join(name)
+}
+
+/// Compare `actual` to the golden file `tests/fixtures/golden/<name>`.
+///
+/// Set `UPDATE_GOLDEN=1` to write `actual` into the golden file instead, e.g. after an
+/// intentional change of the output.
+pub fn assert_golden(name: &str, actual: &str) {
+    let path = PathBuf::from(env!("CARGO_MANIFEST_DIR"))
+        .join("tests")
+        .join("fixtures")
+        .join("golden")
+        .join(name);
+
+    if std::env::var_os("UPDATE_GOLDEN").is_some() {
+        write(&path, actual).expect("Failed to write golden file");
+        return;
+    }
+
+    let expected = read_to_string(&path).unwrap_or_else(|err| {
+        panic!("Failed to read golden file {path:?}: {err}. Run with UPDATE_GOLDEN=1 to create it.")
+    });
+    pretty_assertions::assert_eq!(actual, expected, "Output differs from golden file {path:?}");
+}
+
+/// Compare `schema` to the committed JSON Schema `schema/<name>`.
+///
+/// The schemas are a public contract, so they must always match the code.
+/// Set `UPDATE_GOLDEN=1` to regenerate them.
+pub fn assert_schema(name: &str, schema: &schemars::Schema) {
+    let path = PathBuf::from(env!("CARGO_MANIFEST_DIR"))
+        .join("schema")
+        .join(name);
+    let actual = serde_json::to_string_pretty(schema).expect("Failed to serialize schema") + "\n";
+
+    if std::env::var_os("UPDATE_GOLDEN").is_some() {
+        write(&path, &actual).expect("Failed to write schema file");
+        return;
+    }
+
+    let expected = read_to_string(&path).unwrap_or_else(|err| {
+        panic!("Failed to read schema {path:?}: {err}. Run with UPDATE_GOLDEN=1 to create it.")
+    });
+    pretty_assertions::assert_eq!(actual, expected, "Schema differs from {path:?}");
+}
@@ -1,3 +1,8 @@
+use std::fmt;
+
+use schemars::JsonSchema;
+use serde_derive::Serialize;
+
 /// In mixxx, track positions are saved as absolute `actual_timestamp_seconds * sample_rate`.
 /// For some reason, this doesn't always use the sample_rate of the track, but rather 88_200 Hz.
 /// TODO: Find out how the sample rate is determined!
@@ -6,3 +11,61 @@
 pub fn convert_mixxx_position(cue_position: f64, sample_rate: i64) -> f64 {
     cue_position / sample_rate as f64
 }
+
+/// The sample rate that's used for the positions of cue points.
+/// See [convert_mixxx_position] for more info.
+pub const CUE_SAMPLE_RATE: i64 = 88_200;
+
+/// The type of a Mixxx cue, which is saved as an integer in the database.
+#[derive(Serialize, JsonSchema, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum CueType {
+    /// A numbered hotcue.
+    Hotcue,
+    /// The main cue point, which is used by the "Cue" button.
+    MainCue,
+    Beat,
+    /// A saved loop. It may also be a hotcue.
+    Loop,
+    Jump,
+    /// The intro, which is used by Auto DJ.
+    Intro,
+    /// The outro, which is used by Auto DJ.
+    Outro,
+    /// The range of the track that's actually audible.
+    Audible,
+    Unknown,
+}
+
+impl CueType {
+    pub fn from_mixxx(cue_type: i64) -> Self {
+        match cue_type {
+            1 => CueType::Hotcue,
+            2 => CueType::MainCue,
+            3 => CueType::Beat,
+            4 => CueType::Loop,
+            5 => CueType::Jump,
+            6 => CueType::Intro,
+            7 => CueType::Outro,
+            8 => CueType::Audible,
+            _ => CueType::Unknown,
+        }
+    }
+}
+
+impl fmt::Display for CueType {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            CueType::Hotcue => "hotcue",
+            CueType::MainCue => "main cue",
+            CueType::Beat => "beat",
+            CueType::Loop => "loop",
+            CueType::Jump => "jump",
+            CueType::Intro => "intro",
+            CueType::Outro => "outro",
+            CueType::Audible => "audible range",
+            CueType::Unknown => "unknown cue",
+        };
+        write!(f, "{name}")
+    }
+}
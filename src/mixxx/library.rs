@@ -3,19 +3,23 @@ use std::{collections::BTreeMap, io::Cursor};
 use anyhow::{Context, Result};
 use chrono::NaiveDateTime;
 use prost::Message;
+use schemars::JsonSchema;
 use serde_derive::{Deserialize, Serialize};
 
-use super::schema::{beats::BeatGrid, cue::Cue};
-use crate::mixxx::helper::convert_mixxx_position;
+use super::schema::{
+    beats::{BeatGrid, BeatMap},
+    cue::Cue,
+};
+use crate::{color::RgbColor, mixxx::helper::convert_mixxx_position};
 
-#[derive(Serialize, Deserialize, Debug, Clone)]
+#[derive(Serialize, Deserialize, JsonSchema, Debug, Clone)]
 pub struct Library {
     pub tracks: BTreeMap<usize, Track>,
     pub playlists: Vec<Playlist>,
     pub crates: Vec<Crate>,
 }
 
-#[derive(Serialize, Deserialize, Debug, Clone)]
+#[derive(Serialize, Deserialize, JsonSchema, Debug, Clone)]
 pub struct Track {
     pub id: usize,
     pub artist: String,
@@ -24,24 +28,94 @@ pub struct Track {
     pub album: Option<String>,
     pub year: String,
     pub genre: Option<String>,
+    pub grouping: Option<String>,
     pub tracknumber: Option<String>,
+    pub tracktotal: Option<String>,
+    /// The disc number isn't saved by Mixxx, but can be read from the file's tags.
+    pub disc_number: Option<String>,
+    /// The record label isn't saved by Mixxx, but can be read from the file's tags.
+    pub label: Option<String>,
+    /// The remixer isn't saved by Mixxx, but can be read from the file's tags.
+    pub remixer: Option<String>,
     pub filetype: String,
     pub comment: Option<String>,
     pub url: Option<String>,
+    /// The track colour that's set by the user. `None` if no colour is set.
+    pub color: Option<RgbColor>,
     pub location: TrackLocation,
+    pub cover_art: CoverArt,
     pub technical_info: TrackTechnicalInfo,
     pub metadata: TrackMetadata,
     pub cues: Vec<Cue>,
 }
 
-#[derive(Serialize, Deserialize, Debug, Clone)]
+#[derive(Serialize, Deserialize, JsonSchema, Debug, Clone)]
 pub struct TrackLocation {
     pub location: String,
     pub filename: String,
     pub directory: String,
+    /// The size of the file in bytes.
+    pub filesize: Option<u64>,
 }
 
-#[derive(Serialize, Deserialize, Debug, Clone)]
+#[derive(Serialize, Deserialize, JsonSchema, Debug, Clone)]
+pub struct CoverArt {
+    pub source: CoverArtSource,
+    pub cover_type: CoverArtType,
+    /// The location of the image file, if the cover art is a separate file.
+    /// This is usually relative to the track's directory.
+    pub location: Option<String>,
+    /// Mixxx's hash of the image data.
+    pub hash: i64,
+    /// The path of the exported image relative to the target directory.
+    /// Only set if the cover art has been exported.
+    pub exported: Option<String>,
+}
+
+/// How the cover art has been chosen.
+#[derive(Serialize, Deserialize, JsonSchema, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum CoverArtSource {
+    Unknown,
+    /// Mixxx picked the cover art automatically.
+    Guessed,
+    /// The user explicitly selected the cover art.
+    UserSelected,
+}
+
+impl CoverArtSource {
+    pub fn from_mixxx(source: i64) -> Self {
+        match source {
+            1 => CoverArtSource::Guessed,
+            2 => CoverArtSource::UserSelected,
+            _ => CoverArtSource::Unknown,
+        }
+    }
+}
+
+/// Where the cover art is stored.
+#[derive(Serialize, Deserialize, JsonSchema, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum CoverArtType {
+    /// Mixxx doesn't know about any cover art.
+    None,
+    /// The cover art is embedded in the audio file's tags.
+    Metadata,
+    /// The cover art is a separate image file.
+    File,
+}
+
+impl CoverArtType {
+    pub fn from_mixxx(cover_type: i64) -> Self {
+        match cover_type {
+            1 => CoverArtType::Metadata,
+            2 => CoverArtType::File,
+            _ => CoverArtType::None,
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, JsonSchema, Debug, Clone)]
 pub struct TrackTechnicalInfo {
     pub duration: f64,
     pub bitrate: i64,
@@ -54,43 +128,86 @@ pub struct TrackTechnicalInfo {
     pub replaygain_peak: f64,
 }
 
+/// The decoded beats of a track. All positions are in seconds.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Beats {
+    /// A constant tempo, starting at the first beat.
+    Grid { bpm: f64, first_beat: f64 },
+    /// A variable tempo with the position of each enabled beat.
+    Map(Vec<f64>),
+}
+
 impl TrackTechnicalInfo {
+    /// Decode the beats, which Mixxx saves as protobuf.
+    /// Returns `None`, if the track hasn't been analysed yet.
+    pub fn decode_beats(&self) -> Result<Option<Beats>> {
+        let Some(bytes) = &self.beats else {
+            return Ok(None);
+        };
+
+        if self
+            .beats_version
+            .as_deref()
+            .is_some_and(|version| version.starts_with("BeatMap"))
+        {
+            let map = BeatMap::decode(&mut Cursor::new(bytes))
+                .context("Failed to decode beatmap info")?;
+            let beats = map
+                .beat
+                .iter()
+                .filter(|beat| beat.enabled())
+                .filter_map(|beat| beat.frame_position)
+                .map(|position| convert_mixxx_position(position.into(), self.samplerate))
+                .collect();
+
+            return Ok(Some(Beats::Map(beats)));
+        }
+
+        let grid =
+            BeatGrid::decode(&mut Cursor::new(bytes)).context("Failed to decode beatgrid info")?;
+        let Some(first_beat) = grid.first_beat.and_then(|beat| beat.frame_position) else {
+            return Ok(None);
+        };
+
+        Ok(Some(Beats::Grid {
+            bpm: grid.bpm.and_then(|bpm| bpm.bpm).unwrap_or(self.bpm),
+            first_beat: convert_mixxx_position(first_beat.into(), self.samplerate),
+        }))
+    }
+
     pub fn get_start_of_beatgrid(&self) -> Result<Option<f64>> {
-        if let Some(bytes) = &self.beats {
-            let grid = BeatGrid::decode(&mut Cursor::new(bytes))
-                .context("Failed to decode beatgrid info")?;
-
-            if let Some(beat) = &grid.first_beat {
-                if let Some(position) = beat.frame_position {
-                    // Get the adjusted beatgrid position
-                    let mut position = convert_mixxx_position(position.into(), self.samplerate);
-
-                    let beat_length = 60.0 / self.bpm;
-
-                    // Mixxx tends to use negative numbers to indicate the first beat.
-                    // Rekordbox however doesn't do this, which is why we have to adjust this.
-                    if position.is_sign_negative() {
-                        // Simply add one beat to get to the start of first beat that's actually
-                        // inside of the track.
-                        position += beat_length;
-                    }
-
-                    // For some reason, Mixxx sometimes refers to the second beat that's inside
-                    // the track. Subtract a beat, if that's the case.
-                    if position > beat_length {
-                        position -= beat_length;
-                    }
-
-                    return Ok(Some(position));
-                }
+        let mut position = match self.decode_beats()? {
+            Some(Beats::Grid { first_beat, .. }) => first_beat,
+            // Tracks with a variable tempo have a beatmap with the position of each single beat.
+            // Their first beat inside the track is the actual start, the heuristics below only
+            // apply to a constant tempo.
+            Some(Beats::Map(beats)) => {
+                return Ok(beats.into_iter().find(|beat| !beat.is_sign_negative()));
             }
+            None => return Ok(None),
+        };
+
+        let beat_length = 60.0 / self.bpm;
+
+        // Mixxx tends to use negative numbers to indicate the first beat.
+        // Rekordbox however doesn't do this, which is why we have to adjust this.
+        if position.is_sign_negative() {
+            // Simply add one beat to get to the start of first beat that's actually
+            // inside of the track.
+            position += beat_length;
+        }
+
+        // For some reason, Mixxx sometimes refers to the second beat that's inside
+        // the track. Subtract a beat, if that's the case.
+        if position > beat_length {
+            position -= beat_length;
         }
 
-        Ok(None)
+        Ok(Some(position))
     }
 }
 
-#[derive(Serialize, Deserialize, Debug, Clone)]
+#[derive(Serialize, Deserialize, JsonSchema, Debug, Clone)]
 pub struct TrackMetadata {
     pub rating: i64,
     pub played: bool,
@@ -99,7 +216,7 @@ pub struct TrackMetadata {
     pub datetime_added: NaiveDateTime,
 }
 
-#[derive(Serialize, Deserialize, Debug, Clone)]
+#[derive(Serialize, Deserialize, JsonSchema, Debug, Clone)]
 pub struct Playlist {
     pub id: usize,
     pub name: String,
@@ -110,7 +227,7 @@ pub struct Playlist {
     pub track_ids: Vec<usize>,
 }
 
-#[derive(Serialize, Deserialize, Debug, Clone)]
+#[derive(Serialize, Deserialize, JsonSchema, Debug, Clone)]
 pub struct Crate {
     pub id: usize,
     pub name: String,
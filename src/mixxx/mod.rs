@@ -1,6 +1,9 @@
 /// The logic that executes the queries, cleans the data and brings
 /// it into an easier to handle format.
 pub mod aggregator;
+/// Synthetic Mixxx libraries for tests.
+#[cfg(any(test, feature = "test-fixtures"))]
+pub mod fixture;
 /// Some helper methods to work with mixxx's data structures
 pub mod helper;
 /// The full representation of a Mixxx library in a clean and organized structure.
@@ -1,7 +1,8 @@
+use schemars::JsonSchema;
 use serde_derive::{Deserialize, Serialize};
 use sqlx::FromRow;
 
-#[derive(Serialize, Deserialize, FromRow, Debug, Clone)]
+#[derive(Serialize, Deserialize, JsonSchema, FromRow, Debug, Clone)]
 pub struct Cue {
     pub id: i64,
     pub track_id: i64,
@@ -38,20 +38,17 @@ pub struct Track {
     //pub keys_version: String,
     //pub keys_sub_version: String,
     //pub key_id: i64,
-    //pub grouping: String,
-    //pub coverart_source: INTEGER DEFAULT 0,
-    //pub coverart_type: INTEGER DEFAULT 0,
-    //pub coverart_location: TEXT DEFAULT "",
-    //pub coverart_hash: INTEGER DEFAULT 0,
+    pub grouping: Option<String>,
+    pub coverart_source: Option<i64>,
+    pub coverart_type: Option<i64>,
+    pub coverart_location: Option<String>,
+    pub coverart_hash: Option<i64>,
     pub replaygain_peak: f64,
-    //pub tracktotal: TEXT DEFAULT '//',
-    //pub color: Option<i64>,
-}
-
-#[derive(FromRow, Debug, Clone)]
-pub struct TrackLocation {
-    pub id: i64,
-    pub location: Option<String>,
-    pub filename: Option<String>,
-    pub directory: Option<String>,
+    pub tracktotal: Option<String>,
+    pub color: Option<i64>,
+    // The following fields are joined from the `track_locations` table.
+    pub location_path: Option<String>,
+    pub location_filename: Option<String>,
+    pub location_directory: Option<String>,
+    pub location_filesize: Option<i64>,
 }
@@ -1,29 +1,66 @@
 use anyhow::{Context, Result};
 use sqlx::SqliteConnection;
 
+use super::version::SchemaVersion;
 use crate::mixxx::schema::cue::Cue;
 
-pub async fn get_track_cues(con: &mut SqliteConnection, track_id: i64) -> Result<Vec<Cue>> {
-    let cues = sqlx::query_as!(
-        Cue,
-        r#"
-        SELECT
-            id,
-            track_id,
-            type as cue_type,
-            position,
-            length,
-            hotcue,
-            label,
-            color
-        FROM cues
-        WHERE track_id = $1
-        "#,
-        track_id
-    )
-    .fetch_all(con)
-    .await
-    .context(format!("Failed to get queues for track {track_id}"))?;
+/// Get the cues of all tracks, ordered by their id.
+pub async fn get_cues(con: &mut SqliteConnection, version: SchemaVersion) -> Result<Vec<Cue>> {
+    let cues = match version {
+        // Mixxx 2.2 saved the id of one of its predefined colours.
+        // Those are mapped to the respective RGB values of Mixxx's hotcue palette.
+        SchemaVersion::Mixxx22 => {
+            sqlx::query_as!(
+                Cue,
+                r#"
+                SELECT
+                    id,
+                    track_id,
+                    type as cue_type,
+                    position,
+                    length,
+                    hotcue,
+                    label,
+                    CASE color
+                        WHEN 1 THEN 0xC50A08
+                        WHEN 2 THEN 0x32BE44
+                        WHEN 3 THEN 0x0044FF
+                        WHEN 4 THEN 0xF8D200
+                        WHEN 5 THEN 0x42D4F4
+                        WHEN 6 THEN 0xAF00CC
+                        WHEN 7 THEN 0xFCA6D7
+                        WHEN 8 THEN 0xF2F2FF
+                        ELSE 4294901760
+                    END as "color!: i64"
+                FROM cues
+                ORDER BY id ASC
+                "#,
+            )
+            .fetch_all(con)
+            .await
+        }
+        SchemaVersion::Mixxx23 => {
+            sqlx::query_as!(
+                Cue,
+                r#"
+                SELECT
+                    id,
+                    track_id,
+                    type as cue_type,
+                    position,
+                    length,
+                    hotcue,
+                    label,
+                    color
+                FROM cues
+                ORDER BY id ASC
+                "#,
+            )
+            .fetch_all(con)
+            .await
+        }
+    }
+    .context("Failed to get cues")?;
 
     Ok(cues)
 }
@@ -22,24 +22,24 @@ pub async fn get_crates(con: &mut SqliteConnection) -> Result<Vec<Crate>> {
     Ok(crates)
 }
 
-pub async fn get_crate_tracks(con: &mut SqliteConnection, crate_id: i64) -> Result<Vec<i64>> {
-    let track_ids = sqlx::query!(
+/// Get the `(crate_id, track_id)` pairs of all crates.
+pub async fn get_crate_tracks(con: &mut SqliteConnection) -> Result<Vec<(i64, i64)>> {
+    let records = sqlx::query!(
         r#"
-        SELECT track_id
+        SELECT crate_id, track_id
         FROM crate_tracks
-        WHERE crate_id = $1
+        ORDER BY crate_id ASC, track_id ASC
         "#,
-        crate_id
     )
     .fetch_all(con)
     .await
-    .context(format!("Failed to get tracks for crate {crate_id}"))?;
+    .context("Failed to get crate tracks")?;
 
     // Get the actual integers from the returned record structs.
-    let track_ids = track_ids
+    let pairs = records
         .into_iter()
-        .map(|record| record.track_id)
+        .map(|record| (record.crate_id, record.track_id))
         .collect();
 
-    Ok(track_ids)
+    Ok(pairs)
 }
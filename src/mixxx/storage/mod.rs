@@ -2,3 +2,4 @@ pub mod cue;
 pub mod mcrate;
 pub mod playlist;
 pub mod track;
+pub mod version;
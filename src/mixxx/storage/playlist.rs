@@ -24,25 +24,27 @@ pub async fn get_playlists(con: &mut SqliteConnection) -> Result<Vec<Playlist>>
     Ok(playlists)
 }
 
-pub async fn get_playlist_tracks(con: &mut SqliteConnection, playlist_id: i64) -> Result<Vec<i64>> {
-    let track_ids = sqlx::query!(
+/// Get the `(playlist_id, track_id)` pairs of all playlists.
+/// The tracks of each playlist are ordered by their position.
+pub async fn get_playlist_tracks(con: &mut SqliteConnection) -> Result<Vec<(i64, i64)>> {
+    let records = sqlx::query!(
         r#"
-        SELECT track_id AS "track_id!"
+        SELECT
+            playlist_id AS "playlist_id!",
+            track_id AS "track_id!"
         FROM PlaylistTracks
-        WHERE playlist_id = $1
-        ORDER BY position ASC
+        ORDER BY playlist_id ASC, position ASC
         "#,
-        playlist_id
     )
     .fetch_all(con)
     .await
-    .context(format!("Failed to get tracks for playlist {playlist_id}"))?;
+    .context("Failed to get playlist tracks")?;
 
     // Get the actual integers from the returned record structs.
-    let track_ids = track_ids
+    let pairs = records
         .into_iter()
-        .map(|record| record.track_id)
+        .map(|record| (record.playlist_id, record.track_id))
         .collect();
 
-    Ok(track_ids)
+    Ok(pairs)
 }
@@ -1,82 +1,125 @@
 use anyhow::{Context, Result};
 use sqlx::SqliteConnection;
 
-use crate::mixxx::schema::track::{Track, TrackLocation};
+use super::version::SchemaVersion;
+use crate::mixxx::schema::track::Track;
 
-pub async fn get_tracks(con: &mut SqliteConnection) -> Result<Vec<Track>> {
-    let tracks = sqlx::query_as!(
-        Track,
-        r#"
-        SELECT
-            id,
-            artist,
-            title,
-            album,
-            year,
-            genre,
-            composer as "composer!",
-            tracknumber,
-            location,
-            comment,
-            url,
-            duration as "duration!",
-            bitrate as "bitrate!",
-            samplerate as "samplerate!",
-            cuepoint,
-            bpm as "bpm!",
-            channels,
-            datetime_added as "datetime_added!",
-            mixxx_deleted,
-            played,
-            -- header_parsed as "header_parsed!",
-            filetype as "filetype!",
-            replaygain as "replaygain!",
-            timesplayed as "timesplayed!",
-            rating as "rating!",
-            key as "key!",
-            beats,
-            beats_version,
-            -- bpm_lock,
-            -- beats_sub_version,
-            -- keys,
-            -- keys_version,
-            -- keys_sub_version,
-            -- key_id,
-            -- grouping as "grouping!",
-            -- coverart_source,
-            -- coverart_type,
-            -- coverart_location,
-            -- coverart_hash,
-            replaygain_peak as "replaygain_peak!"
-            -- tracktotal,
-            -- color
-        FROM library
-        "#
-    )
-    .fetch_all(con)
-    .await?;
+/// Get all tracks including their location.
+/// Tracks without a location are skipped.
+pub async fn get_tracks(con: &mut SqliteConnection, version: SchemaVersion) -> Result<Vec<Track>> {
+    let tracks = match version {
+        SchemaVersion::Mixxx22 => {
+            sqlx::query_as!(
+                Track,
+                r#"
+                SELECT
+                    library.id,
+                    library.artist,
+                    library.title,
+                    library.album,
+                    library.year,
+                    library.genre,
+                    library.composer as "composer!",
+                    library.tracknumber,
+                    library.location,
+                    library.comment,
+                    library.url,
+                    library.duration as "duration!",
+                    library.bitrate as "bitrate!",
+                    library.samplerate as "samplerate!",
+                    library.cuepoint,
+                    library.bpm as "bpm!",
+                    library.channels,
+                    library.datetime_added as "datetime_added!",
+                    library.mixxx_deleted,
+                    library.played,
+                    library.filetype as "filetype!",
+                    library.replaygain as "replaygain!",
+                    library.timesplayed as "timesplayed!",
+                    library.rating as "rating!",
+                    library.key as "key!",
+                    library.beats,
+                    library.beats_version,
+                    library.grouping,
+                    library.coverart_source,
+                    library.coverart_type,
+                    library.coverart_location,
+                    library.coverart_hash,
+                    library.replaygain_peak as "replaygain_peak!",
+                    library.tracktotal,
+                    -- Track colours were introduced with Mixxx 2.3.
+                    NULL as "color: i64",
+                    track_locations.location as location_path,
+                    track_locations.filename as location_filename,
+                    track_locations.directory as location_directory,
+                    track_locations.filesize as location_filesize
+                FROM library
+                INNER JOIN track_locations ON library.location = track_locations.id
+                "#
+            )
+            .fetch_all(con)
+            .await
+        }
+        SchemaVersion::Mixxx23 => {
+            sqlx::query_as!(
+                Track,
+                r#"
+                SELECT
+                    library.id,
+                    library.artist,
+                    library.title,
+                    library.album,
+                    library.year,
+                    library.genre,
+                    library.composer as "composer!",
+                    library.tracknumber,
+                    library.location,
+                    library.comment,
+                    library.url,
+                    library.duration as "duration!",
+                    library.bitrate as "bitrate!",
+                    library.samplerate as "samplerate!",
+                    library.cuepoint,
+                    library.bpm as "bpm!",
+                    library.channels,
+                    library.datetime_added as "datetime_added!",
+                    library.mixxx_deleted,
+                    library.played,
+                    -- library.header_parsed as "header_parsed!",
+                    library.filetype as "filetype!",
+                    library.replaygain as "replaygain!",
+                    library.timesplayed as "timesplayed!",
+                    library.rating as "rating!",
+                    library.key as "key!",
+                    library.beats,
+                    library.beats_version,
+                    -- library.bpm_lock,
+                    -- library.beats_sub_version,
+                    -- library.keys,
+                    -- library.keys_version,
+                    -- library.keys_sub_version,
+                    -- library.key_id,
+                    library.grouping,
+                    library.coverart_source,
+                    library.coverart_type,
+                    library.coverart_location,
+                    library.coverart_hash,
+                    library.replaygain_peak as "replaygain_peak!",
+                    library.tracktotal,
+                    library.color,
+                    track_locations.location as location_path,
+                    track_locations.filename as location_filename,
+                    track_locations.directory as location_directory,
+                    track_locations.filesize as location_filesize
+                FROM library
+                INNER JOIN track_locations ON library.location = track_locations.id
+                "#
+            )
+            .fetch_all(con)
+            .await
+        }
+    }
+    .context("Failed to get tracks")?;
 
     Ok(tracks)
 }
-
-pub async fn get_track_location(con: &mut SqliteConnection, id: i64) -> Result<TrackLocation> {
-    let location = sqlx::query_as!(
-        TrackLocation,
-        r#"
-        SELECT
-            id,
-            location,
-            filename,
-            directory
-        FROM track_locations
-        WHERE
-            id = $1
-        "#,
-        id
-    )
-    .fetch_one(con)
-    .await
-    .context(format!("Failed to get location for id {id}"))?;
-
-    Ok(location)
-}
@@ -0,0 +1,92 @@
+use anyhow::{Context, Result, bail};
+use log::info;
+use sqlx::SqliteConnection;
+
+/// The oldest supported schema version, which is used by Mixxx 2.2.
+pub const MIN_SCHEMA_VERSION: i64 = 28;
+/// The newest supported schema version, which is used by Mixxx 2.4 and 2.5.
+pub const MAX_SCHEMA_VERSION: i64 = 39;
+
+/// The supported versions of the Mixxx database schema.
+///
+/// Versions are grouped by the set of queries that's needed to read them.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SchemaVersion {
+    /// Mixxx 2.2 (schema version 28).
+    /// Tracks don't have a colour yet and cue colours are ids of Mixxx's predefined colours.
+    Mixxx22,
+    /// Mixxx 2.3 up to 2.5 (schema versions 29 to 39).
+    Mixxx23,
+}
+
+impl SchemaVersion {
+    pub fn from_revision(revision: i64) -> Result<Self> {
+        match revision {
+            MIN_SCHEMA_VERSION => Ok(SchemaVersion::Mixxx22),
+            29..=MAX_SCHEMA_VERSION => Ok(SchemaVersion::Mixxx23),
+            _ => bail!(
+                "Mixxx database schema version {revision} isn't supported. \
+                Supported are versions {MIN_SCHEMA_VERSION} (Mixxx 2.2) to {MAX_SCHEMA_VERSION} \
+                (Mixxx 2.5)."
+            ),
+        }
+    }
+}
+
+/// Read the schema version from the `settings` table.
+pub async fn get_schema_version(con: &mut SqliteConnection) -> Result<SchemaVersion> {
+    // Mixxx saves the version as `mixxx.schema.version`.
+    // `mixxx.db.version` is accepted as well, in case a fork or future version renames it.
+    let settings = sqlx::query!(
+        r#"
+        SELECT name as "name!", value
+        FROM settings
+        WHERE name IN ('mixxx.schema.version', 'mixxx.db.version')
+        ORDER BY name DESC
+        "#
+    )
+    .fetch_all(con)
+    .await
+    .context("Failed to read schema version from settings table")?;
+
+    let Some(setting) = settings.into_iter().next() else {
+        bail!("Mixxx database doesn't contain a schema version. Is this really a Mixxx database?");
+    };
+    let value = setting.value.unwrap_or_default();
+    let revision = value.trim().parse::<i64>().context(format!(
+        "Invalid Mixxx database schema version '{value}' in setting {}",
+        setting.name
+    ))?;
+    info!("Detected Mixxx database schema version {revision}");
+
+    SchemaVersion::from_revision(revision)
+}
+
+#[cfg(test)]
+mod tests {
+    use pretty_assertions::assert_eq;
+    use rstest::rstest;
+
+    use super::*;
+
+    #[rstest]
+    #[case::mixxx_2_2(28, SchemaVersion::Mixxx22)]
+    #[case::first_mixxx_2_3(29, SchemaVersion::Mixxx23)]
+    #[case::before_latest(38, SchemaVersion::Mixxx23)]
+    #[case::latest(39, SchemaVersion::Mixxx23)]
+    fn supported_revisions(#[case] revision: i64, #[case] expected: SchemaVersion) {
+        assert_eq!(SchemaVersion::from_revision(revision).unwrap(), expected);
+    }
+
+    #[rstest]
+    #[case::negative(-1)]
+    #[case::zero(0)]
+    #[case::before_mixxx_2_2(27)]
+    #[case::newer(40)]
+    fn reject_unsupported_revisions(#[case] revision: i64) {
+        let err = SchemaVersion::from_revision(revision).unwrap_err();
+        assert!(err.to_string().starts_with(&format!(
+            "Mixxx database schema version {revision} isn't supported."
+        )));
+    }
+}
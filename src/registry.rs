@@ -0,0 +1,141 @@
+use std::{fmt, path::Path};
+
+use anyhow::{Result, bail};
+
+use crate::{
+    export::{
+        Exporter,
+        json::JsonExporter,
+        public_json::PublicJsonExporter,
+        rekordbox::RekordboxExporter,
+        spreadsheet::{SpreadsheetExporter, SpreadsheetFormat},
+    },
+    import::{Importer, json::JsonImporter, mixxx::MixxxImporter},
+};
+
+/// All known import and export formats.
+///
+/// [Registry::default] contains all formats that ship with this crate.
+/// Tools that embed the exporter can register their own formats on top of that.
+pub struct Registry {
+    importers: Vec<Box<dyn Importer>>,
+    exporters: Vec<Box<dyn Exporter>>,
+}
+
+impl Default for Registry {
+    fn default() -> Self {
+        let mut registry = Registry::empty();
+        registry.register_importer(Box::new(MixxxImporter));
+        registry.register_importer(Box::new(JsonImporter));
+        registry.register_exporter(Box::new(RekordboxExporter));
+        registry.register_exporter(Box::new(JsonExporter));
+        registry.register_exporter(Box::new(PublicJsonExporter));
+        registry.register_exporter(Box::new(SpreadsheetExporter(SpreadsheetFormat::Csv)));
+        #[cfg(feature = "xlsx")]
+        registry.register_exporter(Box::new(SpreadsheetExporter(SpreadsheetFormat::Xlsx)));
+        #[cfg(feature = "ods")]
+        registry.register_exporter(Box::new(SpreadsheetExporter(SpreadsheetFormat::Ods)));
+
+        registry
+    }
+}
+
+/// Only the names of the formats are shown, as importers and exporters are trait objects.
+impl fmt::Debug for Registry {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Registry")
+            .field(
+                "importers",
+                &self
+                    .importers()
+                    .map(|importer| importer.name())
+                    .collect::<Vec<_>>(),
+            )
+            .field(
+                "exporters",
+                &self
+                    .exporters()
+                    .map(|exporter| exporter.name())
+                    .collect::<Vec<_>>(),
+            )
+            .finish()
+    }
+}
+
+impl Registry {
+    /// A registry without any formats.
+    pub fn empty() -> Self {
+        Registry {
+            importers: Vec::new(),
+            exporters: Vec::new(),
+        }
+    }
+
+    /// Add an importer. An existing importer with the same name is replaced.
+    pub fn register_importer(&mut self, importer: Box<dyn Importer>) {
+        self.importers
+            .retain(|known| known.name() != importer.name());
+        self.importers.push(importer);
+    }
+
+    /// Add an exporter. An existing exporter with the same name is replaced.
+    pub fn register_exporter(&mut self, exporter: Box<dyn Exporter>) {
+        self.exporters
+            .retain(|known| known.name() != exporter.name());
+        self.exporters.push(exporter);
+    }
+
+    /// All importers in the order of their registration.
+    pub fn importers(&self) -> impl Iterator<Item = &dyn Importer> {
+        self.importers.iter().map(Box::as_ref)
+    }
+
+    /// All exporters in the order of their registration.
+    pub fn exporters(&self) -> impl Iterator<Item = &dyn Exporter> {
+        self.exporters.iter().map(Box::as_ref)
+    }
+
+    /// Get the importer with the given name.
+    pub fn importer(&self, name: &str) -> Result<&dyn Importer> {
+        match self.importers().find(|importer| importer.name() == name) {
+            Some(importer) => Ok(importer),
+            None => bail!(
+                "Unknown import format '{name}'. Known formats are: {}",
+                self.importers()
+                    .map(|importer| importer.name())
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            ),
+        }
+    }
+
+    /// Get the importer that reads `source`, based on its file extension.
+    pub fn importer_for(&self, source: &str) -> Result<&dyn Importer> {
+        let Some(extension) = Path::new(source).extension() else {
+            bail!("Cannot detect the format of {source}, as it doesn't have a file extension");
+        };
+        let extension = extension.to_string_lossy().to_lowercase();
+
+        match self
+            .importers()
+            .find(|importer| importer.extensions().contains(&extension.as_str()))
+        {
+            Some(importer) => Ok(importer),
+            None => bail!("Unknown file extension '{extension}' of {source}"),
+        }
+    }
+
+    /// Get the exporter with the given name.
+    pub fn exporter(&self, name: &str) -> Result<&dyn Exporter> {
+        match self.exporters().find(|exporter| exporter.name() == name) {
+            Some(exporter) => Ok(exporter),
+            None => bail!(
+                "Unknown export format '{name}'. Known formats are: {}",
+                self.exporters()
+                    .map(|exporter| exporter.name())
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            ),
+        }
+    }
+}
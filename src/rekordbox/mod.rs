@@ -1,17 +1,34 @@
-use std::path::{Path, PathBuf};
+use std::{io::Write, path::Path};
 
 use anyhow::{Context, Result};
+use log::info;
 use percent_encoding::{AsciiSet, CONTROLS, percent_encode};
-
-use self::schema::{
-    library::Library,
-    playlists::{Playlist, PlaylistTrack, Playlists},
-    tracks::{Cue, Tempo, Track, TrackContent, TrackKind, Tracks, translate_key, translate_rating},
+use rayon::prelude::*;
+
+use self::{
+    schema::{
+        library::Library,
+        playlists::{Playlist, PlaylistTrack, Playlists},
+        tracks::{
+            Cue,
+            TRACK_COLOR_PALETTE,
+            Tempo,
+            Track,
+            TrackContent,
+            TrackKind,
+            Tracks,
+            translate_rating,
+        },
+    },
+    writer::LibraryWriter,
 };
 use crate::{
+    color::RgbColor,
     config::Config,
+    export::Profile,
+    key::translate_key,
     mixxx::{
-        helper::convert_mixxx_position,
+        helper::{CUE_SAMPLE_RATE, convert_mixxx_position},
         library::{Library as MixxxLibrary, Track as MixxxTrack, TrackLocation},
     },
 };
@@ -29,22 +46,104 @@ pub const PATH: &AsciiSet = &CONTROLS
     .add(b'{')
     .add(b'}');
 
+/// Tracks are converted in batches of this size, after which the progress is logged.
+const BATCH_SIZE: usize = 1000;
+
 pub mod schema;
+pub mod writer;
 
+/// Convert the whole Mixxx library into a rekordbox library in memory.
+/// Only the global settings of the `config` are used, profiles are ignored.
+///
+/// Prefer [write_rekordbox_library] for exports, as it doesn't keep the whole document in memory.
 pub fn mixxx_to_rekordbox(config: &Config, mixxx_library: MixxxLibrary) -> Result<Library> {
+    let profile = config.default_profile("rekordbox");
+    let (tracks, playlists) = convert_library(&profile, &mixxx_library)?;
+
+    Ok(Library::new(Tracks::new(tracks), Playlists::new(playlists)))
+}
+
+/// Convert all tracks and playlists of the Mixxx library.
+/// The order of the tracks is kept, even though they're converted in parallel.
+pub(crate) fn convert_library(
+    profile: &Profile,
+    mixxx_library: &MixxxLibrary,
+) -> Result<(Vec<Track>, Vec<Playlist>)> {
+    // Rekordbox only supports a fixed set of track colours.
+    let palette = profile.track_color_palette(&TRACK_COLOR_PALETTE)?;
+
+    let playlists = convert_playlists(profile, mixxx_library);
+
     // Go through all mixxx tracks and create the respective rekordbox tracks.
-    let mut rekordbox_tracks = Vec::new();
-    for mixxx_track in mixxx_library.tracks.values() {
-        let rekordbox_track = convert_track(config, mixxx_track.clone())?;
-        rekordbox_tracks.push(rekordbox_track);
+    let tracks = mixxx_library
+        .tracks
+        .values()
+        .collect::<Vec<_>>()
+        .into_par_iter()
+        .map(|mixxx_track| convert_track(profile, &palette, mixxx_track.clone()))
+        .collect::<Result<Vec<_>>>()?;
+
+    Ok((tracks, playlists))
+}
+
+/// Convert the Mixxx library and stream the resulting rekordbox XML into `writer`.
+/// Only the global settings of the `config` are used, profiles are ignored.
+///
+/// The writer is returned, so that callers can flush it.
+pub fn write_rekordbox_library<W: Write>(
+    config: &Config,
+    mixxx_library: MixxxLibrary,
+    writer: W,
+) -> Result<W> {
+    write_library(&config.default_profile("rekordbox"), &mixxx_library, writer)
+}
+
+/// Convert the Mixxx library with the settings of `profile` and stream the resulting rekordbox
+/// XML into `writer`.
+///
+/// Tracks are converted in parallel in batches of 1000 tracks. Each batch is
+/// written in the original order as soon as it's converted, so the document is never held in
+/// memory as a whole. The writer is returned, so that callers can flush it.
+pub(crate) fn write_library<W: Write>(
+    profile: &Profile,
+    mixxx_library: &MixxxLibrary,
+    writer: W,
+) -> Result<W> {
+    // Rekordbox only supports a fixed set of track colours.
+    let palette = profile.track_color_palette(&TRACK_COLOR_PALETTE)?;
+
+    let playlists = Playlists::new(convert_playlists(profile, mixxx_library));
+
+    let track_count = mixxx_library.tracks.len();
+    let mut library_writer = LibraryWriter::start(writer, track_count)?;
+
+    let mixxx_tracks: Vec<&MixxxTrack> = mixxx_library.tracks.values().collect();
+    let mut exported = 0;
+    for batch in mixxx_tracks.chunks(BATCH_SIZE) {
+        let rekordbox_tracks = batch
+            .par_iter()
+            .map(|mixxx_track| convert_track(profile, &palette, (*mixxx_track).clone()))
+            .collect::<Result<Vec<_>>>()?;
+
+        for rekordbox_track in &rekordbox_tracks {
+            library_writer.write_track(rekordbox_track)?;
+        }
+
+        exported += batch.len();
+        info!("Exported {exported}/{track_count} tracks");
     }
 
+    library_writer.finish(&playlists)
+}
+
+/// Convert all playlists and crates into rekordbox playlists.
+fn convert_playlists(profile: &Profile, mixxx_library: &MixxxLibrary) -> Vec<Playlist> {
     // Check if a playlist with the name "all" exists.
     let mut all_exists = false;
 
     let mut rekordbox_playlists = Vec::new();
     // Go through all playlists and create respective rekordbox playlists
-    for mixxx_playlist in mixxx_library.playlists {
+    for mixxx_playlist in &mixxx_library.playlists {
         // Don't show hidden playlists
         if mixxx_playlist.hidden {
             continue;
@@ -65,11 +164,11 @@ pub fn mixxx_to_rekordbox(config: &Config, mixxx_library: MixxxLibrary) -> Resul
             all_exists = true;
         }
 
-        rekordbox_playlists.push(Playlist::new(mixxx_playlist.name, playlist_tracks));
+        rekordbox_playlists.push(Playlist::new(mixxx_playlist.name.clone(), playlist_tracks));
     }
 
     // Create a playlist that contains all tracks, if it doesn't already exist.
-    if !all_exists && config.create_all_playlist {
+    if !all_exists && profile.create_all_playlist {
         let playlist_tracks = mixxx_library
             .tracks
             .keys()
@@ -81,7 +180,7 @@ pub fn mixxx_to_rekordbox(config: &Config, mixxx_library: MixxxLibrary) -> Resul
 
     // Recordbox doesn't have the concept of crates, which is why we treat them
     // to normal playlists as well.
-    for mixxx_crate in mixxx_library.crates {
+    for mixxx_crate in &mixxx_library.crates {
         // Don't show hidden crates
         if mixxx_crate.hidden {
             continue;
@@ -93,17 +192,19 @@ pub fn mixxx_to_rekordbox(config: &Config, mixxx_library: MixxxLibrary) -> Resul
             .map(|key| PlaylistTrack::new(*key))
             .collect();
 
-        rekordbox_playlists.push(Playlist::new(mixxx_crate.name, crate_tracks));
+        rekordbox_playlists.push(Playlist::new(mixxx_crate.name.clone(), crate_tracks));
     }
 
-    Ok(Library::new(
-        Tracks::new(rekordbox_tracks),
-        Playlists::new(rekordbox_playlists),
-    ))
+    rekordbox_playlists
 }
 
 /// Convert a single mixxx track into a rekordbox style track format.
-pub fn convert_track(config: &Config, mixxx_track: MixxxTrack) -> Result<Track> {
+/// The track colour is mapped to the nearest colour of the given `palette`.
+pub(crate) fn convert_track(
+    profile: &Profile,
+    palette: &[RgbColor],
+    mixxx_track: MixxxTrack,
+) -> Result<Track> {
     // At first, we have to create the inner content of the entry field.
     let mut track_inner = Vec::new();
 
@@ -140,7 +241,7 @@ pub fn convert_track(config: &Config, mixxx_track: MixxxTrack) -> Result<Track>
             2 | 6 | 7 | 8 => continue,
             // This is a normal cue point.
             1 => {
-                let position = convert_mixxx_position(cue.position as f64, 88_200);
+                let position = convert_mixxx_position(cue.position as f64, CUE_SAMPLE_RATE);
                 // Don't check cues with negative hotcues.
                 if cue.hotcue == -1 {
                     continue;
@@ -158,7 +259,8 @@ pub fn convert_track(config: &Config, mixxx_track: MixxxTrack) -> Result<Track>
 
     track_inner.push(TrackContent::Tempo(tempo));
 
-    let location = get_track_location(config, mixxx_track.location)?;
+    let size = mixxx_track.location.filesize.unwrap_or_default();
+    let location = get_track_location(profile, mixxx_track.location)?;
 
     Ok(Track {
         track_id: mixxx_track.id.to_string(),
@@ -166,13 +268,22 @@ pub fn convert_track(config: &Config, mixxx_track: MixxxTrack) -> Result<Track>
         artist: mixxx_track.artist,
         composer: mixxx_track.composer,
         album: mixxx_track.album.unwrap_or_default(),
-        grouping: String::new(),
+        grouping: mixxx_track.grouping.unwrap_or_default(),
         genre: mixxx_track.genre.unwrap_or_default(),
         kind: TrackKind::from_string(mixxx_track.filetype),
-        size: 0.to_string(),
+        size: size.to_string(),
         total_time: mixxx_track.technical_info.duration as u32,
-        disc_number: 1,
-        track_number: mixxx_track.tracknumber.unwrap_or_default(),
+        disc_number: mixxx_track
+            .disc_number
+            .as_deref()
+            .and_then(parse_position)
+            .unwrap_or(1),
+        track_number: mixxx_track
+            .tracknumber
+            .as_deref()
+            .and_then(parse_position)
+            .map(|number| number.to_string())
+            .unwrap_or_default(),
         year: mixxx_track
             .year
             .parse::<u16>()
@@ -186,37 +297,33 @@ pub fn convert_track(config: &Config, mixxx_track: MixxxTrack) -> Result<Track>
         play_count: mixxx_track.metadata.timesplayed,
         rating: translate_rating(mixxx_track.metadata.rating),
         location,
-        remixer: "".into(),
+        remixer: mixxx_track.remixer.unwrap_or_default(),
         tonality: translate_key(&mixxx_track.technical_info.key).into(),
-        label: "".into(),
+        label: mixxx_track.label.unwrap_or_default(),
         mix: "".into(),
+        colour: mixxx_track
+            .color
+            .and_then(|color| color.nearest(palette))
+            .map(|color| color.to_hex()),
 
         values: track_inner,
     })
 }
 
+/// Disc and track numbers are often saved as `1/2` or `03` in tags.
+/// Rekordbox only wants the actual number.
+fn parse_position(position: &str) -> Option<u32> {
+    position.split('/').next()?.trim().parse().ok()
+}
+
 /// Windows needs a bit of special handling, since we assume that we're running Mixxx on a unix
 /// filesystem.
 /// -> We have to convert unix-style paths to Windows style paths.
-pub fn get_track_location(config: &Config, mixxx_location: TrackLocation) -> Result<String> {
-    use anyhow::bail;
-    use path_slash::PathBufExt;
-
-    let unix_path = PathBuf::from_slash(mixxx_location.location);
-    let source_root = PathBuf::from_slash(&config.source_library_root);
-
-    if !unix_path.starts_with(&source_root) {
-        bail!("Mixxx path '{unix_path:?}' is not in source_library_root {source_root:?}");
-    }
-
-    let relative_path = unix_path
-        .strip_prefix(&source_root)
-        .context("Failed to strip prefix {source_root:?} from '{unix_path:?}'")?;
-
-    // Start at the target library root
-    let mut path = PathBuf::from(&config.target_library_root);
-    // Add the relative path from the library root to the actual track.
-    path = path.join(relative_path);
+pub(crate) fn get_track_location(
+    profile: &Profile,
+    mixxx_location: TrackLocation,
+) -> Result<String> {
+    let path = profile.map_location(&mixxx_location.location)?;
 
     let encoded_path = encode_path(&path)?;
     Ok(encoded_path)
@@ -258,3 +365,68 @@ fn encode_path(path: &Path) -> Result<String> {
     // The path needs to be url-encoded, since it's basically an URL.
     Ok(url.as_str().to_owned())
 }
+
+#[cfg(test)]
+mod tests {
+    use pretty_assertions::assert_eq;
+    use rstest::rstest;
+
+    use super::*;
+    use crate::mixxx::{
+        aggregator::read_library,
+        fixture::{Beats, LibraryBuilder, Schema, TrackFixture, assert_golden, sample_library},
+    };
+
+    #[tokio::test]
+    async fn export_sample_library() -> Result<()> {
+        let mut fixture = sample_library().await?;
+        let config = fixture.config();
+        let library = read_library(&mut fixture.connection).await?;
+
+        let xml = quick_xml::se::to_string(&mixxx_to_rekordbox(&config, library.clone())?)?;
+        assert_golden("rekordbox.xml", &xml);
+
+        // The streamed export must be identical to the in-memory one.
+        let streamed = write_rekordbox_library(&config, library, Vec::new())?;
+        assert_eq!(String::from_utf8(streamed)?, xml);
+
+        Ok(())
+    }
+
+    /// Variable tempo tracks start at the first beat of their beatmap that's inside the track.
+    #[tokio::test]
+    async fn export_beatmaps() -> Result<()> {
+        let beatmap = |id, title, frames: Vec<i32>| TrackFixture {
+            bpm: 92.5,
+            beats: Some(Beats::Map(frames)),
+            ..TrackFixture::new(id, "Artist", title)
+        };
+        let mut fixture = LibraryBuilder::new()
+            .track(beatmap(1, "Inside", vec![441, 29_049]))
+            .track(beatmap(2, "Negative", vec![-14_304, 14_304]))
+            .track(beatmap(3, "Second beat", vec![40_000, 68_608]))
+            .build(Schema::Latest)
+            .await?;
+        let config = fixture.config();
+        let library = read_library(&mut fixture.connection).await?;
+
+        let rekordbox = mixxx_to_rekordbox(&config, library)?;
+        let xml = quick_xml::se::to_string(&rekordbox)?;
+        assert_golden("rekordbox_beatmaps.xml", &xml);
+
+        Ok(())
+    }
+
+    #[rstest]
+    #[case::plain("3", Some(3))]
+    #[case::of_total("1/2", Some(1))]
+    #[case::padded("03", Some(3))]
+    #[case::spaces(" 2 / 12 ", Some(2))]
+    #[case::empty("", None)]
+    #[case::no_number("/12", None)]
+    #[case::garbage("A1", None)]
+    #[case::negative("-1", None)]
+    fn parse_positions(#[case] position: &str, #[case] expected: Option<u32>) {
+        assert_eq!(parse_position(position), expected);
+    }
+}
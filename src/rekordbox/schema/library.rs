@@ -2,6 +2,9 @@ use serde_derive::{Deserialize, Serialize};
 
 use super::{playlists::Playlists, tracks::Tracks};
 
+/// The version of the rekordbox XML format.
+pub const LIBRARY_VERSION: &str = "1.0.0";
+
 #[derive(Serialize, Deserialize, Debug)]
 #[serde(rename = "DJ_PLAYLISTS")]
 pub struct Library {
@@ -18,7 +21,7 @@ pub struct Library {
 impl Library {
     pub fn new(tracks: Tracks, playlists: Playlists) -> Self {
         Library {
-            version: "1.0.0".into(),
+            version: LIBRARY_VERSION.into(),
             product: Product::default(),
             tracks,
             playlists,
@@ -44,7 +44,7 @@ impl PlaylistsRoot {
 
 /// The representation of a single Playlist.
 /// A playlist contains multiple `TRACK` elements with TrackIds that point to actual tracks.
-#[derive(Serialize, Deserialize, Debug)]
+#[derive(Serialize, Deserialize, Debug, Clone)]
 pub struct Playlist {
     #[serde(rename = "@Type")]
     r#type: u32,
@@ -69,9 +69,18 @@ impl Playlist {
             tracks,
         }
     }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// The keys of all tracks in this playlist.
+    pub fn track_keys(&self) -> impl Iterator<Item = usize> + '_ {
+        self.tracks.iter().map(|track| track.key)
+    }
 }
 
-#[derive(Serialize, Deserialize, Debug)]
+#[derive(Serialize, Deserialize, Debug, Clone)]
 pub struct PlaylistTrack {
     #[serde(rename = "@Key")]
     key: usize,
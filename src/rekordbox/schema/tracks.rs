@@ -1,5 +1,9 @@
 use serde_derive::{Deserialize, Serialize};
 
+use crate::color::RgbColor;
+// Keys used to be translated here, before they were shared by all exporters.
+pub use crate::key::translate_key;
+
 /// This is the parent object that contains all tracks in this library.
 #[derive(Serialize, Deserialize, Debug)]
 #[serde(rename = "COLLECTION")]
@@ -72,6 +76,10 @@ pub struct Track {
     pub label: String,
     #[serde(rename = "@Mix")]
     pub mix: String,
+    /// The track colour as a hex string in the form of `0xRRGGBB`.
+    /// Rekordbox only knows the colours of [TRACK_COLOR_PALETTE].
+    #[serde(rename = "@Colour", skip_serializing_if = "Option::is_none")]
+    pub colour: Option<String>,
 
     // There can be multiple `Cue` entries, which is why we have to declare
     // the content of a Track like this.
@@ -139,6 +147,19 @@ impl Cue {
     }
 }
 
+/// The fixed set of track colours that's supported by rekordbox.
+/// Pink, Red, Orange, Yellow, Green, Aqua, Blue, Purple
+pub const TRACK_COLOR_PALETTE: [RgbColor; 8] = [
+    RgbColor(0xFF007F),
+    RgbColor(0xFF0000),
+    RgbColor(0xFFA500),
+    RgbColor(0xFFFF00),
+    RgbColor(0x00FF00),
+    RgbColor(0x25FDE9),
+    RgbColor(0x0000FF),
+    RgbColor(0x660099),
+];
+
 /// A string describing the type of audio file.
 #[derive(Serialize, Deserialize, Debug)]
 pub enum TrackKind {
@@ -158,38 +179,6 @@ impl TrackKind {
     }
 }
 
-/// Many programs use something called the Camelot wheel to categorize tonal keys.
-/// Rekordbox uses a different notation, which we have to map to.
-pub fn translate_key(mixxx_key: &str) -> &'static str {
-    match mixxx_key {
-        "11B" => "A",
-        "4B" => "Ab/G#",
-        "8A" => "Am",
-        "1B" => "B",
-        "6B" => "Bb",
-        "3A" => "Bbm/A#m",
-        "10A" => "Bm",
-        "8B" => "C",
-        "12A" => "C#m/Dbm",
-        "5A" => "Cm",
-        "10B" => "D",
-        "3B" => "Db",
-        "7A" => "Dm",
-        "12B" => "E",
-        "5B" => "Eb/D#",
-        "2A" => "Ebm",
-        "9A" => "Em",
-        "7B" => "F",
-        "11A" => "F#m",
-        "4A" => "Fm",
-        "9B" => "G",
-        "1A" => "G#m/Abm",
-        "2B" => "Gb/F#",
-        "6A" => "Gm",
-        _ => "",
-    }
-}
-
 /// Rekordbox uses a byte based rating format.
 pub fn translate_rating(rating: i64) -> u8 {
     match rating {
@@ -0,0 +1,186 @@
+use std::{fmt, io::Write};
+
+use anyhow::{Context, Result, bail};
+use quick_xml::{
+    Writer,
+    events::{BytesEnd, BytesStart, Event},
+};
+
+use super::schema::{
+    library::{LIBRARY_VERSION, Product},
+    playlists::Playlists,
+    tracks::Track,
+};
+
+/// Writes a rekordbox XML document element by element, instead of serializing the whole
+/// [Library](super::schema::library::Library) at once.
+///
+/// The output is byte-identical to `quick_xml::se::to_string` of the respective library.
+/// Usage:
+/// 1. [LibraryWriter::start] with the amount of tracks that'll be written.
+/// 2. [LibraryWriter::write_track] for each track.
+/// 3. [LibraryWriter::finish] with the playlists.
+pub struct LibraryWriter<W: Write> {
+    writer: Writer<W>,
+    track_count: usize,
+    written: usize,
+}
+
+/// The XML writer itself isn't shown, as its output may not implement [Debug].
+impl<W: Write> fmt::Debug for LibraryWriter<W> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("LibraryWriter")
+            .field("track_count", &self.track_count)
+            .field("written", &self.written)
+            .finish_non_exhaustive()
+    }
+}
+
+impl<W: Write> LibraryWriter<W> {
+    /// Write everything up to the first track.
+    /// The `COLLECTION` element contains its amount of entries, so it must be known up front.
+    pub fn start(inner: W, track_count: usize) -> Result<Self> {
+        let mut writer = Writer::new(inner);
+
+        let root = BytesStart::new("DJ_PLAYLISTS").with_attributes([("Version", LIBRARY_VERSION)]);
+        writer.write_event(Event::Start(root))?;
+        writer
+            .write_serializable("PRODUCT", &Product::default())
+            .context("Failed to write product info")?;
+
+        let entries = track_count.to_string();
+        let collection =
+            BytesStart::new("COLLECTION").with_attributes([("Entries", entries.as_str())]);
+        // The serializer writes an empty element, if there aren't any tracks.
+        if track_count == 0 {
+            writer.write_event(Event::Empty(collection))?;
+        } else {
+            writer.write_event(Event::Start(collection))?;
+        }
+
+        Ok(LibraryWriter {
+            writer,
+            track_count,
+            written: 0,
+        })
+    }
+
+    /// Write a single `TRACK` element of the collection.
+    pub fn write_track(&mut self, track: &Track) -> Result<()> {
+        if self.written == self.track_count {
+            bail!(
+                "Tried to write more than the announced {} tracks",
+                self.track_count
+            );
+        }
+
+        self.writer
+            .write_serializable("TRACK", track)
+            .context(format!("Failed to write track {}", track.track_id))?;
+        self.written += 1;
+
+        Ok(())
+    }
+
+    /// Close the collection, write the playlists and return the inner writer.
+    pub fn finish(mut self, playlists: &Playlists) -> Result<W> {
+        if self.written != self.track_count {
+            bail!(
+                "Announced {} tracks, but only {} were written",
+                self.track_count,
+                self.written
+            );
+        }
+
+        if self.track_count != 0 {
+            self.writer
+                .write_event(Event::End(BytesEnd::new("COLLECTION")))?;
+        }
+        self.writer
+            .write_serializable("PLAYLISTS", playlists)
+            .context("Failed to write playlists")?;
+        self.writer
+            .write_event(Event::End(BytesEnd::new("DJ_PLAYLISTS")))?;
+
+        Ok(self.writer.into_inner())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use pretty_assertions::assert_eq;
+    use rstest::rstest;
+
+    use super::*;
+    use crate::rekordbox::schema::{
+        library::Library,
+        playlists::{Playlist, PlaylistTrack},
+        tracks::{Cue, Tempo, TrackContent, TrackKind, Tracks},
+    };
+
+    fn track(id: usize) -> Track {
+        Track {
+            track_id: id.to_string(),
+            name: format!("Title <{id}> & \"Friends\""),
+            artist: "Artist".into(),
+            composer: String::new(),
+            album: "Album".into(),
+            grouping: String::new(),
+            genre: "Techno".into(),
+            kind: TrackKind::Mp3,
+            size: "1000".into(),
+            total_time: 300,
+            disc_number: 1,
+            track_number: id.to_string(),
+            year: "2021".into(),
+            average_bpm: "128".into(),
+            date_added: "2023-01-02".into(),
+            bit_rate: 320,
+            sample_rate: 44100,
+            comments: String::new(),
+            play_count: 0,
+            rating: 0,
+            location: format!("file://localhost/C:/Music/{id}.mp3"),
+            remixer: String::new(),
+            tonality: "Am".into(),
+            label: String::new(),
+            mix: String::new(),
+            colour: id.is_multiple_of(2).then(|| "0xFF0000".into()),
+            values: vec![
+                TrackContent::Cue(Cue::new(String::new(), 1.5, 0)),
+                TrackContent::Tempo(Tempo {
+                    inizio: "0.025".into(),
+                    bpm: "128.00".into(),
+                    metro: "4/4".into(),
+                    battito: "1".into(),
+                }),
+            ],
+        }
+    }
+
+    fn playlists(track_count: usize) -> Playlists {
+        let tracks = (1..=track_count).map(PlaylistTrack::new).collect();
+        Playlists::new(vec![
+            Playlist::new("all".into(), tracks),
+            Playlist::new("empty".into(), Vec::new()),
+        ])
+    }
+
+    #[rstest]
+    #[case(0)]
+    #[case(1)]
+    #[case(3)]
+    fn streamed_output_is_identical(#[case] track_count: usize) {
+        let tracks: Vec<Track> = (1..=track_count).map(track).collect();
+        let library = Library::new(Tracks::new(tracks), playlists(track_count));
+        let expected = quick_xml::se::to_string(&library).unwrap();
+
+        let mut writer = LibraryWriter::start(Vec::new(), track_count).unwrap();
+        for id in 1..=track_count {
+            writer.write_track(&track(id)).unwrap();
+        }
+        let streamed = writer.finish(&playlists(track_count)).unwrap();
+
+        assert_eq!(String::from_utf8(streamed).unwrap(), expected);
+    }
+}
@@ -0,0 +1,164 @@
+use std::{
+    fmt::Write as _,
+    io::Write,
+    path::{Path, PathBuf},
+};
+
+use anyhow::Result;
+
+use super::{Count, LibraryStats, format_duration};
+use crate::{diff::TrackSummary, export::output::AtomicFile};
+
+pub const FILE_NAME: &str = "mixxx_library_stats.html";
+
+const STYLE: &str = "
+body { font-family: sans-serif; margin: 2em auto; max-width: 60em; color: #222; }
+h2 { margin-top: 2em; border-bottom: 1px solid #ccc; }
+table { border-collapse: collapse; width: 100%; }
+td, th { padding: 0.2em 0.5em; text-align: left; vertical-align: top; }
+td.count { width: 5em; text-align: right; }
+td.name { width: 12em; }
+.bar { background: #4a90d9; height: 1em; }
+summary { cursor: pointer; }
+";
+
+/// Write the report into `directory` and return its path.
+pub fn write_report(stats: &LibraryStats, directory: &Path) -> Result<PathBuf> {
+    let path = directory.join(FILE_NAME);
+
+    let mut file = AtomicFile::create(&path)?;
+    file.write_all(render(stats).as_bytes())?;
+    file.commit(0)?;
+
+    Ok(path)
+}
+
+/// Render the statistics as a single HTML page without any external resources.
+pub fn render(stats: &LibraryStats) -> String {
+    let mut html = String::new();
+    // Writing into a string can't fail.
+    let _ = write_page(&mut html, stats);
+
+    html
+}
+
+fn write_page(html: &mut String, stats: &LibraryStats) -> std::fmt::Result {
+    writeln!(html, "<!DOCTYPE html>")?;
+    writeln!(html, "<html lang=\"en\">\n<head>\n<meta charset=\"utf-8\">")?;
+    writeln!(html, "<title>Mixxx library statistics</title>")?;
+    writeln!(html, "<style>{STYLE}</style>\n</head>\n<body>")?;
+    writeln!(html, "<h1>Mixxx library statistics</h1>")?;
+    writeln!(
+        html,
+        "<p>{} tracks with a total duration of {}.</p>",
+        stats.tracks,
+        format_duration(stats.total_duration)
+    )?;
+
+    write_histogram(html, "File types", &stats.file_types)?;
+    write_histogram(html, "Genres", &stats.genres)?;
+    write_histogram(html, "BPM", &stats.bpm_histogram)?;
+    write_histogram(html, "Keys", &stats.key_histogram)?;
+
+    write_tracks(html, "Tracks without beatgrid", &stats.without_beatgrid)?;
+    write_tracks(html, "Tracks without key", &stats.without_key)?;
+    write_tracks(html, "Tracks without hotcues", &stats.without_hotcues)?;
+    write_tracks(html, "Never played tracks", &stats.never_played)?;
+
+    writeln!(html, "<h2>Most played tracks</h2>\n<table>")?;
+    for played in &stats.most_played {
+        writeln!(
+            html,
+            "<tr><td class=\"count\">{}</td><td>{}</td></tr>",
+            played.plays,
+            escape(&played.track.to_string())
+        )?;
+    }
+    writeln!(html, "</table>")?;
+
+    writeln!(html, "<h2>Playlists with deleted tracks</h2>\n<table>")?;
+    for playlist in &stats.playlists_with_deleted_tracks {
+        let ids: Vec<_> = playlist.track_ids.iter().map(usize::to_string).collect();
+        writeln!(
+            html,
+            "<tr><td class=\"name\">{}</td><td>Track ids {}</td></tr>",
+            escape(&playlist.name),
+            ids.join(", ")
+        )?;
+    }
+    writeln!(html, "</table>")?;
+
+    writeln!(html, "</body>\n</html>")
+}
+
+fn write_histogram(html: &mut String, title: &str, counts: &[Count]) -> std::fmt::Result {
+    let max = counts.iter().map(|count| count.tracks).max().unwrap_or(1);
+
+    writeln!(html, "<h2>{title}</h2>\n<table>")?;
+    for count in counts {
+        writeln!(
+            html,
+            "<tr><td class=\"name\">{}</td><td class=\"count\">{}</td>\
+             <td><div class=\"bar\" style=\"width: {:.1}%\"></div></td></tr>",
+            escape(&count.name),
+            count.tracks,
+            count.tracks as f64 / max as f64 * 100.0
+        )?;
+    }
+    writeln!(html, "</table>")
+}
+
+/// Long lists are collapsed, so the report stays readable.
+fn write_tracks(html: &mut String, title: &str, tracks: &[TrackSummary]) -> std::fmt::Result {
+    writeln!(html, "<h2>{title}: {}</h2>", tracks.len())?;
+    if tracks.is_empty() {
+        return Ok(());
+    }
+
+    writeln!(html, "<details>\n<summary>Show tracks</summary>\n<ul>")?;
+    for track in tracks {
+        writeln!(html, "<li>{}</li>", escape(&track.to_string()))?;
+    }
+    writeln!(html, "</ul>\n</details>")
+}
+
+fn escape(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&#39;"),
+            c => escaped.push(c),
+        }
+    }
+
+    escaped
+}
+
+#[cfg(test)]
+mod tests {
+    use anyhow::Result;
+
+    use super::{super::library_stats, *};
+    use crate::mixxx::{aggregator::read_library, fixture::sample_library};
+
+    #[tokio::test]
+    async fn escape_track_names() -> Result<()> {
+        let mut fixture = sample_library().await?;
+        let library = read_library(&mut fixture.connection).await?;
+
+        let html = render(&library_stats(&library));
+
+        assert!(
+            html.contains(
+                "<li>3 Artist &amp; Friends - Quotes &quot;&lt;Unanalyzed&gt;&quot;</li>"
+            )
+        );
+        assert!(!html.contains("<Unanalyzed>"));
+
+        Ok(())
+    }
+}
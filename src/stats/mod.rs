@@ -0,0 +1,333 @@
+use std::{
+    collections::{BTreeMap, HashMap},
+    fmt,
+};
+
+use serde_derive::Serialize;
+
+use crate::{diff::TrackSummary, key::Key, mixxx::library::Library};
+
+/// The self-contained HTML report.
+pub mod html;
+
+/// The width of a single bar of the BPM histogram.
+const BPM_BUCKET_SIZE: u32 = 5;
+/// The amount of tracks that're listed as the most played tracks.
+const MOST_PLAYED_LIMIT: usize = 20;
+/// Long lists of tracks are shortened to this amount of entries in the text output.
+const TEXT_LIST_LIMIT: usize = 10;
+
+/// Statistics and possible problems of a library.
+///
+/// Tracks that've been deleted in Mixxx are ignored, except for the playlists that still
+/// contain them.
+#[derive(Serialize, Debug, Default, Clone, PartialEq)]
+pub struct LibraryStats {
+    pub tracks: usize,
+    /// The total duration of all tracks in seconds.
+    pub total_duration: f64,
+    /// Sorted by the amount of tracks.
+    pub file_types: Vec<Count>,
+    /// Sorted by the amount of tracks.
+    pub genres: Vec<Count>,
+    /// The amount of tracks per BPM range, sorted by BPM.
+    pub bpm_histogram: Vec<Count>,
+    /// The amount of tracks per key in the Camelot notation, sorted along the wheel.
+    pub key_histogram: Vec<Count>,
+    pub without_beatgrid: Vec<TrackSummary>,
+    pub without_key: Vec<TrackSummary>,
+    pub without_hotcues: Vec<TrackSummary>,
+    pub never_played: Vec<TrackSummary>,
+    pub most_played: Vec<PlayCount>,
+    pub playlists_with_deleted_tracks: Vec<DeletedTracks>,
+}
+
+/// The amount of tracks that share a property, e.g. a genre.
+#[derive(Serialize, Debug, Clone, PartialEq)]
+pub struct Count {
+    pub name: String,
+    pub tracks: usize,
+}
+
+#[derive(Serialize, Debug, Clone, PartialEq)]
+pub struct PlayCount {
+    pub track: TrackSummary,
+    pub plays: i64,
+}
+
+/// A playlist or crate that contains tracks, which have been deleted or don't exist anymore.
+#[derive(Serialize, Debug, Clone, PartialEq)]
+pub struct DeletedTracks {
+    /// The name of the playlist. Crates are prefixed with `crate:`.
+    pub name: String,
+    pub track_ids: Vec<usize>,
+}
+
+/// Collect the statistics of a library.
+pub fn library_stats(library: &Library) -> LibraryStats {
+    let tracks: Vec<_> = library
+        .tracks
+        .values()
+        .filter(|track| !track.metadata.deleted)
+        .collect();
+
+    let mut stats = LibraryStats {
+        tracks: tracks.len(),
+        ..Default::default()
+    };
+
+    let mut file_types = HashMap::new();
+    let mut genres = HashMap::new();
+    let mut bpms = BTreeMap::new();
+    let mut keys = BTreeMap::new();
+    for track in &tracks {
+        let summary = TrackSummary::from(*track);
+        stats.total_duration += track.technical_info.duration;
+
+        *file_types.entry(track.filetype.to_lowercase()).or_insert(0) += 1;
+        let genre = track
+            .genre
+            .as_deref()
+            .map(str::trim)
+            .filter(|genre| !genre.is_empty())
+            .unwrap_or("Unknown");
+        *genres.entry(genre.to_string()).or_insert(0) += 1;
+
+        if track.technical_info.bpm > 0.0 {
+            let bucket = track.technical_info.bpm as u32 / BPM_BUCKET_SIZE * BPM_BUCKET_SIZE;
+            *bpms.entry(bucket).or_insert(0) += 1;
+        }
+
+        match Key::parse(&track.technical_info.key) {
+            Some(key) => *keys.entry(key).or_insert(0) += 1,
+            None => stats.without_key.push(summary.clone()),
+        }
+        if track.technical_info.beats.is_none() {
+            stats.without_beatgrid.push(summary.clone());
+        }
+        // Saved loops can be hotcues as well.
+        if track.cues.iter().all(|cue| cue.hotcue < 0) {
+            stats.without_hotcues.push(summary.clone());
+        }
+        if track.metadata.timesplayed == 0 {
+            stats.never_played.push(summary.clone());
+        } else {
+            stats.most_played.push(PlayCount {
+                track: summary,
+                plays: track.metadata.timesplayed,
+            });
+        }
+    }
+
+    stats.file_types = sorted_counts(file_types);
+    stats.genres = sorted_counts(genres);
+    stats.bpm_histogram = bpms
+        .into_iter()
+        .map(|(bucket, tracks)| Count {
+            name: format!("{bucket}-{}", bucket + BPM_BUCKET_SIZE - 1),
+            tracks,
+        })
+        .collect();
+    stats.key_histogram = keys
+        .into_iter()
+        .map(|(key, tracks)| Count {
+            name: key.camelot(),
+            tracks,
+        })
+        .collect();
+    // The sort is stable, so tracks with the same amount of plays stay ordered by id.
+    stats
+        .most_played
+        .sort_by_key(|played| std::cmp::Reverse(played.plays));
+    stats.most_played.truncate(MOST_PLAYED_LIMIT);
+
+    let lists = library
+        .playlists
+        .iter()
+        .map(|playlist| (playlist.name.clone(), &playlist.track_ids))
+        .chain(library.crates.iter().map(|mixxx_crate| {
+            (
+                format!("crate:{}", mixxx_crate.name),
+                &mixxx_crate.track_ids,
+            )
+        }));
+    for (name, track_ids) in lists {
+        let track_ids: Vec<_> = track_ids
+            .iter()
+            .copied()
+            .filter(|id| {
+                library
+                    .tracks
+                    .get(id)
+                    .is_none_or(|track| track.metadata.deleted)
+            })
+            .collect();
+        if !track_ids.is_empty() {
+            stats
+                .playlists_with_deleted_tracks
+                .push(DeletedTracks { name, track_ids });
+        }
+    }
+
+    stats
+}
+
+/// Sort by the amount of tracks, then by name.
+fn sorted_counts(counts: HashMap<String, usize>) -> Vec<Count> {
+    let mut counts: Vec<_> = counts
+        .into_iter()
+        .map(|(name, tracks)| Count { name, tracks })
+        .collect();
+    counts.sort_by(|left, right| {
+        right
+            .tracks
+            .cmp(&left.tracks)
+            .then_with(|| left.name.cmp(&right.name))
+    });
+
+    counts
+}
+
+/// Format seconds as e.g. `12h 03m 09s`.
+pub fn format_duration(seconds: f64) -> String {
+    let seconds = seconds.round() as u64;
+    format!(
+        "{}h {:02}m {:02}s",
+        seconds / 3600,
+        seconds / 60 % 60,
+        seconds % 60
+    )
+}
+
+fn write_counts(f: &mut fmt::Formatter<'_>, title: &str, counts: &[Count]) -> fmt::Result {
+    writeln!(f, "{title}:")?;
+    for count in counts {
+        writeln!(f, "  {:<12} {}", count.name, count.tracks)?;
+    }
+
+    Ok(())
+}
+
+fn write_tracks(f: &mut fmt::Formatter<'_>, title: &str, tracks: &[TrackSummary]) -> fmt::Result {
+    writeln!(f, "{title}: {}", tracks.len())?;
+    for track in tracks.iter().take(TEXT_LIST_LIMIT) {
+        writeln!(f, "  {track}")?;
+    }
+    if tracks.len() > TEXT_LIST_LIMIT {
+        writeln!(f, "  ... and {} more", tracks.len() - TEXT_LIST_LIMIT)?;
+    }
+
+    Ok(())
+}
+
+impl fmt::Display for LibraryStats {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "Tracks: {}", self.tracks)?;
+        writeln!(
+            f,
+            "Total duration: {}",
+            format_duration(self.total_duration)
+        )?;
+        writeln!(f)?;
+        write_counts(f, "File types", &self.file_types)?;
+        write_counts(f, "Genres", &self.genres)?;
+        write_counts(f, "BPM", &self.bpm_histogram)?;
+        write_counts(f, "Keys", &self.key_histogram)?;
+        writeln!(f)?;
+        write_tracks(f, "Tracks without beatgrid", &self.without_beatgrid)?;
+        write_tracks(f, "Tracks without key", &self.without_key)?;
+        write_tracks(f, "Tracks without hotcues", &self.without_hotcues)?;
+        write_tracks(f, "Never played tracks", &self.never_played)?;
+        writeln!(f, "Most played tracks:")?;
+        for played in &self.most_played {
+            writeln!(f, "  {:>4}x {}", played.plays, played.track)?;
+        }
+        writeln!(
+            f,
+            "Playlists with deleted tracks: {}",
+            self.playlists_with_deleted_tracks.len()
+        )?;
+        for playlist in &self.playlists_with_deleted_tracks {
+            writeln!(
+                f,
+                "  {}: {} deleted tracks",
+                playlist.name,
+                playlist.track_ids.len()
+            )?;
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use anyhow::Result;
+    use pretty_assertions::assert_eq;
+
+    use super::*;
+    use crate::mixxx::{aggregator::read_library, fixture::sample_library};
+
+    fn ids(tracks: &[TrackSummary]) -> Vec<usize> {
+        tracks.iter().map(|track| track.id).collect()
+    }
+
+    #[tokio::test]
+    async fn sample_library_stats() -> Result<()> {
+        let mut fixture = sample_library().await?;
+        let mut library = read_library(&mut fixture.connection).await?;
+        library.tracks.get_mut(&2).unwrap().metadata.deleted = true;
+
+        let stats = library_stats(&library);
+
+        assert_eq!(stats.tracks, 2);
+        assert_eq!(stats.total_duration, 600.0);
+        assert_eq!(
+            stats.genres,
+            vec![
+                Count {
+                    name: "Techno".into(),
+                    tracks: 1
+                },
+                Count {
+                    name: "Unknown".into(),
+                    tracks: 1
+                },
+            ]
+        );
+        assert_eq!(
+            stats.bpm_histogram,
+            vec![Count {
+                name: "125-129".into(),
+                tracks: 1
+            }]
+        );
+        assert_eq!(
+            stats.key_histogram,
+            vec![Count {
+                name: "8A".into(),
+                tracks: 1
+            }]
+        );
+        assert_eq!(ids(&stats.without_beatgrid), vec![3]);
+        assert_eq!(ids(&stats.without_key), vec![3]);
+        assert_eq!(ids(&stats.without_hotcues), vec![3]);
+        assert_eq!(ids(&stats.never_played), vec![3]);
+        assert_eq!(stats.most_played[0].plays, 7);
+        assert_eq!(
+            stats.playlists_with_deleted_tracks,
+            vec![DeletedTracks {
+                name: "Warmup".into(),
+                track_ids: vec![2],
+            }]
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn format_durations() {
+        assert_eq!(format_duration(0.4), "0h 00m 00s");
+        assert_eq!(format_duration(43_389.0), "12h 03m 09s");
+    }
+}
@@ -0,0 +1,657 @@
+use std::{
+    collections::{BTreeSet, HashMap, HashSet},
+    fs::{File, copy, create_dir_all, metadata, read_to_string, remove_dir, remove_file, rename},
+    io::{ErrorKind, Write, copy as copy_stream},
+    path::{Path, PathBuf},
+    time::{Duration, SystemTime},
+};
+
+use anyhow::{Context, Result, bail};
+use log::{debug, warn};
+use path_slash::{PathBufExt, PathExt};
+use rayon::prelude::*;
+use serde_derive::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+
+use crate::{
+    export::{Profile, output::AtomicFile},
+    mixxx::library::{Library, Track},
+    validate::{INVALID_CHARACTERS, RESERVED_NAMES},
+};
+
+/// The file in the sync directory that lists all files that've been copied by the sync.
+/// Only these files are ever removed as orphans.
+pub const SYNC_MANIFEST_FILE_NAME: &str = ".mixxx_library_exporter_sync.json";
+/// FAT32 only stores modification times with a precision of two seconds.
+const MTIME_TOLERANCE: Duration = Duration::from_secs(2);
+
+/// Copy the music files of a profile into a directory, e.g. on a USB stick.
+#[derive(Deserialize, Debug, Clone, PartialEq)]
+#[serde(deny_unknown_fields)]
+pub struct SyncConfig {
+    /// The directory on this machine, into which the music files are copied.
+    /// It's the same directory as the `target_library_root` on the target machine.
+    pub directory: PathBuf,
+    /// Only the tracks of these playlists and crates are copied and exported.
+    /// All tracks and playlists are exported, if this isn't set.
+    pub playlists: Option<Vec<String>>,
+    /// The path of each file inside the directory, e.g. `{artist}/{album}/{title}`.
+    /// Available fields are `artist`, `album`, `title`, `genre`, `year` and `tracknumber`.
+    /// The file's extension is appended automatically.
+    /// The folder structure below the `source_library_root` is mirrored, if this isn't set.
+    pub path_template: Option<String>,
+    /// How files that already exist in the directory are recognized as unchanged.
+    #[serde(default)]
+    pub compare: SyncCompare,
+    /// Remove files from the directory that aren't part of the export anymore.
+    /// Only files that've been copied by a previous sync are removed, anything else in the
+    /// directory is left alone.
+    #[serde(default)]
+    pub remove_orphans: bool,
+}
+
+#[derive(Deserialize, Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum SyncCompare {
+    /// Files with the same size and modification time are unchanged.
+    #[default]
+    SizeMtime,
+    /// Files with the same content are unchanged. This reads every file on both sides.
+    Hash,
+}
+
+/// A summary of a single sync.
+#[derive(Debug, Default)]
+pub struct SyncReport {
+    /// The amount of copied files.
+    pub copied: usize,
+    /// The amount of files that already existed in the directory.
+    pub unchanged: usize,
+    /// Files of previous syncs that've been removed from the directory.
+    pub removed: Vec<PathBuf>,
+    /// The ids of tracks that couldn't be copied. They're left out of the export.
+    pub failed: Vec<usize>,
+}
+
+/// All files inside the sync directory that've been copied by the sync, as relative paths.
+#[derive(Serialize, Deserialize, Debug, Default, Clone, PartialEq, Eq)]
+struct SyncManifest {
+    files: BTreeSet<String>,
+}
+
+impl SyncManifest {
+    fn read(directory: &Path) -> Result<Self> {
+        let path = directory.join(SYNC_MANIFEST_FILE_NAME);
+        if !path.exists() {
+            return Ok(SyncManifest::default());
+        }
+
+        let content =
+            read_to_string(&path).context(format!("Failed to read sync manifest {path:?}"))?;
+        serde_json::from_str(&content).context(format!("Invalid sync manifest at {path:?}"))
+    }
+
+    fn write(&self, directory: &Path) -> Result<()> {
+        let mut file = AtomicFile::create(&directory.join(SYNC_MANIFEST_FILE_NAME))?;
+        serde_json::to_writer_pretty(&mut file, self)?;
+        file.write_all(b"\n")?;
+
+        file.commit(0)
+    }
+
+    /// Record a copied file. Previous entries that only differ in case are the same file on
+    /// USB sticks, so they're replaced.
+    fn insert(&mut self, relative: &Path) {
+        let key = path_key(relative);
+        self.files.retain(|file| path_key(Path::new(file)) != key);
+        self.files.insert(relative.to_slash_lossy().into_owned());
+    }
+}
+
+/// The library and profile that point to the copied files, ready to be exported.
+#[derive(Debug)]
+pub struct SyncedLibrary {
+    pub library: Library,
+    pub profile: Profile,
+    pub report: SyncReport,
+}
+
+/// Copy all tracks of the selected playlists into the sync directory.
+///
+/// The returned library only contains the copied tracks and their locations point into the sync
+/// directory. The returned profile maps the sync directory to the `target_library_root`.
+pub fn sync_files(
+    library: &Library,
+    profile: &Profile,
+    sync: &SyncConfig,
+) -> Result<SyncedLibrary> {
+    create_dir_all(&sync.directory).context(format!(
+        "Failed to create sync directory {:?}",
+        sync.directory
+    ))?;
+    let directory = sync.directory.canonicalize().context(format!(
+        "Failed to resolve sync directory {:?}",
+        sync.directory
+    ))?;
+
+    // Files are copied into and removed from the sync directory, which must never touch the
+    // original files.
+    let source_root = PathBuf::from_slash(&profile.source_library_root);
+    let source_root = source_root.canonicalize().unwrap_or(source_root);
+    if directory.starts_with(&source_root) || source_root.starts_with(&directory) {
+        bail!(
+            "The sync directory {directory:?} overlaps with the source_library_root {source_root:?}"
+        );
+    }
+
+    let mut library = select_playlists(library, sync)?;
+    let destinations = destinations(&library, profile, sync)?;
+    let mut manifest = SyncManifest::read(&directory)?;
+
+    let results: Vec<_> = library
+        .tracks
+        .par_iter()
+        .map(|(id, track)| {
+            let destination = directory.join(&destinations[id]);
+            let result = sync_file(
+                Path::new(&track.location.location),
+                &destination,
+                sync.compare,
+            );
+            if let Err(err) = &result {
+                warn!("Failed to copy track {id} to {destination:?}: {err:#}");
+            }
+            (*id, result)
+        })
+        .collect();
+
+    let mut report = SyncReport::default();
+    for (id, result) in results {
+        match result {
+            Ok(true) => {
+                report.copied += 1;
+                manifest.insert(&destinations[&id]);
+            }
+            Ok(false) => report.unchanged += 1,
+            Err(_) => report.failed.push(id),
+        }
+    }
+
+    if sync.remove_orphans {
+        let expected: HashSet<_> = destinations.values().map(|path| path_key(path)).collect();
+        report.removed = remove_orphans(&directory, &expected, &mut manifest)?;
+    }
+    manifest.write(&directory)?;
+
+    // Point all tracks to their copies and drop those that couldn't be copied.
+    for id in &report.failed {
+        library.tracks.remove(id);
+    }
+    let root = directory.to_slash_lossy().trim_end_matches('/').to_string();
+    for (id, track) in &mut library.tracks {
+        let location = format!("{root}/{}", destinations[id].to_slash_lossy());
+        let path = PathBuf::from_slash(&location);
+        track.location.directory = path
+            .parent()
+            .map(|parent| parent.to_slash_lossy().into_owned())
+            .unwrap_or_default();
+        track.location.filename = path
+            .file_name()
+            .map(|name| name.to_string_lossy().into_owned())
+            .unwrap_or_default();
+        track.location.location = location;
+    }
+    retain_tracks(&mut library);
+
+    let mut profile = profile.clone();
+    profile.source_library_root = root;
+
+    Ok(SyncedLibrary {
+        library,
+        profile,
+        report,
+    })
+}
+
+/// Only keep the selected playlists and crates and the tracks they contain.
+/// Tracks that've been deleted in Mixxx are never copied.
+fn select_playlists(library: &Library, sync: &SyncConfig) -> Result<Library> {
+    let mut library = library.clone();
+    library.tracks.retain(|_, track| !track.metadata.deleted);
+
+    if let Some(names) = &sync.playlists {
+        for name in names {
+            let exists = library
+                .playlists
+                .iter()
+                .any(|playlist| &playlist.name == name)
+                || library
+                    .crates
+                    .iter()
+                    .any(|mixxx_crate| &mixxx_crate.name == name);
+            if !exists {
+                bail!("There's no playlist or crate with the name '{name}' to sync");
+            }
+        }
+
+        library
+            .playlists
+            .retain(|playlist| names.contains(&playlist.name));
+        library
+            .crates
+            .retain(|mixxx_crate| names.contains(&mixxx_crate.name));
+        let selected: HashSet<_> = library
+            .playlists
+            .iter()
+            .flat_map(|playlist| &playlist.track_ids)
+            .chain(
+                library
+                    .crates
+                    .iter()
+                    .flat_map(|mixxx_crate| &mixxx_crate.track_ids),
+            )
+            .copied()
+            .collect();
+        library.tracks.retain(|id, _| selected.contains(id));
+    }
+    retain_tracks(&mut library);
+
+    Ok(library)
+}
+
+/// Remove all tracks from playlists and crates that aren't part of the library.
+fn retain_tracks(library: &mut Library) {
+    let tracks = &library.tracks;
+    for playlist in &mut library.playlists {
+        playlist.track_ids.retain(|id| tracks.contains_key(id));
+    }
+    for mixxx_crate in &mut library.crates {
+        mixxx_crate.track_ids.retain(|id| tracks.contains_key(id));
+    }
+}
+
+/// The path of each track relative to the sync directory.
+///
+/// File systems of USB sticks are case insensitive, so paths that only differ in case get a
+/// numbered suffix.
+fn destinations(
+    library: &Library,
+    profile: &Profile,
+    sync: &SyncConfig,
+) -> Result<HashMap<usize, PathBuf>> {
+    let source_root = PathBuf::from_slash(&profile.source_library_root);
+
+    let mut destinations = HashMap::new();
+    let mut taken = HashSet::new();
+    for (id, track) in &library.tracks {
+        let source = PathBuf::from_slash(&track.location.location);
+        let path = match &sync.path_template {
+            Some(template) => render_template(template, track, &source),
+            None => source
+                .strip_prefix(&source_root)
+                .context(format!(
+                    "Track {id} at {source:?} is not in source_library_root {source_root:?}"
+                ))?
+                .to_path_buf(),
+        };
+
+        let mut unique = path.clone();
+        let mut counter = 1;
+        while !taken.insert(path_key(&unique)) {
+            counter += 1;
+            unique = numbered(&path, counter);
+        }
+        destinations.insert(*id, unique);
+    }
+
+    Ok(destinations)
+}
+
+/// USB sticks are case insensitive, so paths are compared by their lowercase form.
+fn path_key(path: &Path) -> String {
+    path.to_slash_lossy().to_lowercase()
+}
+
+/// Fill the track's fields into the template.
+/// Each directory and file name is cleaned of characters that aren't allowed on USB sticks.
+fn render_template(template: &str, track: &Track, source: &Path) -> PathBuf {
+    let fields = [
+        ("{artist}", track.artist.as_str()),
+        ("{album}", track.album.as_deref().unwrap_or_default()),
+        ("{title}", track.title.as_str()),
+        ("{genre}", track.genre.as_deref().unwrap_or_default()),
+        ("{year}", track.year.as_str()),
+        (
+            "{tracknumber}",
+            track.tracknumber.as_deref().unwrap_or_default(),
+        ),
+    ];
+
+    let mut path = PathBuf::new();
+    for component in template
+        .split('/')
+        .filter(|component| !component.is_empty())
+    {
+        let mut name = component.to_string();
+        for (placeholder, value) in fields {
+            name = name.replace(placeholder, &sanitize(value));
+        }
+        path.push(clean_name(&name));
+    }
+    if let Some(extension) = source.extension() {
+        path.set_extension(extension);
+    }
+
+    path
+}
+
+/// Replace characters that would split the name or aren't allowed in file names.
+fn sanitize(value: &str) -> String {
+    value
+        .chars()
+        .map(|c| {
+            if INVALID_CHARACTERS.contains(&c) || c.is_control() {
+                '_'
+            } else {
+                c
+            }
+        })
+        .collect()
+}
+
+/// Windows and FAT32 don't allow names that end with dots or spaces or that're reserved for
+/// devices. Empty names are replaced, as they would vanish from the path.
+fn clean_name(name: &str) -> String {
+    let name = name.trim().trim_end_matches('.').trim_end();
+    if name.is_empty() {
+        return "Unknown".to_string();
+    }
+    if RESERVED_NAMES.contains(&name.to_uppercase().as_str()) {
+        return format!("{name}_");
+    }
+
+    name.to_string()
+}
+
+/// Add a counter to the file name, e.g. `Title (2).mp3`.
+fn numbered(path: &Path, counter: usize) -> PathBuf {
+    let stem = path.file_stem().unwrap_or_default().to_string_lossy();
+    let name = match path.extension() {
+        Some(extension) => format!("{stem} ({counter}).{}", extension.to_string_lossy()),
+        None => format!("{stem} ({counter})"),
+    };
+
+    path.with_file_name(name)
+}
+
+/// Copy a single file, unless an unchanged copy already exists.
+/// Returns whether the file has been copied.
+fn sync_file(source: &Path, destination: &Path, compare: SyncCompare) -> Result<bool> {
+    let source_metadata = metadata(source).context(format!("Failed to read {source:?}"))?;
+    if let Ok(destination_metadata) = metadata(destination)
+        && destination_metadata.len() == source_metadata.len()
+    {
+        let unchanged = match compare {
+            SyncCompare::SizeMtime => same_time(
+                source_metadata.modified()?,
+                destination_metadata.modified()?,
+            ),
+            SyncCompare::Hash => hash_file(source)? == hash_file(destination)?,
+        };
+        if unchanged {
+            debug!("{destination:?} is unchanged");
+            return Ok(false);
+        }
+    }
+
+    let directory = destination.parent().context(format!(
+        "Sync file doesn't have a directory: {destination:?}"
+    ))?;
+    create_dir_all(directory).context(format!("Failed to create directory {directory:?}"))?;
+
+    // Copy next to the destination first, so an interrupted sync never leaves half a file.
+    let file_name = destination
+        .file_name()
+        .unwrap_or_default()
+        .to_string_lossy();
+    let temporary = directory.join(format!(".{file_name}.tmp"));
+    let copied = copy(source, &temporary)
+        .context(format!("Failed to copy {source:?}"))
+        .and_then(|_| {
+            // Keep the modification time of the source, so the copy is recognized by the next
+            // sync.
+            File::options()
+                .write(true)
+                .open(&temporary)
+                .and_then(|file| file.set_modified(source_metadata.modified()?))
+                .context(format!("Failed to set modification time of {temporary:?}"))
+        });
+    if let Err(err) = copied {
+        let _ = remove_file(&temporary);
+        return Err(err);
+    }
+    rename(&temporary, destination).context(format!("Failed to move copy to {destination:?}"))?;
+
+    Ok(true)
+}
+
+fn same_time(left: SystemTime, right: SystemTime) -> bool {
+    let difference = left
+        .duration_since(right)
+        .or_else(|_| right.duration_since(left))
+        .unwrap_or_default();
+
+    difference <= MTIME_TOLERANCE
+}
+
+fn hash_file(path: &Path) -> Result<String> {
+    let mut file = File::open(path).context(format!("Failed to open {path:?}"))?;
+    let mut hasher = Sha256::new();
+    copy_stream(&mut file, &mut hasher).context(format!("Failed to read {path:?}"))?;
+
+    Ok(format!("{:x}", hasher.finalize()))
+}
+
+/// Remove all files of the manifest that aren't expected anymore and any directories that
+/// are empty afterwards. Returns the removed files.
+fn remove_orphans(
+    directory: &Path,
+    expected: &HashSet<String>,
+    manifest: &mut SyncManifest,
+) -> Result<Vec<PathBuf>> {
+    let orphans: Vec<_> = manifest
+        .files
+        .iter()
+        .filter(|file| !expected.contains(&path_key(Path::new(file))))
+        .cloned()
+        .collect();
+
+    let mut removed = Vec::new();
+    for file in orphans {
+        let path = directory.join(PathBuf::from_slash(&file));
+        match remove_file(&path) {
+            Ok(()) => removed.push(path.clone()),
+            // The file has already been removed by someone else.
+            Err(err) if err.kind() == ErrorKind::NotFound => (),
+            Err(err) => return Err(err).context(format!("Failed to remove orphan {path:?}")),
+        }
+        manifest.files.remove(&file);
+
+        // Only succeeds for empty directories, which is exactly what we want.
+        let mut parent = path.parent();
+        while let Some(current) = parent
+            && current != directory
+            && remove_dir(current).is_ok()
+        {
+            debug!("Removed empty directory {current:?}");
+            parent = current.parent();
+        }
+    }
+
+    Ok(removed)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs::{read, write};
+
+    use anyhow::Result;
+    use pretty_assertions::assert_eq;
+    use rstest::rstest;
+    use tempfile::tempdir;
+
+    use super::*;
+    use crate::mixxx::{
+        aggregator::read_library,
+        fixture::{LibraryBuilder, Schema, TrackFixture},
+    };
+
+    #[rstest]
+    #[case("Back in Black", "Back in Black")]
+    #[case(" Dots... ", "Dots")]
+    #[case("", "Unknown")]
+    #[case("con", "con_")]
+    fn clean_names(#[case] name: &str, #[case] expected: &str) {
+        assert_eq!(clean_name(name), expected);
+    }
+
+    async fn music_library(music: &Path, artist: &str) -> Result<Library> {
+        let root = music.to_slash_lossy().into_owned();
+        for name in ["one.mp3", "two.flac", "three.mp3"] {
+            write(music.join(name), name)?;
+        }
+        let mut fixture = LibraryBuilder::new()
+            .track(TrackFixture {
+                path: format!("{root}/one.mp3"),
+                ..TrackFixture::new(1, "AC/DC", "Back in Black")
+            })
+            .track(TrackFixture {
+                path: format!("{root}/two.flac"),
+                ..TrackFixture::new(2, artist, "One More Time")
+            })
+            .track(TrackFixture {
+                path: format!("{root}/three.mp3"),
+                ..TrackFixture::new(3, artist, "Da Funk")
+            })
+            .playlist("USB", &[2, 1])
+            .playlist("Home", &[3])
+            .build(Schema::Latest)
+            .await?;
+
+        read_library(&mut fixture.connection).await
+    }
+
+    fn sync_config(directory: &Path, playlist: &str) -> SyncConfig {
+        SyncConfig {
+            directory: directory.to_path_buf(),
+            playlists: Some(vec![playlist.into()]),
+            path_template: Some("{artist}/{title}".into()),
+            compare: SyncCompare::SizeMtime,
+            remove_orphans: true,
+        }
+    }
+
+    #[tokio::test]
+    async fn sync_selected_playlists() -> Result<()> {
+        let music = tempdir()?;
+        let library = music_library(music.path(), "Daft Punk").await?;
+        let usb = tempdir()?;
+        let usb_path = usb.path().canonicalize()?;
+        // Music that hasn't been copied by the sync is never removed.
+        write(usb.path().join("old.mp3"), "old")?;
+        let profile = Profile {
+            source_library_root: music.path().to_slash_lossy().into_owned(),
+            target_library_root: PathBuf::from("C:/Music"),
+            ..profile()
+        };
+        let sync = sync_config(usb.path(), "USB");
+
+        let synced = sync_files(&library, &profile, &sync)?;
+
+        assert_eq!(synced.report.copied, 2);
+        assert!(synced.report.removed.is_empty());
+        assert_eq!(
+            read(usb.path().join("AC_DC/Back in Black.mp3"))?,
+            b"one.mp3"
+        );
+        assert!(usb.path().join("Daft Punk/One More Time.flac").exists());
+
+        let playlists: Vec<_> = synced
+            .library
+            .playlists
+            .iter()
+            .map(|playlist| (playlist.name.as_str(), playlist.track_ids.clone()))
+            .collect();
+        assert_eq!(playlists, vec![("USB", vec![2, 1])]);
+        assert_eq!(synced.library.tracks.len(), 2);
+        let location = &synced.library.tracks[&1].location.location;
+        assert_eq!(
+            synced.profile.map_location(location)?,
+            PathBuf::from("C:/Music/AC_DC/Back in Black.mp3")
+        );
+
+        // Nothing has changed since the first sync.
+        let synced = sync_files(&library, &profile, &sync)?;
+        assert_eq!((synced.report.copied, synced.report.unchanged), (0, 2));
+
+        // Only the copies of the previous syncs are removed.
+        let synced = sync_files(&library, &profile, &sync_config(usb.path(), "Home"))?;
+        let mut removed = synced.report.removed;
+        removed.sort();
+        assert_eq!(
+            removed,
+            vec![
+                usb_path.join("AC_DC/Back in Black.mp3"),
+                usb_path.join("Daft Punk/One More Time.flac")
+            ]
+        );
+        assert!(usb.path().join("old.mp3").exists());
+        assert!(!usb.path().join("AC_DC").exists());
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn keep_files_with_changed_case() -> Result<()> {
+        let usb = tempdir()?;
+        let sync = sync_config(usb.path(), "USB");
+        let music = tempdir()?;
+        let profile = Profile {
+            source_library_root: music.path().to_slash_lossy().into_owned(),
+            ..profile()
+        };
+
+        let library = music_library(music.path(), "daft punk").await?;
+        sync_files(&library, &profile, &sync)?;
+        // USB sticks are case insensitive, so the renamed artist still points to the same file.
+        let library = music_library(music.path(), "Daft Punk").await?;
+        let synced = sync_files(&library, &profile, &sync)?;
+
+        assert!(synced.report.removed.is_empty());
+        assert!(usb.path().join("daft punk/One More Time.flac").exists());
+        let manifest = SyncManifest::read(usb.path())?;
+        assert_eq!(
+            manifest.files.into_iter().collect::<Vec<_>>(),
+            vec!["AC_DC/Back in Black.mp3", "Daft Punk/One More Time.flac"]
+        );
+
+        Ok(())
+    }
+
+    fn profile() -> Profile {
+        Profile {
+            name: "usb".into(),
+            format: "rekordbox".into(),
+            target_directory: PathBuf::new(),
+            source_library_root: "/music".into(),
+            target_library_root: PathBuf::from("C:/Music"),
+            create_all_playlist: false,
+            file_name: None,
+            backups: 0,
+            track_color_palette: None,
+            target_filesystem: None,
+            sync: None,
+            options: serde_json::Value::Null,
+        }
+    }
+}
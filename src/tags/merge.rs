@@ -0,0 +1,239 @@
+use std::path::Path;
+
+use log::{debug, warn};
+use rayon::prelude::*;
+use serde_derive::{Deserialize, Serialize};
+
+use super::reader::{FileTags, read_file_tags};
+use crate::mixxx::library::{Library, Track};
+
+/// Decides which value is used, if Mixxx and the audio file's tags disagree.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum TagPrecedence {
+    /// Fields that are stored by Mixxx are never touched.
+    /// Only fields that're unknown to Mixxx, such as the label, are taken from the file.
+    #[default]
+    Mixxx,
+    /// Values from the file's tags replace the values from Mixxx.
+    File,
+    /// Values from the file's tags are only used, if the value is empty in Mixxx.
+    FillEmpty,
+}
+
+/// A field for which Mixxx and the audio file have different values.
+#[derive(Debug, Clone, PartialEq)]
+pub struct TagConflict {
+    pub track_id: usize,
+    pub field: &'static str,
+    pub mixxx: String,
+    pub file: String,
+}
+
+/// The result of merging file tags into the library.
+#[derive(Debug, Default)]
+pub struct TagMergeReport {
+    /// The amount of files whose tags have been read.
+    pub read: usize,
+    /// The ids of tracks whose files couldn't be read.
+    pub failed: Vec<usize>,
+    /// All fields whose values differ between Mixxx and the file.
+    pub conflicts: Vec<TagConflict>,
+}
+
+/// Read the tags of each track's audio file and merge them into the library.
+///
+/// Files are read in parallel. Files that cannot be read are skipped with a warning.
+pub fn merge_file_tags(library: &mut Library, precedence: TagPrecedence) -> TagMergeReport {
+    // The results are collected in the order of the tracks, which keeps the report deterministic.
+    let results: Vec<Option<Vec<TagConflict>>> = library
+        .tracks
+        .par_iter_mut()
+        .map(|(_, track)| {
+            let path = Path::new(&track.location.location);
+            let tags = match read_file_tags(path) {
+                Ok(tags) => tags,
+                Err(err) => {
+                    warn!("Couldn't read tags of track {}: {err:#}", track.id);
+                    return None;
+                }
+            };
+            debug!("Read tags for track {}: {tags:?}", track.id);
+
+            let mut conflicts = Vec::new();
+            merge_track(track, tags, precedence, &mut conflicts);
+            Some(conflicts)
+        })
+        .collect();
+
+    let mut report = TagMergeReport::default();
+    for (id, result) in library.tracks.keys().zip(results) {
+        match result {
+            Some(conflicts) => {
+                report.read += 1;
+                report.conflicts.extend(conflicts);
+            }
+            None => report.failed.push(*id),
+        }
+    }
+
+    report
+}
+
+/// Merge the tags of a single file into the respective track.
+pub fn merge_track(
+    track: &mut Track,
+    tags: FileTags,
+    precedence: TagPrecedence,
+    conflicts: &mut Vec<TagConflict>,
+) {
+    let mut merger = FieldMerger {
+        track_id: track.id,
+        precedence,
+        conflicts,
+    };
+
+    // Fields that're stored by Mixxx.
+    merger.merge_string("artist", &mut track.artist, tags.artist);
+    merger.merge_string("title", &mut track.title, tags.title);
+    merger.merge_string("composer", &mut track.composer, tags.composer);
+    merger.merge_string("year", &mut track.year, tags.year);
+    merger.merge("album", &mut track.album, tags.album);
+    merger.merge("genre", &mut track.genre, tags.genre);
+    merger.merge("grouping", &mut track.grouping, tags.grouping);
+    merger.merge("comment", &mut track.comment, tags.comment);
+    merger.merge("tracknumber", &mut track.tracknumber, tags.tracknumber);
+    merger.merge("tracktotal", &mut track.tracktotal, tags.tracktotal);
+
+    // Fields that're unknown to Mixxx are always filled from the file.
+    fill_empty(&mut track.disc_number, tags.disc_number);
+    fill_empty(&mut track.label, tags.label);
+    fill_empty(&mut track.remixer, tags.remixer);
+}
+
+/// Small helper that merges single fields and remembers all conflicts.
+struct FieldMerger<'a> {
+    track_id: usize,
+    precedence: TagPrecedence,
+    conflicts: &'a mut Vec<TagConflict>,
+}
+
+impl FieldMerger<'_> {
+    fn merge(&mut self, field: &'static str, value: &mut Option<String>, file: Option<String>) {
+        let Some(file) = file else {
+            return;
+        };
+
+        let Some(mixxx) = value.as_ref().filter(|value| !value.trim().is_empty()) else {
+            // Mixxx doesn't have a value, use the file's one if we're allowed to.
+            if self.precedence != TagPrecedence::Mixxx {
+                *value = Some(file);
+            }
+            return;
+        };
+
+        if same_value(field, mixxx, &file) {
+            return;
+        }
+
+        self.conflicts.push(TagConflict {
+            track_id: self.track_id,
+            field,
+            mixxx: mixxx.clone(),
+            file: file.clone(),
+        });
+
+        if self.precedence == TagPrecedence::File {
+            *value = Some(file);
+        }
+    }
+
+    /// Same as [Self::merge], but for fields in which Mixxx uses an empty string for unknown
+    /// values.
+    fn merge_string(&mut self, field: &'static str, value: &mut String, file: Option<String>) {
+        let mut wrapped = Some(std::mem::take(value));
+        self.merge(field, &mut wrapped, file);
+        *value = wrapped.unwrap_or_default();
+    }
+}
+
+fn fill_empty(value: &mut Option<String>, file: Option<String>) {
+    if value.is_none() {
+        *value = file;
+    }
+}
+
+/// Check whether two values mean the same thing.
+/// This prevents lots of bogus conflicts, e.g. for `2021` vs `2021-05-01` or `7` vs `07`.
+fn same_value(field: &str, mixxx: &str, file: &str) -> bool {
+    let (mixxx, file) = (mixxx.trim(), file.trim());
+    match field {
+        "tracknumber" | "tracktotal" => match (mixxx.parse::<u32>(), file.parse::<u32>()) {
+            (Ok(mixxx), Ok(file)) => mixxx == file,
+            _ => mixxx == file,
+        },
+        "year" => mixxx.get(..4) == file.get(..4),
+        _ => mixxx == file,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use pretty_assertions::assert_eq;
+    use rstest::rstest;
+
+    use super::*;
+
+    fn conflicting_tags() -> FileTags {
+        FileTags {
+            album: Some("File Album".into()),
+            year: Some("2021-05-01".into()),
+            composer: Some("File Composer".into()),
+            tracknumber: Some("03".into()),
+            label: Some("File Label".into()),
+            ..Default::default()
+        }
+    }
+
+    #[rstest]
+    #[case(TagPrecedence::Mixxx, "Mixxx Album", "")]
+    #[case(TagPrecedence::FillEmpty, "Mixxx Album", "File Composer")]
+    #[case(TagPrecedence::File, "File Album", "File Composer")]
+    fn merge_precedence(
+        #[case] precedence: TagPrecedence,
+        #[case] album: &str,
+        #[case] composer: &str,
+    ) {
+        // The values as they come from Mixxx.
+        let mut album_value = Some("Mixxx Album".to_string());
+        let mut year = "2021".to_string();
+        let mut composer_value = String::new();
+        let mut tracknumber = Some("3".to_string());
+
+        let mut conflicts = Vec::new();
+        let mut merger = FieldMerger {
+            track_id: 1,
+            precedence,
+            conflicts: &mut conflicts,
+        };
+        let tags = conflicting_tags();
+        merger.merge("album", &mut album_value, tags.album);
+        merger.merge_string("year", &mut year, tags.year);
+        merger.merge_string("composer", &mut composer_value, tags.composer);
+        merger.merge("tracknumber", &mut tracknumber, tags.tracknumber);
+
+        assert_eq!(album_value.as_deref(), Some(album));
+        assert_eq!(composer_value, composer);
+        // Equivalent values are neither replaced, nor reported as conflict.
+        assert_eq!(tracknumber.as_deref(), Some("3"));
+        assert_eq!(
+            conflicts,
+            vec![TagConflict {
+                track_id: 1,
+                field: "album",
+                mixxx: "Mixxx Album".into(),
+                file: "File Album".into(),
+            }]
+        );
+    }
+}
@@ -0,0 +1,8 @@
+/// Merging of file tags into the Mixxx library.
+pub mod merge;
+/// Reading of tags from audio files.
+pub mod reader;
+/// Encoding of Serato's cue point tags.
+pub mod serato;
+/// Writing of cues, beatgrid info and colours into the audio files' tags.
+pub mod writer;
@@ -0,0 +1,227 @@
+use std::{fs::File, io::ErrorKind, path::Path};
+
+use anyhow::{Context, Result};
+use sha2::{Digest, Sha256};
+use symphonia::core::{
+    errors::Error,
+    formats::FormatOptions,
+    io::MediaSourceStream,
+    meta::{MetadataOptions, MetadataRevision, StandardTagKey, StandardVisualKey, Visual},
+    probe::{Hint, ProbeResult},
+};
+
+/// Metadata that's read directly from the tags of an audio file.
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct FileTags {
+    pub artist: Option<String>,
+    pub title: Option<String>,
+    pub album: Option<String>,
+    pub year: Option<String>,
+    pub genre: Option<String>,
+    pub composer: Option<String>,
+    pub grouping: Option<String>,
+    pub comment: Option<String>,
+    pub tracknumber: Option<String>,
+    pub tracktotal: Option<String>,
+    pub disc_number: Option<String>,
+    pub label: Option<String>,
+    pub remixer: Option<String>,
+}
+
+impl FileTags {
+    /// Take all tags we're interested in from a metadata revision.
+    /// Values that have already been set by a previous revision aren't overwritten.
+    fn apply_revision(&mut self, revision: &MetadataRevision) {
+        for tag in revision.tags() {
+            let value = tag.value.to_string().trim().to_string();
+            if value.is_empty() {
+                continue;
+            }
+
+            let field = match tag.std_key {
+                Some(StandardTagKey::Artist) => &mut self.artist,
+                Some(StandardTagKey::TrackTitle) => &mut self.title,
+                Some(StandardTagKey::Album) => &mut self.album,
+                Some(StandardTagKey::Date) => &mut self.year,
+                Some(StandardTagKey::Genre) => &mut self.genre,
+                Some(StandardTagKey::Composer) => &mut self.composer,
+                Some(StandardTagKey::ContentGroup) => &mut self.grouping,
+                Some(StandardTagKey::Comment) => &mut self.comment,
+                Some(StandardTagKey::TrackNumber) => &mut self.tracknumber,
+                Some(StandardTagKey::TrackTotal) => &mut self.tracktotal,
+                Some(StandardTagKey::DiscNumber) => &mut self.disc_number,
+                Some(StandardTagKey::Label) => &mut self.label,
+                Some(StandardTagKey::Remixer) => &mut self.remixer,
+                // Vorbis comments don't have a standardized grouping field, but `GROUPING` is
+                // what most taggers use.
+                None if tag.key.eq_ignore_ascii_case("grouping") => &mut self.grouping,
+                _ => continue,
+            };
+
+            if field.is_none() {
+                *field = Some(value);
+            }
+        }
+    }
+}
+
+/// Open and probe the audio file at the given path.
+///
+/// This handles all tag formats that're supported by symphonia, i.e. ID3v2, Vorbis comments,
+/// MP4 atoms and FLAC metadata blocks.
+fn probe(path: &Path) -> Result<ProbeResult> {
+    let file = File::open(path).context(format!("Failed to open audio file {path:?}"))?;
+    let stream = MediaSourceStream::new(Box::new(file), Default::default());
+
+    // Help the prober by passing the file extension.
+    let mut hint = Hint::new();
+    if let Some(extension) = path.extension().and_then(|extension| extension.to_str()) {
+        hint.with_extension(extension);
+    }
+
+    symphonia::default::get_probe()
+        .format(
+            &hint,
+            stream,
+            &FormatOptions::default(),
+            &MetadataOptions::default(),
+        )
+        .context(format!("Failed to read audio file {path:?}"))
+}
+
+/// Read the tags of the audio file at the given path.
+pub fn read_file_tags(path: &Path) -> Result<FileTags> {
+    let mut probed = probe(path)?;
+
+    let mut tags = FileTags::default();
+
+    // Tags that're part of the container format, e.g. Vorbis comments or MP4 atoms.
+    if let Some(revision) = probed.format.metadata().current() {
+        tags.apply_revision(revision);
+    }
+
+    // Tags that're prepended to the actual audio stream, e.g. ID3v2 tags of MP3 files.
+    if let Some(metadata) = probed.metadata.get()
+        && let Some(revision) = metadata.current()
+    {
+        tags.apply_revision(revision);
+    }
+
+    // Track numbers are often saved as `7/12`.
+    // Split them, so they can be compared to Mixxx's `tracknumber` and `tracktotal` columns.
+    if let Some((number, total)) = tags
+        .tracknumber
+        .as_deref()
+        .and_then(|tracknumber| tracknumber.split_once('/'))
+    {
+        let (number, total) = (number.trim().to_string(), total.trim().to_string());
+        if tags.tracktotal.is_none() && !total.is_empty() {
+            tags.tracktotal = Some(total);
+        }
+        tags.tracknumber = Some(number);
+    }
+
+    Ok(tags)
+}
+
+/// Read the cover art that's embedded in the audio file at the given path.
+///
+/// The front cover is preferred, if there are multiple images.
+/// Returns `None`, if the file doesn't contain any images.
+pub fn read_embedded_cover(path: &Path) -> Result<Option<Vec<u8>>> {
+    let mut probed = probe(path)?;
+
+    let mut visuals: Vec<Visual> = Vec::new();
+    if let Some(revision) = probed.format.metadata().current() {
+        visuals.extend(revision.visuals().iter().cloned());
+    }
+    if let Some(metadata) = probed.metadata.get()
+        && let Some(revision) = metadata.current()
+    {
+        visuals.extend(revision.visuals().iter().cloned());
+    }
+
+    let cover = visuals
+        .iter()
+        .find(|visual| visual.usage == Some(StandardVisualKey::FrontCover))
+        .or_else(|| visuals.first())
+        .map(|visual| visual.data.to_vec());
+
+    Ok(cover)
+}
+
+/// Hash the audio stream of the file at the given path, without any of its tags.
+///
+/// Copies of a file that only differ in their tags have the same hash.
+/// The audio isn't decoded, so the same song in different encodings has different hashes.
+pub fn hash_audio_content(path: &Path) -> Result<String> {
+    let mut probed = probe(path)?;
+    let track_id = probed
+        .format
+        .default_track()
+        .context(format!("Audio file {path:?} doesn't contain any audio"))?
+        .id;
+
+    let mut hasher = Sha256::new();
+    loop {
+        match probed.format.next_packet() {
+            Ok(packet) if packet.track_id() == track_id => hasher.update(&packet.data),
+            Ok(_) => {}
+            // Symphonia signals the end of the stream with an EOF error.
+            Err(Error::IoError(err)) if err.kind() == ErrorKind::UnexpectedEof => break,
+            Err(err) => return Err(err).context(format!("Failed to read audio file {path:?}")),
+        }
+    }
+
+    Ok(format!("{:x}", hasher.finalize()))
+}
+
+#[cfg(test)]
+mod tests {
+    use id3::{Tag, TagLike, Version};
+    use pretty_assertions::assert_eq;
+    use rstest::rstest;
+    use tempfile::TempDir;
+
+    use super::*;
+    use crate::mixxx::fixture::audio_fixture as fixture;
+
+    #[rstest]
+    #[case("tagged.mp3")]
+    #[case("tagged.flac")]
+    fn read_fixture_tags(#[case] name: &str) {
+        let tags = read_file_tags(&fixture(name)).unwrap();
+
+        assert_eq!(tags.artist.as_deref(), Some("Tagged Artist"));
+        assert_eq!(tags.title.as_deref(), Some("Tagged Title"));
+        assert_eq!(tags.album.as_deref(), Some("Tagged Album"));
+        assert_eq!(tags.year.as_deref(), Some("2021"));
+        assert_eq!(tags.composer.as_deref(), Some("Tagged Composer"));
+        assert_eq!(tags.grouping.as_deref(), Some("Tagged Grouping"));
+        assert_eq!(tags.tracknumber.as_deref(), Some("7"));
+        assert_eq!(tags.label.as_deref(), Some("Tagged Label"));
+        assert_eq!(tags.remixer.as_deref(), Some("Tagged Remixer"));
+        assert!(tags.disc_number.as_deref().unwrap().starts_with('2'));
+    }
+
+    #[test]
+    fn ignore_tags_in_content_hash() -> Result<()> {
+        let dir = TempDir::new()?;
+        let retagged = dir.path().join("retagged.mp3");
+        std::fs::copy(fixture("tagged.mp3"), &retagged)?;
+        let mut tag = Tag::read_from_path(&retagged)?;
+        tag.set_title("A completely different title");
+        tag.write_to_path(&retagged, Version::Id3v24)?;
+
+        assert_eq!(
+            hash_audio_content(&fixture("tagged.mp3"))?,
+            hash_audio_content(&retagged)?
+        );
+        assert_ne!(
+            hash_audio_content(&fixture("tagged.mp3"))?,
+            hash_audio_content(&fixture("tagged.flac"))?
+        );
+
+        Ok(())
+    }
+}
@@ -0,0 +1,219 @@
+use base64::{Engine, engine::general_purpose::STANDARD_NO_PAD};
+
+use crate::color::RgbColor;
+
+/// The description of the ID3 `GEOB` frame that contains the markers.
+pub const MARKERS2_DESCRIPTION: &str = "Serato Markers2";
+
+/// The description of the ID3 `GEOB` frame that contains the beatgrid.
+pub const BEATGRID_DESCRIPTION: &str = "Serato BeatGrid";
+
+/// Serato pads the ID3 tag data with null bytes to at least this length.
+const MARKERS2_MIN_LENGTH: usize = 470;
+
+/// The colour Serato uses for hotcues by default.
+pub const DEFAULT_CUE_COLOR: RgbColor = RgbColor(0xCC0000);
+
+/// The colour Serato uses for loops by default.
+const DEFAULT_LOOP_COLOR: u32 = 0x27AAE1;
+
+/// A single entry of the `Serato Markers2` tag.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Marker {
+    /// The track's colour.
+    Color(RgbColor),
+    /// A hotcue with its index, position in milliseconds, colour and name.
+    Cue {
+        index: u8,
+        position: u32,
+        color: RgbColor,
+        name: String,
+    },
+    /// A saved loop with its index, start and end in milliseconds and name.
+    Loop {
+        index: u8,
+        start: u32,
+        end: u32,
+        name: String,
+    },
+    /// Whether the beatgrid is locked.
+    BpmLock(bool),
+}
+
+impl Marker {
+    fn name(&self) -> &'static str {
+        match self {
+            Marker::Color(_) => "COLOR",
+            Marker::Cue { .. } => "CUE",
+            Marker::Loop { .. } => "LOOP",
+            Marker::BpmLock(_) => "BPMLOCK",
+        }
+    }
+
+    fn data(&self) -> Vec<u8> {
+        let mut data = Vec::new();
+        match self {
+            Marker::Color(color) => {
+                data.push(0);
+                data.extend([color.red(), color.green(), color.blue()]);
+            }
+            Marker::Cue {
+                index,
+                position,
+                color,
+                name,
+            } => {
+                data.extend([0, *index]);
+                data.extend(position.to_be_bytes());
+                data.push(0);
+                data.extend([color.red(), color.green(), color.blue()]);
+                data.extend([0, 0]);
+                data.extend(name.as_bytes());
+                data.push(0);
+            }
+            Marker::Loop {
+                index,
+                start,
+                end,
+                name,
+            } => {
+                data.extend([0, *index]);
+                data.extend(start.to_be_bytes());
+                data.extend(end.to_be_bytes());
+                data.extend([0xFF, 0xFF, 0xFF, 0xFF]);
+                // ARGB colour, followed by an unknown byte and the `locked` flag.
+                data.push(0);
+                data.extend(&DEFAULT_LOOP_COLOR.to_be_bytes()[1..]);
+                data.extend([0, 0]);
+                data.extend(name.as_bytes());
+                data.push(0);
+            }
+            Marker::BpmLock(locked) => data.push(u8::from(*locked)),
+        }
+
+        data
+    }
+}
+
+/// Encode the markers into the payload of the `Serato Markers2` ID3 `GEOB` frame.
+///
+/// The format is documented at <https://github.com/Holzhaus/serato-tags>.
+pub fn encode_markers2(markers: &[Marker]) -> Vec<u8> {
+    // The raw payload starts with a version and is terminated by a null byte.
+    let mut payload = vec![0x01, 0x01];
+    for marker in markers {
+        let data = marker.data();
+        payload.extend(marker.name().as_bytes());
+        payload.push(0);
+        payload.extend((data.len() as u32).to_be_bytes());
+        payload.extend(data);
+    }
+    payload.push(0);
+
+    // Serato omits the base64 padding and inserts a newline every 72 characters.
+    let encoded = STANDARD_NO_PAD.encode(payload);
+    let lines: Vec<&[u8]> = encoded.as_bytes().chunks(72).collect();
+
+    let mut data = vec![0x01, 0x01];
+    data.extend(lines.join(&b'\n'));
+    data.push(0);
+    if data.len() < MARKERS2_MIN_LENGTH {
+        data.resize(MARKERS2_MIN_LENGTH, 0);
+    }
+
+    data
+}
+
+/// A single marker of the `Serato BeatGrid` tag. Positions are in seconds.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum BeatGridMarker {
+    /// The start of a section with the given amount of beats until the next marker.
+    Beats { position: f32, beats: u32 },
+    /// The last marker, from which on the tempo stays constant.
+    Terminal { position: f32, bpm: f32 },
+}
+
+/// Encode the markers into the payload of the `Serato BeatGrid` ID3 `GEOB` frame.
+/// The last marker has to be the only [BeatGridMarker::Terminal] marker.
+///
+/// Unlike the `Serato Markers2` tag, the payload isn't base64 encoded.
+/// The format is documented at <https://github.com/Holzhaus/serato-tags>.
+pub fn encode_beatgrid(markers: &[BeatGridMarker]) -> Vec<u8> {
+    let mut data = vec![0x01, 0x00];
+    data.extend((markers.len() as u32).to_be_bytes());
+    for marker in markers {
+        match marker {
+            BeatGridMarker::Beats { position, beats } => {
+                data.extend(position.to_be_bytes());
+                data.extend(beats.to_be_bytes());
+            }
+            BeatGridMarker::Terminal { position, bpm } => {
+                data.extend(position.to_be_bytes());
+                data.extend(bpm.to_be_bytes());
+            }
+        }
+    }
+    // The meaning of the footer is unknown, Serato accepts a null byte.
+    data.push(0);
+
+    data
+}
+
+#[cfg(test)]
+mod tests {
+    use pretty_assertions::assert_eq;
+
+    use super::*;
+
+    #[test]
+    fn encode_cue() {
+        let data = encode_markers2(&[Marker::Cue {
+            index: 0,
+            position: 1000,
+            color: RgbColor(0xCC0000),
+            name: "Drop".into(),
+        }]);
+
+        assert_eq!(data.len(), MARKERS2_MIN_LENGTH);
+        assert_eq!(&data[..2], &[0x01, 0x01]);
+
+        // Decode the payload again and make sure it's what Serato expects.
+        let end = data.iter().position(|byte| *byte == 0).unwrap();
+        let encoded: Vec<u8> = data[2..end]
+            .iter()
+            .copied()
+            .filter(|byte| *byte != b'\n')
+            .collect();
+        let payload = STANDARD_NO_PAD.decode(encoded).unwrap();
+
+        let mut expected = vec![0x01, 0x01];
+        expected.extend(b"CUE\0");
+        expected.extend(17u32.to_be_bytes());
+        expected.extend([0, 0, 0, 0, 0x03, 0xE8, 0, 0xCC, 0, 0, 0, 0]);
+        expected.extend(b"Drop\0");
+        expected.push(0);
+        assert_eq!(payload, expected);
+    }
+
+    #[test]
+    fn encode_variable_beatgrid() {
+        let data = encode_beatgrid(&[
+            BeatGridMarker::Beats {
+                position: 0.5,
+                beats: 4,
+            },
+            BeatGridMarker::Terminal {
+                position: 2.5,
+                bpm: 128.0,
+            },
+        ]);
+
+        let mut expected = vec![0x01, 0x00, 0, 0, 0, 2];
+        expected.extend(0.5f32.to_be_bytes());
+        expected.extend(4u32.to_be_bytes());
+        expected.extend(2.5f32.to_be_bytes());
+        expected.extend(128f32.to_be_bytes());
+        expected.push(0);
+        assert_eq!(data, expected);
+    }
+}
@@ -0,0 +1,433 @@
+use std::{
+    fs::copy,
+    path::{Path, PathBuf},
+};
+
+use anyhow::{Context, Result};
+use id3::{
+    Tag,
+    TagLike,
+    Version,
+    frame::{Content, EncapsulatedObject, Frame},
+    no_tag_ok,
+};
+use log::{debug, info, warn};
+
+use super::serato::{
+    BEATGRID_DESCRIPTION,
+    BeatGridMarker,
+    DEFAULT_CUE_COLOR,
+    MARKERS2_DESCRIPTION,
+    Marker,
+    encode_beatgrid,
+    encode_markers2,
+};
+use crate::{
+    color::RgbColor,
+    key::translate_key,
+    mixxx::{
+        helper::{CUE_SAMPLE_RATE, convert_mixxx_position},
+        library::{Beats, Library, Track},
+    },
+};
+
+/// Options for the tag writer.
+#[derive(Debug, Clone, Copy)]
+pub struct TagWriterOptions {
+    /// Don't touch any files, only report what would be changed.
+    pub dry_run: bool,
+    /// Copy each file to `<file>.bak` before it's modified.
+    pub backup: bool,
+}
+
+/// What happened to a single file.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TagWriteResult {
+    Modified,
+    Unchanged,
+    Unsupported,
+}
+
+/// A report of all files that have been touched by the tag writer.
+#[derive(Debug, Default)]
+pub struct TagWriteReport {
+    pub modified: Vec<PathBuf>,
+    pub unchanged: Vec<PathBuf>,
+    pub unsupported: Vec<PathBuf>,
+    pub failed: Vec<(PathBuf, String)>,
+}
+
+/// Write the cues, loops, colour, BPM and key of all tracks into their audio files.
+///
+/// Only ID3v2 tags are supported for now, all other files are reported as unsupported.
+/// Cues are written as Serato Markers2 and the beats as Serato BeatGrid.
+/// Traktor's PRIV frames aren't written, as their binary format isn't documented.
+pub fn write_library_tags(library: &Library, options: TagWriterOptions) -> TagWriteReport {
+    let mut report = TagWriteReport::default();
+
+    for track in library.tracks.values() {
+        let path = PathBuf::from(&track.location.location);
+        match write_track_tags(track, &path, options) {
+            Ok(TagWriteResult::Modified) => report.modified.push(path),
+            Ok(TagWriteResult::Unchanged) => report.unchanged.push(path),
+            Ok(TagWriteResult::Unsupported) => report.unsupported.push(path),
+            Err(err) => {
+                warn!("Failed to write tags of track {}: {err:#}", track.id);
+                report.failed.push((path, format!("{err:#}")));
+            }
+        }
+    }
+
+    report
+}
+
+/// Write the tags of a single track into the file at `path`.
+pub fn write_track_tags(
+    track: &Track,
+    path: &Path,
+    options: TagWriterOptions,
+) -> Result<TagWriteResult> {
+    // Only MP3 files use ID3 tags.
+    let is_mp3 = path
+        .extension()
+        .is_some_and(|extension| extension.eq_ignore_ascii_case("mp3"));
+    if !is_mp3 {
+        debug!("Skipping unsupported file {path:?}");
+        return Ok(TagWriteResult::Unsupported);
+    }
+
+    let existing = no_tag_ok(Tag::read_from_path(path))
+        .context(format!("Failed to read ID3 tag of {path:?}"))?;
+    let has_tag = existing.is_some();
+    let existing = existing.unwrap_or_default();
+
+    let mut tag = existing.clone();
+    apply_track(&mut tag, track);
+
+    if tag == existing {
+        return Ok(TagWriteResult::Unchanged);
+    }
+
+    if options.dry_run {
+        info!("Would update tags of {path:?}");
+        return Ok(TagWriteResult::Modified);
+    }
+
+    if options.backup {
+        let mut backup_path = path.as_os_str().to_owned();
+        backup_path.push(".bak");
+        copy(path, &backup_path).context(format!("Failed to create backup of {path:?}"))?;
+    }
+
+    // Some players only read ID3v2.3, so files keep the version of their existing tag.
+    // ID3v2.2 can't be written anymore and files without tags get the most compatible version.
+    let version = match existing.version() {
+        Version::Id3v24 if has_tag => Version::Id3v24,
+        _ => Version::Id3v23,
+    };
+    tag.write_to_path(path, version)
+        .context(format!("Failed to write ID3 tag to {path:?}"))?;
+    info!("Updated tags of {path:?}");
+
+    Ok(TagWriteResult::Modified)
+}
+
+/// Set all frames of the tag that're managed by us.
+fn apply_track(tag: &mut Tag, track: &Track) {
+    let bpm = track.technical_info.bpm;
+    if bpm > 0.0 {
+        tag.set_text("TBPM", format!("{}", (bpm * 100.0).round() / 100.0));
+    }
+
+    // ID3 expects the musical key, e.g. `Am`.
+    // Rekordbox's notation sometimes contains alternative names, of which we take the first one.
+    let key = translate_key(&track.technical_info.key);
+    if let Some(key) = key.split('/').next().filter(|key| !key.is_empty()) {
+        tag.set_text("TKEY", key);
+    }
+
+    let markers = serato_markers(track);
+    tag.add_frame(Frame::with_content(
+        "GEOB",
+        Content::EncapsulatedObject(EncapsulatedObject {
+            mime_type: "application/octet-stream".into(),
+            filename: String::new(),
+            description: MARKERS2_DESCRIPTION.into(),
+            data: encode_markers2(&markers),
+        }),
+    ));
+
+    if let Some(markers) = serato_beatgrid(track) {
+        tag.add_frame(Frame::with_content(
+            "GEOB",
+            Content::EncapsulatedObject(EncapsulatedObject {
+                mime_type: "application/octet-stream".into(),
+                filename: String::new(),
+                description: BEATGRID_DESCRIPTION.into(),
+                data: encode_beatgrid(&markers),
+            }),
+        ));
+    }
+}
+
+/// Convert the track's beats into Serato beatgrid markers.
+/// Returns `None`, if the track hasn't been analysed or its beats can't be decoded.
+fn serato_beatgrid(track: &Track) -> Option<Vec<BeatGridMarker>> {
+    match track.technical_info.decode_beats() {
+        Ok(Some(Beats::Grid { bpm, first_beat })) => Some(vec![BeatGridMarker::Terminal {
+            position: first_beat as f32,
+            bpm: bpm as f32,
+        }]),
+        Ok(Some(Beats::Map(beats))) => beatmap_markers(&beats),
+        Ok(None) => None,
+        Err(err) => {
+            warn!("Failed to decode beats of track {}: {err:?}", track.id);
+            None
+        }
+    }
+}
+
+/// Convert the beats of a variable tempo track into beatgrid markers.
+///
+/// Consecutive beats with the same length are merged into a single marker, so only tempo
+/// changes result in a new marker. The tempo of the last section is kept until the end.
+fn beatmap_markers(beats: &[f64]) -> Option<Vec<BeatGridMarker>> {
+    // Beats whose lengths differ by less than this are considered to have the same tempo.
+    const TOLERANCE: f64 = 0.001;
+
+    if beats.len() < 2 {
+        return None;
+    }
+    let lengths: Vec<f64> = beats.windows(2).map(|pair| pair[1] - pair[0]).collect();
+
+    let mut markers = Vec::new();
+    let mut start = 0;
+    for (index, length) in lengths.iter().enumerate().skip(1) {
+        if (length - lengths[start]).abs() > TOLERANCE {
+            markers.push(BeatGridMarker::Beats {
+                position: beats[start] as f32,
+                beats: (index - start) as u32,
+            });
+            start = index;
+        }
+    }
+    markers.push(BeatGridMarker::Terminal {
+        position: beats[start] as f32,
+        bpm: (60.0 / lengths[start]) as f32,
+    });
+
+    Some(markers)
+}
+
+/// Convert the track's colour, hotcues and loops into Serato markers.
+fn serato_markers(track: &Track) -> Vec<Marker> {
+    let mut markers = Vec::new();
+
+    if let Some(color) = track.color {
+        markers.push(Marker::Color(color));
+    }
+
+    // Serato positions are in milliseconds.
+    let to_millis = |position: i64| -> u32 {
+        let seconds = convert_mixxx_position(position as f64, CUE_SAMPLE_RATE);
+        (seconds * 1000.0).round().max(0.0) as u32
+    };
+
+    for cue in &track.cues {
+        // Cues without hotcue number cannot be represented in Serato.
+        let Ok(index) = u8::try_from(cue.hotcue) else {
+            continue;
+        };
+        let color = RgbColor::from_cue_color(cue.color).unwrap_or(DEFAULT_CUE_COLOR);
+
+        match cue.cue_type {
+            // A normal hotcue.
+            1 => markers.push(Marker::Cue {
+                index,
+                position: to_millis(cue.position),
+                color,
+                name: cue.label.clone(),
+            }),
+            // A saved loop.
+            4 => markers.push(Marker::Loop {
+                index,
+                start: to_millis(cue.position),
+                end: to_millis(cue.position + cue.length),
+                name: cue.label.clone(),
+            }),
+            _ => continue,
+        }
+    }
+
+    markers.push(Marker::BpmLock(false));
+
+    markers
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs::{copy, read};
+
+    use anyhow::Result;
+    use pretty_assertions::assert_eq;
+    use tempfile::TempDir;
+
+    use super::*;
+    use crate::mixxx::{
+        aggregator::read_library,
+        fixture::{audio_fixture, sample_library},
+    };
+
+    /// The first track of the sample library, which has a colour, hotcues and a loop.
+    async fn sample_track() -> Result<Track> {
+        let mut fixture = sample_library().await?;
+        let mut library = read_library(&mut fixture.connection).await?;
+
+        Ok(library.tracks.remove(&1).expect("Sample track exists"))
+    }
+
+    fn copy_fixture(dir: &TempDir) -> Result<PathBuf> {
+        let path = dir.path().join("tagged.mp3");
+        copy(audio_fixture("tagged.mp3"), &path)?;
+        Ok(path)
+    }
+
+    #[tokio::test]
+    async fn write_tags_with_backup() -> Result<()> {
+        let track = sample_track().await?;
+        let dir = TempDir::new()?;
+        let path = copy_fixture(&dir)?;
+        let options = TagWriterOptions {
+            dry_run: false,
+            backup: true,
+        };
+
+        assert_eq!(
+            write_track_tags(&track, &path, options)?,
+            TagWriteResult::Modified
+        );
+
+        let tag = Tag::read_from_path(&path)?;
+        assert_eq!(tag.version(), Version::Id3v24);
+        // Tags that aren't managed by us are kept.
+        assert_eq!(tag.title(), Some("Tagged Title"));
+        assert_eq!(
+            tag.get("TBPM").and_then(|frame| frame.content().text()),
+            Some("128")
+        );
+        assert_eq!(
+            tag.get("TKEY").and_then(|frame| frame.content().text()),
+            Some("Am")
+        );
+        assert!(
+            tag.encapsulated_objects()
+                .any(|object| object.description == MARKERS2_DESCRIPTION)
+        );
+        // The sample track's grid starts at 0.05 seconds.
+        let beatgrid = tag
+            .encapsulated_objects()
+            .find(|object| object.description == BEATGRID_DESCRIPTION)
+            .expect("Beatgrid is written");
+        assert_eq!(
+            beatgrid.data,
+            encode_beatgrid(&[BeatGridMarker::Terminal {
+                position: 0.05,
+                bpm: 128.0
+            }])
+        );
+
+        let mut backup = path.as_os_str().to_owned();
+        backup.push(".bak");
+        assert_eq!(read(backup)?, read(audio_fixture("tagged.mp3"))?);
+
+        // Writing the same track again doesn't change anything.
+        assert_eq!(
+            write_track_tags(&track, &path, options)?,
+            TagWriteResult::Unchanged
+        );
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn dry_run_keeps_files() -> Result<()> {
+        let track = sample_track().await?;
+        let dir = TempDir::new()?;
+        let path = copy_fixture(&dir)?;
+        let options = TagWriterOptions {
+            dry_run: true,
+            backup: true,
+        };
+
+        assert_eq!(
+            write_track_tags(&track, &path, options)?,
+            TagWriteResult::Modified
+        );
+
+        assert_eq!(read(&path)?, read(audio_fixture("tagged.mp3"))?);
+        assert!(!dir.path().join("tagged.mp3.bak").exists());
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn keep_tag_version() -> Result<()> {
+        let track = sample_track().await?;
+        let dir = TempDir::new()?;
+        let path = copy_fixture(&dir)?;
+        Tag::read_from_path(&path)?.write_to_path(&path, Version::Id3v23)?;
+        let options = TagWriterOptions {
+            dry_run: false,
+            backup: false,
+        };
+
+        write_track_tags(&track, &path, options)?;
+
+        let tag = Tag::read_from_path(&path)?;
+        assert_eq!(tag.version(), Version::Id3v23);
+        assert_eq!(
+            tag.get("TBPM").and_then(|frame| frame.content().text()),
+            Some("128")
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn merge_beats_with_same_tempo() {
+        // Three beats at 120 BPM, followed by beats at 100 BPM.
+        let beats = [0.5, 1.0, 1.5, 2.0, 2.6, 3.2, 3.8];
+
+        assert_eq!(
+            beatmap_markers(&beats),
+            Some(vec![
+                BeatGridMarker::Beats {
+                    position: 0.5,
+                    beats: 3,
+                },
+                BeatGridMarker::Terminal {
+                    position: 2.0,
+                    bpm: 100.0,
+                },
+            ])
+        );
+        assert_eq!(beatmap_markers(&[1.0]), None);
+    }
+
+    #[tokio::test]
+    async fn default_cue_color() -> Result<()> {
+        let mut track = sample_track().await?;
+        track.cues[0].color = 0xFFFF_0000;
+
+        let colors: Vec<_> = serato_markers(&track)
+            .into_iter()
+            .filter_map(|marker| match marker {
+                Marker::Cue { color, .. } => Some(color),
+                _ => None,
+            })
+            .collect();
+        assert_eq!(colors, vec![DEFAULT_CUE_COLOR, RgbColor(0xC50A08)]);
+
+        Ok(())
+    }
+}
@@ -0,0 +1,409 @@
+use std::{
+    collections::BTreeMap,
+    fmt,
+    path::{Path, PathBuf},
+};
+
+use path_slash::PathBufExt;
+use rayon::prelude::*;
+use serde_derive::{Deserialize, Serialize};
+
+use crate::{diff::TrackSummary, export::Profile, mixxx::library::Library};
+
+/// Windows doesn't support longer paths by default (`MAX_PATH` without the trailing null).
+const MAX_PATH_LENGTH: usize = 259;
+/// The maximum length of a single file or directory name in UTF-16 code units.
+const MAX_NAME_LENGTH: usize = 255;
+/// FAT32 can't store files of 4 GiB or larger.
+const MAX_FAT32_FILE_SIZE: u64 = u32::MAX as u64;
+/// Characters that aren't allowed in file names by Windows, FAT32 and exFAT.
+pub(crate) const INVALID_CHARACTERS: [char; 9] = ['<', '>', ':', '"', '/', '\\', '|', '?', '*'];
+/// Names that Windows reserves for devices, even with an extension such as `CON.mp3`.
+pub(crate) const RESERVED_NAMES: [&str; 22] = [
+    "CON", "PRN", "AUX", "NUL", "COM1", "COM2", "COM3", "COM4", "COM5", "COM6", "COM7", "COM8",
+    "COM9", "LPT1", "LPT2", "LPT3", "LPT4", "LPT5", "LPT6", "LPT7", "LPT8", "LPT9",
+];
+
+/// The file system of the target library root, which decides which paths are valid.
+///
+/// All of them are case insensitive, so paths that only differ in case point to the same file.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[serde(rename_all = "snake_case")]
+pub enum TargetFilesystem {
+    /// NTFS on Windows.
+    Windows,
+    /// Most USB sticks and the only file system that's supported by older CDJs.
+    Fat32,
+    Exfat,
+}
+
+/// All problems that were found in a library.
+#[derive(Serialize, Debug, Default, Clone, PartialEq)]
+pub struct ValidationReport {
+    /// Tracks whose file doesn't exist anymore.
+    pub missing_files: Vec<TrackPath>,
+    /// The problems of the mapped paths, per profile.
+    pub profiles: Vec<ProfileReport>,
+}
+
+/// The problems of the paths, as they're written by a single profile.
+#[derive(Serialize, Debug, Clone, PartialEq)]
+pub struct ProfileReport {
+    pub profile: String,
+    /// The file system against which the paths were checked.
+    /// Only the source library root is checked, if this isn't known.
+    pub filesystem: Option<TargetFilesystem>,
+    pub invalid_paths: Vec<InvalidPath>,
+    /// Groups of tracks, whose paths only differ in case.
+    pub case_collisions: Vec<Vec<TrackPath>>,
+}
+
+#[derive(Serialize, Debug, Clone, PartialEq)]
+pub struct TrackPath {
+    pub track: TrackSummary,
+    pub path: String,
+}
+
+#[derive(Serialize, Debug, Clone, PartialEq)]
+pub struct InvalidPath {
+    pub track: TrackSummary,
+    pub path: String,
+    pub problems: Vec<PathProblem>,
+}
+
+#[derive(Serialize, Debug, Clone, PartialEq)]
+#[serde(tag = "problem", rename_all = "snake_case")]
+pub enum PathProblem {
+    /// The track isn't located inside the `source_library_root`, so it cannot be mapped.
+    OutsideSourceRoot,
+    InvalidCharacter {
+        name: String,
+        character: char,
+    },
+    ReservedName {
+        name: String,
+    },
+    /// Windows silently removes trailing dots and spaces.
+    TrailingDotOrSpace {
+        name: String,
+    },
+    NameTooLong {
+        name: String,
+    },
+    PathTooLong {
+        length: usize,
+    },
+    FileTooLarge {
+        size: u64,
+    },
+}
+
+impl ValidationReport {
+    pub fn problems(&self) -> usize {
+        self.missing_files.len()
+            + self
+                .profiles
+                .iter()
+                .map(|profile| profile.invalid_paths.len() + profile.case_collisions.len())
+                .sum::<usize>()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.problems() == 0
+    }
+}
+
+/// Check that all files exist and that their mapped paths are valid for each profile.
+/// Tracks that've been deleted in Mixxx are ignored.
+pub fn validate_library(library: &Library, profiles: &[Profile]) -> ValidationReport {
+    ValidationReport {
+        missing_files: find_missing_files(library),
+        profiles: profiles
+            .iter()
+            .map(|profile| check_target_paths(library, profile))
+            .collect(),
+    }
+}
+
+/// All tracks whose file has vanished from disk.
+pub fn find_missing_files(library: &Library) -> Vec<TrackPath> {
+    library
+        .tracks
+        .par_iter()
+        .filter(|(_, track)| !track.metadata.deleted)
+        .filter(|(_, track)| !Path::new(&track.location.location).exists())
+        .map(|(_, track)| TrackPath {
+            track: TrackSummary::from(track),
+            path: track.location.location.clone(),
+        })
+        .collect()
+}
+
+/// Check the paths, as they're exported by the profile, against its target file system.
+pub fn check_target_paths(library: &Library, profile: &Profile) -> ProfileReport {
+    let filesystem = profile.target_filesystem;
+    let source_root = PathBuf::from_slash(&profile.source_library_root);
+
+    let mut invalid_paths = Vec::new();
+    let mut paths_by_case: BTreeMap<String, Vec<TrackPath>> = BTreeMap::new();
+    for track in library.tracks.values() {
+        if track.metadata.deleted {
+            continue;
+        }
+
+        let location = PathBuf::from_slash(&track.location.location);
+        let (Ok(path), Ok(relative_path)) = (
+            profile.map_location(&track.location.location),
+            location.strip_prefix(&source_root),
+        ) else {
+            invalid_paths.push(InvalidPath {
+                track: TrackSummary::from(track),
+                path: track.location.location.clone(),
+                problems: vec![PathProblem::OutsideSourceRoot],
+            });
+            continue;
+        };
+        let path = path.to_slash_lossy().into_owned();
+
+        let Some(filesystem) = filesystem else {
+            continue;
+        };
+
+        // Only the part below the library root is ours, the root itself is the user's choice.
+        let mut problems: Vec<_> = relative_path
+            .iter()
+            .flat_map(|name| check_name(&name.to_string_lossy()))
+            .collect();
+        let length = path.encode_utf16().count();
+        if length > MAX_PATH_LENGTH {
+            problems.push(PathProblem::PathTooLong { length });
+        }
+        if filesystem == TargetFilesystem::Fat32
+            && let Some(size) = track.location.filesize
+            && size > MAX_FAT32_FILE_SIZE
+        {
+            problems.push(PathProblem::FileTooLarge { size });
+        }
+
+        let track_path = TrackPath {
+            track: TrackSummary::from(track),
+            path: path.clone(),
+        };
+        if !problems.is_empty() {
+            invalid_paths.push(InvalidPath {
+                track: track_path.track.clone(),
+                path,
+                problems,
+            });
+        }
+        paths_by_case
+            .entry(track_path.path.to_lowercase())
+            .or_default()
+            .push(track_path);
+    }
+
+    ProfileReport {
+        profile: profile.name.clone(),
+        filesystem,
+        invalid_paths,
+        case_collisions: paths_by_case
+            .into_values()
+            .filter(|paths| paths.len() > 1)
+            .collect(),
+    }
+}
+
+/// The problems of a single file or directory name.
+/// Windows, FAT32 and exFAT share the same rules for names.
+fn check_name(name: &str) -> Vec<PathProblem> {
+    let mut problems = Vec::new();
+
+    if let Some(character) = name
+        .chars()
+        .find(|c| INVALID_CHARACTERS.contains(c) || c.is_ascii_control())
+    {
+        problems.push(PathProblem::InvalidCharacter {
+            name: name.to_string(),
+            character,
+        });
+    }
+
+    let stem = name.split('.').next().unwrap_or_default().trim_end();
+    if RESERVED_NAMES
+        .iter()
+        .any(|reserved| reserved.eq_ignore_ascii_case(stem))
+    {
+        problems.push(PathProblem::ReservedName {
+            name: name.to_string(),
+        });
+    }
+
+    if name.ends_with('.') || name.ends_with(' ') {
+        problems.push(PathProblem::TrailingDotOrSpace {
+            name: name.to_string(),
+        });
+    }
+
+    if name.encode_utf16().count() > MAX_NAME_LENGTH {
+        problems.push(PathProblem::NameTooLong {
+            name: name.to_string(),
+        });
+    }
+
+    problems
+}
+
+impl fmt::Display for TargetFilesystem {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            TargetFilesystem::Windows => write!(f, "Windows"),
+            TargetFilesystem::Fat32 => write!(f, "FAT32"),
+            TargetFilesystem::Exfat => write!(f, "exFAT"),
+        }
+    }
+}
+
+impl fmt::Display for PathProblem {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            PathProblem::OutsideSourceRoot => write!(f, "not inside the source_library_root"),
+            PathProblem::InvalidCharacter { name, character } => {
+                write!(f, "invalid character {character:?} in '{name}'")
+            }
+            PathProblem::ReservedName { name } => write!(f, "reserved name '{name}'"),
+            PathProblem::TrailingDotOrSpace { name } => {
+                write!(f, "'{name}' ends with a dot or space")
+            }
+            PathProblem::NameTooLong { name } => {
+                write!(f, "'{name}' is longer than {MAX_NAME_LENGTH} characters")
+            }
+            PathProblem::PathTooLong { length } => write!(
+                f,
+                "the path has {length} characters, at most {MAX_PATH_LENGTH} are supported"
+            ),
+            PathProblem::FileTooLarge { size } => {
+                write!(f, "the file has {size} bytes, which is too large for FAT32")
+            }
+        }
+    }
+}
+
+impl fmt::Display for ValidationReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "Missing files: {}", self.missing_files.len())?;
+        for missing in &self.missing_files {
+            writeln!(f, "  {}: {}", missing.track, missing.path)?;
+        }
+
+        for profile in &self.profiles {
+            match profile.filesystem {
+                Some(filesystem) => writeln!(f, "Profile '{}' ({filesystem}):", profile.profile)?,
+                None => writeln!(f, "Profile '{}':", profile.profile)?,
+            }
+            writeln!(f, "  Invalid paths: {}", profile.invalid_paths.len())?;
+            for invalid in &profile.invalid_paths {
+                writeln!(f, "    {}: {}", invalid.track, invalid.path)?;
+                for problem in &invalid.problems {
+                    writeln!(f, "      {problem}")?;
+                }
+            }
+            writeln!(
+                f,
+                "  Paths that only differ in case: {}",
+                profile.case_collisions.len()
+            )?;
+            for collision in &profile.case_collisions {
+                for track_path in collision {
+                    writeln!(f, "    {}: {}", track_path.track, track_path.path)?;
+                }
+            }
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use anyhow::Result;
+    use pretty_assertions::assert_eq;
+    use rstest::rstest;
+
+    use super::*;
+    use crate::mixxx::{aggregator::read_library, fixture::sample_library};
+
+    #[rstest]
+    #[case("Artist - Title.mp3", vec![])]
+    #[case("Live: Berlin", vec![PathProblem::InvalidCharacter { name: "Live: Berlin".into(), character: ':' }])]
+    #[case("What?.flac", vec![PathProblem::InvalidCharacter { name: "What?.flac".into(), character: '?' }])]
+    #[case("con.mp3", vec![PathProblem::ReservedName { name: "con.mp3".into() }])]
+    #[case("Console.mp3", vec![])]
+    #[case("Vol. 2.", vec![PathProblem::TrailingDotOrSpace { name: "Vol. 2.".into() }])]
+    fn check_names(#[case] name: &str, #[case] expected: Vec<PathProblem>) {
+        assert_eq!(check_name(name), expected);
+    }
+
+    #[test]
+    fn reject_long_names() {
+        // Each of these characters is a surrogate pair in UTF-16.
+        let name = "🎵".repeat(128);
+        assert_eq!(
+            check_name(&name),
+            vec![PathProblem::NameTooLong { name: name.clone() }]
+        );
+    }
+
+    #[tokio::test]
+    async fn validate_sample_library() -> Result<()> {
+        let mut fixture = sample_library().await?;
+        let mut library = read_library(&mut fixture.connection).await?;
+        let mut profile = fixture.config().default_profile("rekordbox");
+
+        // Add a track that only differs in case from the first track.
+        let mut copy = library.tracks[&1].clone();
+        copy.id = 4;
+        copy.location.location = copy.location.location.replace("First Track", "FIRST TRACK");
+        library.tracks.insert(4, copy);
+        // And one that's outside of the library root.
+        let mut outside = library.tracks[&2].clone();
+        outside.id = 5;
+        outside.location.location = "/tmp/outside.mp3".into();
+        library.tracks.insert(5, outside);
+
+        // Without a file system, only the source root is checked.
+        let report = validate_library(&library, std::slice::from_ref(&profile));
+        // None of the fixture's files exist.
+        assert_eq!(report.missing_files.len(), 5);
+        let invalid: Vec<_> = report.profiles[0]
+            .invalid_paths
+            .iter()
+            .map(|invalid| (invalid.track.id, invalid.problems.clone()))
+            .collect();
+        assert_eq!(invalid, vec![(5, vec![PathProblem::OutsideSourceRoot])]);
+        assert!(report.profiles[0].case_collisions.is_empty());
+
+        profile.target_filesystem = Some(TargetFilesystem::Fat32);
+        let report = check_target_paths(&library, &profile);
+        let invalid: Vec<_> = report
+            .invalid_paths
+            .iter()
+            .map(|invalid| (invalid.track.id, invalid.problems.len()))
+            .collect();
+        assert_eq!(invalid, vec![(3, 1), (5, 1)]);
+        assert_eq!(
+            report.invalid_paths[0].problems,
+            vec![PathProblem::InvalidCharacter {
+                name: "Quotes \"<Unanalyzed>\".mp3".into(),
+                character: '"'
+            }]
+        );
+        let collisions: Vec<Vec<_>> = report
+            .case_collisions
+            .iter()
+            .map(|paths| paths.iter().map(|path| path.track.id).collect())
+            .collect();
+        assert_eq!(collisions, vec![vec![1, 4]]);
+
+        Ok(())
+    }
+}
@@ -0,0 +1,139 @@
+#!/usr/bin/env python3
+"""Generates the tiny, silent audio fixtures that are used to test the tag reader.
+
+The fixtures are written next to this script, regardless of the working directory.
+"""
+
+import struct
+from pathlib import Path
+
+OUT = Path(__file__).parent
+
+SAMPLE_RATE = 44100
+
+
+def syncsafe(n):
+    """Encode an integer as ID3v2 syncsafe integer, which only uses 7 bits per byte."""
+    return bytes([(n >> 21) & 0x7F, (n >> 14) & 0x7F, (n >> 7) & 0x7F, n & 0x7F])
+
+
+def text_frame(frame_id, text):
+    """An ID3v2.4 text frame with UTF-8 encoding."""
+    data = b"\x03" + text.encode("utf-8")
+    return frame_id.encode() + syncsafe(len(data)) + b"\x00\x00" + data
+
+
+def mp3():
+    frames = b"".join(
+        [
+            text_frame("TIT2", "Tagged Title"),
+            text_frame("TPE1", "Tagged Artist"),
+            text_frame("TALB", "Tagged Album"),
+            text_frame("TDRC", "2021"),
+            text_frame("TRCK", "7/12"),
+            text_frame("TCOM", "Tagged Composer"),
+            text_frame("TCON", "House"),
+            text_frame("TIT1", "Tagged Grouping"),
+            text_frame("TPUB", "Tagged Label"),
+            text_frame("TPE4", "Tagged Remixer"),
+            text_frame("TPOS", "2/2"),
+        ]
+    )
+    id3 = b"ID3\x04\x00\x00" + syncsafe(len(frames)) + frames
+
+    # MPEG-1 Layer III, 128kbps, 44.1kHz, no padding, no CRC, joint stereo
+    header = bytes([0xFF, 0xFB, 0x90, 0x64])
+    frame_length = 144 * 128000 // SAMPLE_RATE
+    mp3_frame = header + bytes(frame_length - 4)
+
+    return id3 + mp3_frame * 4
+
+
+def vorbis_comment(comments):
+    vendor = b"fixture"
+    data = struct.pack("<I", len(vendor)) + vendor + struct.pack("<I", len(comments))
+    for comment in comments:
+        comment = comment.encode("utf-8")
+        data += struct.pack("<I", len(comment)) + comment
+    return data
+
+
+def crc8(data):
+    crc = 0
+    for byte in data:
+        crc ^= byte
+        for _ in range(8):
+            if crc & 0x80:
+                crc = ((crc << 1) ^ 0x07) & 0xFF
+            else:
+                crc = (crc << 1) & 0xFF
+    return crc
+
+
+def crc16(data):
+    crc = 0
+    for byte in data:
+        crc ^= byte << 8
+        for _ in range(8):
+            if crc & 0x8000:
+                crc = ((crc << 1) ^ 0x8005) & 0xFFFF
+            else:
+                crc = (crc << 1) & 0xFFFF
+    return crc
+
+
+def flac_frame(number):
+    header = bytes([0xFF, 0xF8, 0x19, 0x18, number])
+    header += bytes([crc8(header)])
+    # Two CONSTANT subframes with a 16 bit sample value of 0.
+    frame = header + bytes([0x00, 0x00, 0x00]) * 2
+    return frame + crc16(frame).to_bytes(2, "big")
+
+
+def flac():
+    # STREAMINFO: min/max block size, min/max frame size, sample rate (20 bits),
+    # channels - 1 (3 bits), bits per sample - 1 (5 bits), total samples (36 bits), md5
+    channels = 2
+    bits_per_sample = 16
+    total_samples = 0
+    stream_info = struct.pack(">HH", 4096, 4096) + b"\x00\x00\x00" + b"\x00\x00\x00"
+    packed = (
+        (SAMPLE_RATE << 44)
+        | ((channels - 1) << 41)
+        | ((bits_per_sample - 1) << 36)
+        | total_samples
+    )
+    stream_info += packed.to_bytes(8, "big") + bytes(16)
+
+    comments = vorbis_comment(
+        [
+            "TITLE=Tagged Title",
+            "ARTIST=Tagged Artist",
+            "ALBUM=Tagged Album",
+            "DATE=2021",
+            "TRACKNUMBER=7",
+            "COMPOSER=Tagged Composer",
+            "GENRE=House",
+            "GROUPING=Tagged Grouping",
+            "LABEL=Tagged Label",
+            "REMIXER=Tagged Remixer",
+            "DISCNUMBER=2",
+        ]
+    )
+
+    # STREAMINFO comes first, VORBIS_COMMENT is marked as the last metadata block.
+    metadata = (
+        bytes([0x00])
+        + len(stream_info).to_bytes(3, "big")
+        + stream_info
+        + bytes([0x84])
+        + len(comments).to_bytes(3, "big")
+        + comments
+    )
+
+    return b"fLaC" + metadata + flac_frame(0) + flac_frame(1) + flac_frame(2)
+
+
+if __name__ == "__main__":
+    (OUT / "tagged.mp3").write_bytes(mp3())
+    (OUT / "tagged.flac").write_bytes(flac())
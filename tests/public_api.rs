@@ -0,0 +1,212 @@
+//! Uses the exporter the way other tools embed it, i.e. only through its public API.
+
+use std::{collections::BTreeMap, fs::create_dir, time::Duration};
+
+use anyhow::Result;
+use chrono::NaiveDate;
+use mixxx_library_exporter::{
+    Beats,
+    CoverArt,
+    CoverArtSource,
+    CoverArtType,
+    Crate,
+    Cue,
+    DbAccess,
+    Library,
+    Playlist,
+    Registry,
+    RgbColor,
+    Track,
+    TrackLocation,
+    TrackMetadata,
+    TrackTechnicalInfo,
+    beats,
+    fixture::{CueFixture, LibraryBuilder, MixxxFixture, Schema, TrackFixture, write_config},
+    new_connection,
+    read_library,
+    rekordbox,
+    translate_key,
+    write_rekordbox_library,
+};
+use pretty_assertions::assert_eq;
+use prost::Message;
+use tempfile::TempDir;
+
+/// A Mixxx database with a single track in a playlist.
+async fn sample_database() -> Result<MixxxFixture> {
+    LibraryBuilder::new()
+        .track(TrackFixture {
+            bpm: 128.0,
+            key: "8A".into(),
+            cues: vec![CueFixture::hotcue(0, 1.0)],
+            ..TrackFixture::new(1, "Artist", "Title")
+        })
+        .playlist("Warmup", &[1])
+        .build(Schema::Latest)
+        .await
+}
+
+#[tokio::test]
+async fn export_rekordbox_library() -> Result<()> {
+    let fixture = sample_database().await?;
+    let config = fixture.config();
+    let db_path = fixture.path.to_string_lossy().to_string();
+
+    let mut db = new_connection(&db_path, DbAccess::Snapshot, Duration::from_secs(1)).await?;
+    let library = read_library(&mut db.connection).await?;
+    assert_eq!(library.tracks.len(), 1);
+    assert_eq!(library.playlists[0].track_ids, vec![1]);
+
+    let xml = String::from_utf8(write_rekordbox_library(&config, library, Vec::new())?)?;
+    assert!(xml.contains(r#"Location="file://localhost/C:/Music/Artist/Title.mp3""#));
+    assert!(xml.contains(r#"<POSITION_MARK Name="" Type="0" Start="1.000" Num="0""#));
+    assert!(xml.contains(r#"<NODE Type="1" Name="Warmup" KeyType="0" Entries="1">"#));
+
+    // The key translation is still available at its previous path.
+    assert_eq!(rekordbox::schema::tracks::translate_key("8A"), "Am");
+    assert_eq!(translate_key("8A"), "Am");
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn export_with_registry() -> Result<()> {
+    let fixture = sample_database().await?;
+    let config = fixture.config();
+    let db_path = fixture.path.to_string_lossy().to_string();
+
+    let registry = Registry::default();
+    let library = registry
+        .importer("mixxx")?
+        .import(&db_path, &config)
+        .await?;
+
+    let profile = config.default_profile("json");
+    create_dir(&profile.target_directory)?;
+    let report = registry.exporter("json")?.export(&library, &profile)?;
+    assert_eq!(report.tracks, 1);
+    assert_eq!(
+        report.files,
+        vec![profile.target_directory.join("mixxx_library.json")]
+    );
+    assert!(report.files[0].exists());
+
+    Ok(())
+}
+
+/// Build a library by hand, e.g. from another DJ software, without a Mixxx database.
+fn build_library() -> Library {
+    let added = NaiveDate::from_ymd_opt(2023, 1, 2)
+        .and_then(|date| date.and_hms_opt(10, 0, 0))
+        .expect("Valid date");
+    let grid = beats::BeatGrid {
+        bpm: Some(beats::Bpm {
+            bpm: Some(128.0),
+            source: Some(beats::Source::User as i32),
+        }),
+        first_beat: Some(beats::Beat {
+            frame_position: Some(0),
+            ..Default::default()
+        }),
+    };
+
+    let track = Track {
+        id: 1,
+        artist: "Artist".into(),
+        composer: String::new(),
+        title: "Title".into(),
+        album: None,
+        year: "2023".into(),
+        genre: None,
+        grouping: None,
+        tracknumber: None,
+        tracktotal: None,
+        disc_number: None,
+        label: None,
+        remixer: None,
+        filetype: "mp3".into(),
+        comment: None,
+        url: None,
+        color: Some(RgbColor(0xFF0000)),
+        location: TrackLocation {
+            location: "/music/Artist/Title.mp3".into(),
+            filename: "Title.mp3".into(),
+            directory: "/music/Artist".into(),
+            filesize: Some(1000),
+        },
+        cover_art: CoverArt {
+            source: CoverArtSource::Unknown,
+            cover_type: CoverArtType::None,
+            location: None,
+            hash: 0,
+            exported: None,
+        },
+        technical_info: TrackTechnicalInfo {
+            duration: 300.0,
+            bitrate: 320,
+            samplerate: 44100,
+            bpm: 128.0,
+            beats: Some(grid.encode_to_vec()),
+            beats_version: Some("BeatGrid-2.0".into()),
+            key: "8A".into(),
+            replaygain: 0.0,
+            replaygain_peak: 0.0,
+        },
+        metadata: TrackMetadata {
+            rating: 0,
+            played: false,
+            timesplayed: 0,
+            deleted: false,
+            datetime_added: added,
+        },
+        cues: vec![Cue {
+            id: 1,
+            track_id: 1,
+            cue_type: 1,
+            position: 88200,
+            length: 0,
+            hotcue: 0,
+            label: String::new(),
+            color: 0xFF0000,
+        }],
+    };
+
+    Library {
+        tracks: BTreeMap::from([(1, track)]),
+        playlists: vec![Playlist {
+            id: 1,
+            name: "Warmup".into(),
+            position: 1,
+            hidden: false,
+            date_created: added,
+            date_modified: added,
+            track_ids: vec![1],
+        }],
+        crates: Vec::<Crate>::new(),
+    }
+}
+
+#[test]
+fn export_library_built_by_hand() -> Result<()> {
+    let dir = TempDir::new()?;
+    let config = write_config(dir.path(), &dir.path().join("mixxxdb.sqlite"), "");
+
+    let library = build_library();
+    assert_eq!(
+        library.tracks[&1].technical_info.decode_beats()?,
+        Some(Beats::Grid {
+            bpm: 128.0,
+            first_beat: 0.0
+        })
+    );
+
+    let xml = String::from_utf8(write_rekordbox_library(&config, library, Vec::new())?)?;
+    assert!(xml.contains(r#"Location="file://localhost/C:/Music/Artist/Title.mp3""#));
+    assert!(xml.contains(r#"Colour="0xFF0000""#));
+    assert!(xml.contains(r#"<POSITION_MARK Name="" Type="0" Start="1.000" Num="0""#));
+
+    // All public types can be inspected, e.g. in logs.
+    assert!(format!("{:?}", Registry::default()).contains("rekordbox"));
+
+    Ok(())
+}